@@ -4,8 +4,10 @@ use muc::fmt::{FmtMode, parse_and_format_mode};
 use muc::lexer::{TokenKind, tokenize};
 use muc::parser::{ParseError, parse_str};
 use muc::typecheck::{TypeError, check_program};
-use muc::vm::{self, VmError, VmHost};
+use muc::vfs::{VfsHost, VirtualFs};
+use muc::vm::{self, HttpRequest, HttpResponse, ProcOutput, ProcRequest, VmError, VmHost};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 const MAX_OUTPUT_BYTES: usize = 64 * 1024;
@@ -41,6 +43,7 @@ struct RunResponse {
     fuel_used: u64,
     trapped: bool,
     trap_code: Option<String>,
+    files: BTreeMap<String, String>,
 }
 
 #[wasm_bindgen]
@@ -72,8 +75,8 @@ pub fn stats(src: String, mode: String) -> JsValue {
 }
 
 #[wasm_bindgen]
-pub fn run(src: String, fuel: u32, stdin: Option<String>) -> JsValue {
-    let response = run_impl(&src, u64::from(fuel), stdin);
+pub fn run(src: String, fuel: u32, stdin: Option<String>, files: Option<String>) -> JsValue {
+    let response = run_impl(&src, u64::from(fuel), stdin, files);
     to_js_value(&response)
 }
 
@@ -143,7 +146,23 @@ fn stats_impl(src: &str, mode: FmtMode) -> StatsResponse {
     }
 }
 
-fn run_impl(src: &str, fuel: u64, stdin: Option<String>) -> RunResponse {
+fn run_impl(src: &str, fuel: u64, stdin: Option<String>, files: Option<String>) -> RunResponse {
+    let vfs = match files.as_deref() {
+        Some(manifest) => match VirtualFs::from_json_manifest(manifest) {
+            Ok(vfs) => vfs,
+            Err(err) => {
+                return trapped_response(
+                    1,
+                    String::new(),
+                    format!("E_VFS_MANIFEST: {err}"),
+                    0,
+                    Some("E_VFS_MANIFEST".to_string()),
+                );
+            }
+        },
+        None => VirtualFs::new(),
+    };
+
     let program = match parse_str(src) {
         Ok(program) => program,
         Err(err) => {
@@ -204,11 +223,12 @@ fn run_impl(src: &str, fuel: u64, stdin: Option<String>) -> RunResponse {
         }
     };
 
-    let mut host = WebHost::new(stdin, MAX_OUTPUT_BYTES);
+    let mut host = VfsHost::new(WebHost::new(stdin, MAX_OUTPUT_BYTES), vfs);
     let fuel_limit = if fuel == 0 { 1 } else { fuel };
     let result = vm::run_bytecode_with_fuel_and_host(&bytecode, &[], fuel_limit, &mut host);
     // The current VM API does not expose consumed fuel; keep this field stable.
     let fuel_used = 0;
+    let VfsHost { inner: host, vfs } = host;
 
     match result {
         Ok(()) => RunResponse {
@@ -218,6 +238,7 @@ fn run_impl(src: &str, fuel: u64, stdin: Option<String>) -> RunResponse {
             fuel_used,
             trapped: false,
             trap_code: None,
+            files: vfs.into_snapshot(),
         },
         Err(err) => {
             let mut stderr = host.stderr;
@@ -232,7 +253,10 @@ fn run_impl(src: &str, fuel: u64, stdin: Option<String>) -> RunResponse {
             }
 
             let exit_code = parse_exit_code(&err.message).unwrap_or(1);
-            trapped_response(exit_code, host.stdout, stderr, fuel_used, trap_code)
+            let mut response =
+                trapped_response(exit_code, host.stdout, stderr, fuel_used, trap_code);
+            response.files = vfs.into_snapshot();
+            response
         }
     }
 }
@@ -281,12 +305,12 @@ fn validate_web_effects(src: &str, program: &muc::ast::Program) -> Vec<ErrorItem
     for decl in &program.module.decls {
         if let Decl::Import(import_decl) = decl {
             let module_name = import_decl.module.parts.join(".");
-            if matches!(module_name.as_str(), "core.fs" | "core.proc" | "core.http") {
+            if matches!(module_name.as_str(), "core.proc" | "core.http") {
                 let (line, col) = line_col(src, import_decl.span);
                 errors.push(ErrorItem {
                     code: "E_WEB_EFFECT".to_string(),
                     msg: format!(
-                        "import `{module_name}` is not allowed in browser mode (only !{{io}} is supported)"
+                        "import `{module_name}` is not allowed in browser mode (only !{{io,fs}} are supported)"
                     ),
                     line,
                     col,
@@ -323,7 +347,7 @@ fn collect_disallowed_effects(
     errors: &mut Vec<ErrorItem>,
 ) {
     for atom in &effects.atoms {
-        if !matches!(atom, EffectAtom::Io) {
+        if !matches!(atom, EffectAtom::Io | EffectAtom::Fs) {
             let (line, col) = line_col(src, span);
             errors.push(ErrorItem {
                 code: "E_WEB_EFFECT".to_string(),
@@ -346,10 +370,16 @@ fn collect_disallowed_lambda_effects(src: &str, expr: &Expr, errors: &mut Vec<Er
             }
             collect_disallowed_lambda_effects(src, tail, errors);
         }
-        Expr::Let { value, body, .. } => {
+        Expr::Let { value, body, .. } | Expr::LetPattern { value, body, .. } => {
             collect_disallowed_lambda_effects(src, value, errors);
             collect_disallowed_lambda_effects(src, body, errors);
         }
+        Expr::LetRec { bindings, body, .. } => {
+            for binding in bindings {
+                collect_disallowed_lambda_effects(src, &binding.value, errors);
+            }
+            collect_disallowed_lambda_effects(src, body, errors);
+        }
         Expr::If {
             cond,
             then_branch,
@@ -365,6 +395,9 @@ fn collect_disallowed_lambda_effects(src: &str, expr: &Expr, errors: &mut Vec<Er
         } => {
             collect_disallowed_lambda_effects(src, scrutinee, errors);
             for arm in arms {
+                if let Some(guard) = &arm.guard {
+                    collect_disallowed_lambda_effects(src, guard, errors);
+                }
                 collect_disallowed_lambda_effects(src, &arm.expr, errors);
             }
         }
@@ -391,10 +424,23 @@ fn collect_disallowed_lambda_effects(src: &str, expr: &Expr, errors: &mut Vec<Er
         }
         Expr::Require { expr, .. }
         | Expr::Ensure { expr, .. }
-        | Expr::Paren { inner: expr, .. } => {
+        | Expr::Paren { inner: expr, .. }
+        | Expr::Try { inner: expr, .. }
+        | Expr::Field { base: expr, .. } => {
             collect_disallowed_lambda_effects(src, expr, errors);
         }
-        Expr::NameApp { args, .. } => {
+        Expr::Record { fields, .. } => {
+            for init in fields {
+                collect_disallowed_lambda_effects(src, &init.value, errors);
+            }
+        }
+        Expr::Update { base, fields, .. } => {
+            collect_disallowed_lambda_effects(src, base, errors);
+            for init in fields {
+                collect_disallowed_lambda_effects(src, &init.value, errors);
+            }
+        }
+        Expr::NameApp { args, .. } | Expr::Tuple { items: args, .. } => {
             for arg in args {
                 collect_disallowed_lambda_effects(src, arg, errors);
             }
@@ -490,6 +536,7 @@ fn trapped_response(
         fuel_used,
         trapped: true,
         trap_code,
+        files: BTreeMap::new(),
     }
 }
 
@@ -572,25 +619,53 @@ impl VmHost for WebHost {
     }
 
     fn fs_read_to_string(&mut self, _path: &str) -> Result<String, String> {
-        Err("web sandbox: fs.read disabled".to_string())
+        Err("web sandbox: fs.read requires a virtual filesystem".to_string())
     }
 
     fn fs_write_string(&mut self, _path: &str, _data: &str) -> Result<(), String> {
-        Err("web sandbox: fs.write disabled".to_string())
+        Err("web sandbox: fs.write requires a virtual filesystem".to_string())
+    }
+
+    fn fs_append_string(&mut self, _path: &str, _data: &str) -> Result<(), String> {
+        Err("web sandbox: fs.append requires a virtual filesystem".to_string())
+    }
+
+    fn fs_list_dir(&mut self, _path: &str) -> Result<Vec<String>, String> {
+        Err("web sandbox: fs.list_dir requires a virtual filesystem".to_string())
+    }
+
+    fn fs_exists(&mut self, _path: &str) -> Result<bool, String> {
+        Err("web sandbox: fs.exists requires a virtual filesystem".to_string())
+    }
+
+    fn fs_create_dir(&mut self, _path: &str) -> Result<(), String> {
+        Err("web sandbox: fs.mkdir requires a virtual filesystem".to_string())
+    }
+
+    fn fs_remove_file(&mut self, _path: &str) -> Result<(), String> {
+        Err("web sandbox: fs.remove requires a virtual filesystem".to_string())
     }
 
     fn proc_run(&mut self, _cmd: &str, _args: &[String]) -> Result<i32, String> {
         Err("web sandbox: proc.run disabled".to_string())
     }
 
+    fn proc_run_capture(&mut self, _req: &ProcRequest) -> Result<ProcOutput, String> {
+        Err("web sandbox: proc.run_capture disabled".to_string())
+    }
+
     fn http_get(&mut self, _url: &str) -> Result<String, String> {
         Err("web sandbox: http.get disabled".to_string())
     }
+
+    fn http_request(&mut self, _req: &HttpRequest) -> Result<HttpResponse, String> {
+        Err("web sandbox: http.request disabled".to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{symref_width_stats, token_economy_count};
+    use super::{run_impl, symref_width_stats, token_economy_count};
 
     #[test]
     fn token_metric_matches_expected_weights() {
@@ -606,4 +681,33 @@ mod tests {
         assert_eq!(max, 3);
         assert!((avg - 2.0).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn run_reads_and_writes_virtual_files() {
+        let src = "@w.vfs{F main:()->i32!{io,fs}=m(c(read,\"in.txt\")){Ok(s)=>{c(write,\"out/copy.txt\",s);c(println,s);0};Er(e)=>{c(println,e);1};};}";
+        let response = run_impl(
+            src,
+            10_000,
+            None,
+            Some("{\"in.txt\":\"hello\"}".to_string()),
+        );
+        assert!(!response.trapped, "unexpected trap: {}", response.stderr);
+        assert_eq!(response.stdout, "hello\n");
+        assert_eq!(
+            response.files.get("out/copy.txt").map(String::as_str),
+            Some("hello")
+        );
+    }
+
+    #[test]
+    fn run_rejects_invalid_vfs_manifest() {
+        let response = run_impl(
+            "@w.bad{F main:()->i32=0;}",
+            10_000,
+            None,
+            Some("[1]".to_string()),
+        );
+        assert!(response.trapped);
+        assert_eq!(response.trap_code.as_deref(), Some("E_VFS_MANIFEST"));
+    }
 }
@@ -37,6 +37,7 @@ pub enum Decl {
     Import(ImportDecl),
     Export(ExportDecl),
     Type(TypeDecl),
+    Alias(AliasDecl),
     Value(ValueDecl),
     Function(FunctionDecl),
 }
@@ -51,6 +52,8 @@ pub struct ImportDecl {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExportDecl {
     pub names: Vec<Ident>,
+    // Parallel to `names`: `~Name` exports a type without its constructors.
+    pub opaque: Vec<bool>,
     pub span: Span,
 }
 
@@ -62,10 +65,20 @@ pub struct TypeDecl {
     pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasDecl {
+    pub name: Ident,
+    pub params: Vec<Ident>,
+    pub ty: TypeExpr,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CtorDecl {
     pub name: Ident,
     pub fields: Vec<TypeExpr>,
+    // Non-empty only for record declarations, parallel to `fields`.
+    pub field_names: Vec<Ident>,
     pub span: Span,
 }
 
@@ -81,11 +94,21 @@ pub struct ValueDecl {
 pub struct FunctionDecl {
     pub name: Ident,
     pub type_params: Vec<Ident>,
+    pub param_names: Vec<Ident>,
     pub sig: FunctionType,
     pub expr: Expr,
     pub span: Span,
 }
 
+impl FunctionDecl {
+    pub fn param_name(&self, idx: usize, symtab: Option<&[String]>) -> String {
+        match self.param_names.get(idx) {
+            Some(name) => name.resolved_string(symtab),
+            None => format!("arg{idx}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Name {
     Ident(String),
@@ -151,6 +174,8 @@ pub enum PrimType {
 pub enum TypeExpr {
     Prim(PrimType, Span),
     Named {
+        // Import alias for `alias.Name` references to another module's type.
+        module: Option<Ident>,
         name: Ident,
         args: Vec<TypeExpr>,
         span: Span,
@@ -208,6 +233,8 @@ pub struct FunctionType {
     pub params: Vec<TypeExpr>,
     pub ret: Box<TypeExpr>,
     pub effects: EffectSet,
+    // Where `!{...}` is written; empty, just after `ret`, when it is omitted.
+    pub effects_span: Span,
     pub span: Span,
 }
 
@@ -225,6 +252,9 @@ pub enum EffectAtom {
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct EffectSet {
     pub atoms: Vec<EffectAtom>,
+    // Effect variables such as the `e` in `(A)->B!{e}`; a function signature
+    // is polymorphic over the variables it mentions.
+    pub vars: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -257,6 +287,20 @@ pub enum Expr {
         body: Box<Expr>,
         span: Span,
     },
+    LetPattern {
+        pattern: Pattern,
+        ty: Option<TypeExpr>,
+        value: Box<Expr>,
+        body: Box<Expr>,
+        span: Span,
+    },
+    // `w(f=l(..),g=l(..),body)`: local functions that may call themselves and
+    // each other. Every binding's value is a lambda.
+    LetRec {
+        bindings: Vec<FieldInit>,
+        body: Box<Expr>,
+        span: Span,
+    },
     If {
         cond: Box<Expr>,
         then_branch: Box<Expr>,
@@ -277,6 +321,8 @@ pub enum Expr {
         params: Vec<Param>,
         ret: TypeExpr,
         effects: EffectSet,
+        // As in `FunctionType`.
+        effects_span: Span,
         body: Box<Expr>,
         span: Span,
     },
@@ -295,6 +341,8 @@ pub enum Expr {
     },
     Name(Ident),
     NameApp {
+        // Import alias for `alias.Ctor(..)` applications.
+        module: Option<Ident>,
         name: Ident,
         args: Vec<Expr>,
         span: Span,
@@ -304,6 +352,29 @@ pub enum Expr {
         inner: Box<Expr>,
         span: Span,
     },
+    Tuple {
+        items: Vec<Expr>,
+        span: Span,
+    },
+    Try {
+        inner: Box<Expr>,
+        span: Span,
+    },
+    Record {
+        name: Ident,
+        fields: Vec<FieldInit>,
+        span: Span,
+    },
+    Field {
+        base: Box<Expr>,
+        field: Ident,
+        span: Span,
+    },
+    Update {
+        base: Box<Expr>,
+        fields: Vec<FieldInit>,
+        span: Span,
+    },
 }
 
 impl Expr {
@@ -312,6 +383,8 @@ impl Expr {
             Expr::Block { span, .. }
             | Expr::Unit(span)
             | Expr::Let { span, .. }
+            | Expr::LetPattern { span, .. }
+            | Expr::LetRec { span, .. }
             | Expr::If { span, .. }
             | Expr::Match { span, .. }
             | Expr::Call { span, .. }
@@ -320,7 +393,12 @@ impl Expr {
             | Expr::Require { span, .. }
             | Expr::Ensure { span, .. }
             | Expr::NameApp { span, .. }
-            | Expr::Paren { span, .. } => *span,
+            | Expr::Paren { span, .. }
+            | Expr::Tuple { span, .. }
+            | Expr::Try { span, .. }
+            | Expr::Record { span, .. }
+            | Expr::Field { span, .. }
+            | Expr::Update { span, .. } => *span,
             Expr::Name(name) => name.span,
             Expr::Literal(lit) => lit.span(),
         }
@@ -334,9 +412,17 @@ pub struct Param {
     pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInit {
+    pub name: Ident,
+    pub value: Expr,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchArm {
     pub pattern: Pattern,
+    pub guard: Option<Expr>,
     pub expr: Expr,
     pub span: Span,
 }
@@ -347,6 +433,8 @@ pub enum Pattern {
     Literal(Literal),
     Name(Ident),
     Ctor {
+        // Import alias for `alias.Ctor` patterns.
+        module: Option<Ident>,
         name: Ident,
         args: Vec<Pattern>,
         span: Span,
@@ -359,6 +447,10 @@ pub enum Pattern {
         inner: Box<Pattern>,
         span: Span,
     },
+    Or {
+        alts: Vec<Pattern>,
+        span: Span,
+    },
 }
 
 impl Pattern {
@@ -367,7 +459,8 @@ impl Pattern {
             Pattern::Wildcard(span)
             | Pattern::Ctor { span, .. }
             | Pattern::Tuple { span, .. }
-            | Pattern::Paren { span, .. } => *span,
+            | Pattern::Paren { span, .. }
+            | Pattern::Or { span, .. } => *span,
             Pattern::Literal(lit) => lit.span(),
             Pattern::Name(id) => id.span,
         }
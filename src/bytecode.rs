@@ -1,9 +1,43 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 
-use crate::ast::{Decl, Expr, FunctionDecl, Ident, Literal, Param, Pattern, Program, ValueDecl};
+use crate::ast::{
+    Decl, Expr, FieldInit, FunctionDecl, Ident, Literal, MatchArm, Module, Param, Pattern, Program,
+    ValueDecl,
+};
+use crate::fmt::format_expr_readable;
 
-pub const MAGIC: &[u8; 4] = b"MUB1";
+pub const MAGIC: &[u8; 4] = b"MUB2";
+// Streams written before the metadata and constructor-order tables existed;
+// they decode as debug builds with no metadata, and their ADT tags rank by
+// string index, which is where older compilers put them in order.
+pub const MAGIC_V1: &[u8; 4] = b"MUB1";
+
+// Which checks a build keeps. Release builds drop `^` requires and `_`
+// ensures; asserts stay in both profiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profile {
+    #[default]
+    Debug,
+    Release,
+}
+
+impl Profile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Profile::Debug),
+            "release" => Some(Profile::Release),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct BytecodeError {
@@ -92,6 +126,7 @@ pub enum OpCode {
     CallClosure = 19,
     Trap = 20,
     ContractConst = 21,
+    MkTuple = 22,
 }
 
 impl OpCode {
@@ -118,6 +153,7 @@ impl OpCode {
             19 => Some(OpCode::CallClosure),
             20 => Some(OpCode::Trap),
             21 => Some(OpCode::ContractConst),
+            22 => Some(OpCode::MkTuple),
             _ => None,
         }
     }
@@ -132,27 +168,60 @@ pub struct FunctionBytecode {
 
 #[derive(Debug, Clone)]
 pub struct DecodedBytecode {
+    pub metadata: BTreeMap<String, String>,
     pub strings: Vec<String>,
+    // Constructor tags per ADT family, as string indices in declaration order.
+    pub ctor_families: Vec<Vec<u32>>,
     pub functions: Vec<FunctionBytecode>,
     pub entry_fn: u32,
 }
 
+impl DecodedBytecode {
+    // The recorded build profile; streams without one are debug builds.
+    pub fn profile(&self) -> Profile {
+        self.metadata
+            .get("profile")
+            .and_then(|name| Profile::from_name(name))
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Default)]
 struct CompileCtx {
     strings: Vec<String>,
     string_ids: HashMap<String, u32>,
-    ctor_names: HashSet<String>,
+    ctors: HashMap<String, CtorInfo>,
     fn_ids: HashMap<String, u32>,
     value_ids: HashMap<String, u32>,
     functions: Vec<FunctionBytecode>,
     symtab: Option<Vec<String>>,
+    imports: HashMap<String, String>,
+    profile: Profile,
+}
+
+#[derive(Debug, Clone)]
+struct CtorInfo {
+    arity: usize,
+    family: Vec<String>,
+    field_names: Vec<String>,
 }
 
 struct Lowerer<'a> {
     ctx: &'a mut CompileCtx,
+    module: &'a Module,
     code: Vec<u8>,
     locals: BTreeMap<String, u32>,
     next_local: u32,
+    exit: ExitContracts,
+}
+
+// The ensures of the function being lowered, checked before each `Return`
+// with `_r` bound to the returned value and the entry locals in scope.
+#[derive(Default)]
+struct ExitContracts {
+    owner: String,
+    ensures: Vec<Expr>,
+    locals: BTreeMap<String, u32>,
 }
 
 fn id_text(id: &Ident, symtab: Option<&[String]>) -> String {
@@ -160,12 +229,21 @@ fn id_text(id: &Ident, symtab: Option<&[String]>) -> String {
 }
 
 pub fn compile(program: &Program) -> Result<Vec<u8>, BytecodeError> {
+    compile_with_profile(program, Profile::Debug)
+}
+
+pub fn compile_with_profile(program: &Program, profile: Profile) -> Result<Vec<u8>, BytecodeError> {
     let mut top_functions = Vec::new();
     let mut top_values = Vec::new();
+    let mut imports = HashMap::new();
     for decl in &program.module.decls {
         match decl {
             Decl::Function(f) => top_functions.push(f),
             Decl::Value(v) => top_values.push(v),
+            Decl::Import(d) => {
+                let alias = id_text(&d.alias, program.module.symtab.as_deref());
+                imports.insert(alias, d.module.parts.join("."));
+            }
             _ => {}
         }
     }
@@ -175,9 +253,26 @@ pub fn compile(program: &Program) -> Result<Vec<u8>, BytecodeError> {
         });
     }
 
+    // Constructor tags are interned first so the constructor-order table can
+    // name every family, including ones the program never builds.
+    let (families, ctors) = collect_ctors(program);
+    let mut ctor_order = Vec::new();
+    for name in families.iter().flatten() {
+        if !ctor_order.contains(name) {
+            ctor_order.push(name.clone());
+        }
+    }
     let mut ctx = CompileCtx {
-        ctor_names: collect_ctors(program),
+        ctors,
+        string_ids: ctor_order
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.clone(), idx as u32))
+            .collect(),
+        strings: ctor_order,
         symtab: program.module.symtab.clone(),
+        imports,
+        profile,
         ..CompileCtx::default()
     };
 
@@ -203,11 +298,11 @@ pub fn compile(program: &Program) -> Result<Vec<u8>, BytecodeError> {
     ];
 
     for (idx, v) in top_values.iter().enumerate() {
-        let func = lower_top_value(&mut ctx, v)?;
+        let func = lower_top_value(&mut ctx, &program.module, v)?;
         ctx.functions[idx] = func;
     }
     for (idx, f) in top_functions.iter().enumerate() {
-        let func = lower_top_function(&mut ctx, f)?;
+        let func = lower_top_function(&mut ctx, &program.module, f)?;
         ctx.functions[top_values.len() + idx] = func;
     }
 
@@ -215,25 +310,41 @@ pub fn compile(program: &Program) -> Result<Vec<u8>, BytecodeError> {
         message: "missing `main` function".to_string(),
     })?;
 
-    Ok(encode_parts(&ctx.strings, &ctx.functions, entry_fn))
+    let metadata = BTreeMap::from([("profile".to_string(), profile.as_str().to_string())]);
+    let ctor_families = families
+        .iter()
+        .map(|family| family.iter().map(|name| ctx.string_ids[name]).collect())
+        .collect::<Vec<_>>();
+    Ok(encode_parts(
+        &metadata,
+        &ctx.strings,
+        &ctor_families,
+        &ctx.functions,
+        entry_fn,
+    ))
 }
 
 fn lower_top_function(
     ctx: &mut CompileCtx,
+    module: &Module,
     f: &FunctionDecl,
 ) -> Result<FunctionBytecode, BytecodeError> {
     let mut locals = BTreeMap::new();
     for i in 0..f.sig.params.len() {
-        locals.insert(format!("arg{i}"), i as u32);
+        locals.insert(f.param_name(i, ctx.symtab.as_deref()), i as u32);
     }
+    let owner = format!("`{}`", id_text(&f.name, ctx.symtab.as_deref()));
     let mut lowerer = Lowerer {
         ctx,
+        module,
         code: Vec::new(),
         next_local: f.sig.params.len() as u32,
         locals,
+        exit: ExitContracts::default(),
     };
+    lowerer.begin_body(owner, &f.expr);
     lowerer.lower_expr(&f.expr)?;
-    lowerer.code.push(OpCode::Return as u8);
+    lowerer.emit_return()?;
     Ok(FunctionBytecode {
         arity: f.sig.params.len() as u8,
         captures: 0,
@@ -241,15 +352,23 @@ fn lower_top_function(
     })
 }
 
-fn lower_top_value(ctx: &mut CompileCtx, v: &ValueDecl) -> Result<FunctionBytecode, BytecodeError> {
+fn lower_top_value(
+    ctx: &mut CompileCtx,
+    module: &Module,
+    v: &ValueDecl,
+) -> Result<FunctionBytecode, BytecodeError> {
+    let owner = format!("`{}`", id_text(&v.name, ctx.symtab.as_deref()));
     let mut lowerer = Lowerer {
         ctx,
+        module,
         code: Vec::new(),
         next_local: 0,
         locals: BTreeMap::new(),
+        exit: ExitContracts::default(),
     };
+    lowerer.begin_body(owner, &v.expr);
     lowerer.lower_expr(&v.expr)?;
-    lowerer.code.push(OpCode::Return as u8);
+    lowerer.emit_return()?;
     Ok(FunctionBytecode {
         arity: 0,
         captures: 0,
@@ -301,6 +420,35 @@ impl<'a> Lowerer<'a> {
                 self.lower_expr(body)?;
                 restore_local(&mut self.locals, &resolved, prev);
             }
+            Expr::LetPattern {
+                pattern,
+                value,
+                body,
+                ..
+            } => {
+                self.lower_expr(value)?;
+                let slot = self.alloc_local();
+                self.code.push(OpCode::StoreLocal as u8);
+                self.code.extend_from_slice(&slot.to_le_bytes());
+                let mut bound = Vec::new();
+                self.bind_irrefutable(pattern, slot, &mut bound)?;
+                self.lower_expr(body)?;
+                for (name, prev) in bound.into_iter().rev() {
+                    restore_local(&mut self.locals, &name, prev);
+                }
+            }
+            Expr::Tuple { items, .. } => {
+                if items.len() > u8::MAX as usize {
+                    return Err(BytecodeError {
+                        message: "tuple has too many items".to_string(),
+                    });
+                }
+                for item in items {
+                    self.lower_expr(item)?;
+                }
+                self.code.push(OpCode::MkTuple as u8);
+                self.code.push(items.len() as u8);
+            }
             Expr::Block { prefix, tail, .. } => {
                 for e in prefix {
                     self.lower_expr(e)?;
@@ -329,14 +477,25 @@ impl<'a> Lowerer<'a> {
                 self.code[patch_end..patch_end + 4].copy_from_slice(&end_ip.to_le_bytes());
             }
             Expr::Call { callee, args, .. } => {
+                if let Some((module, name)) = self.qualified_name(callee) {
+                    if module.starts_with("core.") && self.lower_builtin_call(&name, args)? {
+                        return Ok(());
+                    }
+                    return Err(BytecodeError {
+                        message: format!(
+                            "cannot lower `{name}` from module `{module}`: only the entry module is compiled"
+                        ),
+                    });
+                }
                 if let Expr::Name(name) = &**callee {
                     let resolved = id_text(name, self.ctx.symtab.as_deref());
-                    if let Some(builtin_id) = builtin_id(&resolved) {
+                    if let Some(slot) = self.locals.get(&resolved).copied() {
+                        self.code.push(OpCode::LoadLocal as u8);
+                        self.code.extend_from_slice(&slot.to_le_bytes());
                         for arg in args {
                             self.lower_expr(arg)?;
                         }
-                        self.code.push(OpCode::CallBuiltin as u8);
-                        self.code.push(builtin_id);
+                        self.code.push(OpCode::CallClosure as u8);
                         self.code.push(args.len() as u8);
                         return Ok(());
                     }
@@ -349,14 +508,7 @@ impl<'a> Lowerer<'a> {
                         self.code.push(args.len() as u8);
                         return Ok(());
                     }
-                    if let Some(slot) = self.locals.get(&resolved).copied() {
-                        self.code.push(OpCode::LoadLocal as u8);
-                        self.code.extend_from_slice(&slot.to_le_bytes());
-                        for arg in args {
-                            self.lower_expr(arg)?;
-                        }
-                        self.code.push(OpCode::CallClosure as u8);
-                        self.code.push(args.len() as u8);
+                    if self.lower_builtin_call(&resolved, args)? {
                         return Ok(());
                     }
                 }
@@ -370,141 +522,176 @@ impl<'a> Lowerer<'a> {
             }
             Expr::Lambda { params, body, .. } => {
                 let captures = capture_plan(&self.locals, params, self.ctx.symtab.as_deref());
-                let lambda_id = self.compile_lambda(params, body, &captures)?;
-                for cap in &captures {
-                    let slot = self.locals.get(cap).ok_or_else(|| BytecodeError {
-                        message: format!("missing capture `{cap}` during lambda lowering"),
-                    })?;
-                    self.code.push(OpCode::LoadLocal as u8);
+                let lambda_id = self.reserve_function();
+                let owner = format!("a lambda in {}", self.exit.owner);
+                self.compile_lambda(lambda_id, owner, params, body, &captures, &[])?;
+                self.emit_closure(lambda_id, &captures)?;
+            }
+            Expr::LetRec { bindings, body, .. } => {
+                // The group shares one capture list, so each member can rebuild
+                // itself and its siblings from its own captures on entry.
+                let symtab = self.ctx.symtab.clone();
+                let names = bindings
+                    .iter()
+                    .map(|b| id_text(&b.name, symtab.as_deref()))
+                    .collect::<Vec<_>>();
+                let captures = capture_plan(&self.locals, &[], symtab.as_deref())
+                    .into_iter()
+                    .filter(|name| !names.contains(name))
+                    .collect::<Vec<_>>();
+                let group = names
+                    .iter()
+                    .map(|name| (name.clone(), self.reserve_function()))
+                    .collect::<Vec<_>>();
+                for (binding, (name, lambda_id)) in bindings.iter().zip(&group) {
+                    let Expr::Lambda { params, body, .. } = &binding.value else {
+                        return Err(BytecodeError {
+                            message: format!("recursive binding `{name}` must be a lambda"),
+                        });
+                    };
+                    let owner = format!("`{name}`");
+                    self.compile_lambda(*lambda_id, owner, params, body, &captures, &group)?;
+                }
+                let mut bound = Vec::new();
+                for (name, lambda_id) in &group {
+                    self.emit_closure(*lambda_id, &captures)?;
+                    let slot = self.alloc_local();
+                    self.code.push(OpCode::StoreLocal as u8);
                     self.code.extend_from_slice(&slot.to_le_bytes());
+                    bound.push((name.clone(), self.locals.insert(name.clone(), slot)));
+                }
+                self.lower_expr(body)?;
+                for (name, prev) in bound.into_iter().rev() {
+                    restore_local(&mut self.locals, &name, prev);
                 }
-                self.code.push(OpCode::MkClosure as u8);
-                self.code.extend_from_slice(&lambda_id.to_le_bytes());
-                self.code.push(captures.len() as u8);
             }
             Expr::Match {
                 scrutinee, arms, ..
-            } => {
-                self.lower_expr(scrutinee)?;
-                let scrut_slot = self.alloc_local();
+            } => self.lower_match(scrutinee, arms)?,
+            Expr::Paren { inner, .. } => self.lower_expr(inner)?,
+            Expr::Record { name, fields, .. } => {
+                let record = id_text(name, self.ctx.symtab.as_deref());
+                let Some(info) = self.ctx.ctors.get(&record).cloned() else {
+                    return Err(BytecodeError {
+                        message: format!("unknown record `{record}` in lowering"),
+                    });
+                };
+                let values = self.lower_field_inits(fields)?;
+                for field in &info.field_names {
+                    let Some(slot) = values.get(field) else {
+                        return Err(BytecodeError {
+                            message: format!("record `{record}` is missing field `{field}`"),
+                        });
+                    };
+                    self.code.push(OpCode::LoadLocal as u8);
+                    self.code.extend_from_slice(&slot.to_le_bytes());
+                }
+                let tag_id = self.intern_string(&record);
+                self.code.push(OpCode::MkAdt as u8);
+                self.code.extend_from_slice(&tag_id.to_le_bytes());
+                self.code.push(info.arity as u8);
+            }
+            Expr::Field { base, field, .. } => {
+                if let Some((module, name)) = self.qualified_name(expr) {
+                    return Err(BytecodeError {
+                        message: format!(
+                            "cannot lower `{name}` from module `{module}`: only the entry module is compiled"
+                        ),
+                    });
+                }
+                let field = id_text(field, self.ctx.symtab.as_deref());
+                let records = self.records_with_fields(std::slice::from_ref(&field));
+                let index_of = |info: &CtorInfo| {
+                    info.field_names
+                        .iter()
+                        .position(|name| *name == field)
+                        .expect("record was selected by field name")
+                };
+                let indices = records
+                    .iter()
+                    .map(|(_, info)| index_of(info))
+                    .collect::<Vec<_>>();
+                if indices.is_empty() {
+                    return Err(BytecodeError {
+                        message: format!("no record has a field `{field}`"),
+                    });
+                }
+                self.lower_expr(base)?;
+                if indices.iter().all(|idx| *idx == indices[0]) {
+                    self.code.push(OpCode::GetAdtField as u8);
+                    self.code.push(indices[0] as u8);
+                } else {
+                    let slot = self.alloc_local();
+                    self.code.push(OpCode::StoreLocal as u8);
+                    self.code.extend_from_slice(&slot.to_le_bytes());
+                    let tags = records.into_iter().map(|(tag, _)| tag).collect::<Vec<_>>();
+                    self.lower_record_dispatch(slot, &tags, |this, i| {
+                        this.code.push(OpCode::LoadLocal as u8);
+                        this.code.extend_from_slice(&slot.to_le_bytes());
+                        this.code.push(OpCode::GetAdtField as u8);
+                        this.code.push(indices[i] as u8);
+                    });
+                }
+            }
+            Expr::Update { base, fields, .. } => {
+                let names = fields
+                    .iter()
+                    .map(|init| id_text(&init.name, self.ctx.symtab.as_deref()))
+                    .collect::<Vec<_>>();
+                let records = self.records_with_fields(&names);
+                if records.is_empty() {
+                    return Err(BytecodeError {
+                        message: format!("no record has the fields `{}`", names.join("`, `")),
+                    });
+                }
+                self.lower_expr(base)?;
+                let base_slot = self.alloc_local();
                 self.code.push(OpCode::StoreLocal as u8);
-                self.code.extend_from_slice(&scrut_slot.to_le_bytes());
-                let mut end_jumps = Vec::new();
-                let mut has_fallback = false;
-                for arm in arms {
-                    match &arm.pattern {
-                        Pattern::Wildcard(_) => {
-                            has_fallback = true;
-                            self.lower_expr(&arm.expr)?;
-                            let end_patch = self.emit_jump_placeholder(OpCode::Jump);
-                            end_jumps.push(end_patch);
-                        }
-                        Pattern::Literal(Literal::Bool(expected, _)) => {
-                            self.code.push(OpCode::LoadLocal as u8);
-                            self.code.extend_from_slice(&scrut_slot.to_le_bytes());
-                            if *expected {
-                                let next_patch = self.emit_jump_placeholder(OpCode::JumpIfFalse);
-                                self.lower_expr(&arm.expr)?;
-                                let end_patch = self.emit_jump_placeholder(OpCode::Jump);
-                                end_jumps.push(end_patch);
-                                self.patch_jump_to_current(next_patch);
-                            } else {
-                                let arm_patch = self.emit_jump_placeholder(OpCode::JumpIfFalse);
-                                let next_patch = self.emit_jump_placeholder(OpCode::Jump);
-                                self.patch_jump_to_current(arm_patch);
-                                self.lower_expr(&arm.expr)?;
-                                let end_patch = self.emit_jump_placeholder(OpCode::Jump);
-                                end_jumps.push(end_patch);
-                                self.patch_jump_to_current(next_patch);
+                self.code.extend_from_slice(&base_slot.to_le_bytes());
+                let values = self.lower_field_inits(fields)?;
+                let tags = records
+                    .iter()
+                    .map(|(tag, _)| tag.clone())
+                    .collect::<Vec<_>>();
+                self.lower_record_dispatch(base_slot, &tags, |this, i| {
+                    let (tag, info) = &records[i];
+                    for (idx, field) in info.field_names.iter().enumerate() {
+                        this.code.push(OpCode::LoadLocal as u8);
+                        match values.get(field) {
+                            Some(slot) => this.code.extend_from_slice(&slot.to_le_bytes()),
+                            None => {
+                                this.code.extend_from_slice(&base_slot.to_le_bytes());
+                                this.code.push(OpCode::GetAdtField as u8);
+                                this.code.push(idx as u8);
                             }
                         }
-                        Pattern::Ctor { name, args, .. } => {
-                            let ctor_name = id_text(name, self.ctx.symtab.as_deref());
-                            let tag_id = self.intern_string(&ctor_name);
-                            self.code.push(OpCode::LoadLocal as u8);
-                            self.code.extend_from_slice(&scrut_slot.to_le_bytes());
-                            let arm_patch = self.emit_jump_if_tag_placeholder(tag_id);
-                            let next_patch = self.emit_jump_placeholder(OpCode::Jump);
-                            self.patch_jump_to_current(arm_patch);
-                            let mut bound: Vec<(String, Option<u32>)> = Vec::new();
-                            for (idx, arg_pat) in args.iter().enumerate() {
-                                match arg_pat {
-                                    Pattern::Name(id) => {
-                                        self.code.push(OpCode::LoadLocal as u8);
-                                        self.code.extend_from_slice(&scrut_slot.to_le_bytes());
-                                        self.code.push(OpCode::GetAdtField as u8);
-                                        self.code.push(idx as u8);
-                                        let slot = self.alloc_local();
-                                        self.code.push(OpCode::StoreLocal as u8);
-                                        self.code.extend_from_slice(&slot.to_le_bytes());
-                                        let bind_name = id_text(id, self.ctx.symtab.as_deref());
-                                        let prev = self.locals.insert(bind_name.clone(), slot);
-                                        bound.push((bind_name, prev));
-                                    }
-                                    Pattern::Wildcard(_) => {}
-                                    _ => {
-                                        return Err(BytecodeError {
-                                            message:
-                                                "only identifier and wildcard constructor field patterns are supported in bytecode lowering"
-                                                    .to_string(),
-                                        });
-                                    }
-                                }
-                            }
-                            self.lower_expr(&arm.expr)?;
-                            for (name, prev) in bound.into_iter().rev() {
-                                restore_local(&mut self.locals, &name, prev);
-                            }
-                            let end_patch = self.emit_jump_placeholder(OpCode::Jump);
-                            end_jumps.push(end_patch);
-                            self.patch_jump_to_current(next_patch);
-                        }
-                        Pattern::Name(id) => {
-                            let name_text = id_text(id, self.ctx.symtab.as_deref());
-                            if self.ctx.ctor_names.contains(&name_text) {
-                                let tag_id = self.intern_string(&name_text);
-                                self.code.push(OpCode::LoadLocal as u8);
-                                self.code.extend_from_slice(&scrut_slot.to_le_bytes());
-                                let arm_patch = self.emit_jump_if_tag_placeholder(tag_id);
-                                let next_patch = self.emit_jump_placeholder(OpCode::Jump);
-                                self.patch_jump_to_current(arm_patch);
-                                self.lower_expr(&arm.expr)?;
-                                let end_patch = self.emit_jump_placeholder(OpCode::Jump);
-                                end_jumps.push(end_patch);
-                                self.patch_jump_to_current(next_patch);
-                            } else {
-                                has_fallback = true;
-                                self.code.push(OpCode::LoadLocal as u8);
-                                self.code.extend_from_slice(&scrut_slot.to_le_bytes());
-                                let slot = self.alloc_local();
-                                self.code.push(OpCode::StoreLocal as u8);
-                                self.code.extend_from_slice(&slot.to_le_bytes());
-                                let prev = self.locals.insert(name_text.clone(), slot);
-                                self.lower_expr(&arm.expr)?;
-                                restore_local(&mut self.locals, &name_text, prev);
-                                let end_patch = self.emit_jump_placeholder(OpCode::Jump);
-                                end_jumps.push(end_patch);
-                            }
-                        }
-                        _ => {
-                            return Err(BytecodeError {
-                                message:
-                                    "only boolean, constructor, name, and wildcard patterns are supported in bytecode lowering"
-                                        .to_string(),
-                            });
-                        }
                     }
-                }
-                if !has_fallback {
-                    let msg_id = self.intern_string("E4005: invalid match");
-                    self.code.push(OpCode::Trap as u8);
-                    self.code.extend_from_slice(&msg_id.to_le_bytes());
-                }
-                for patch in end_jumps {
-                    self.patch_jump_to_current(patch);
-                }
+                    let tag_id = this.intern_string(tag);
+                    this.code.push(OpCode::MkAdt as u8);
+                    this.code.extend_from_slice(&tag_id.to_le_bytes());
+                    this.code.push(info.arity as u8);
+                });
+            }
+            Expr::Try { inner, .. } => {
+                // Unwrap `Ok`, or return the `Er` value itself from the
+                // enclosing function.
+                self.lower_expr(inner)?;
+                let slot = self.alloc_local();
+                self.code.push(OpCode::StoreLocal as u8);
+                self.code.extend_from_slice(&slot.to_le_bytes());
+                self.code.push(OpCode::LoadLocal as u8);
+                self.code.extend_from_slice(&slot.to_le_bytes());
+                let ok_id = self.intern_string("Ok");
+                let on_ok = self.emit_jump_if_tag_placeholder(ok_id);
+                self.code.push(OpCode::LoadLocal as u8);
+                self.code.extend_from_slice(&slot.to_le_bytes());
+                self.emit_return()?;
+                self.patch_jump_to_current(on_ok);
+                self.code.push(OpCode::LoadLocal as u8);
+                self.code.extend_from_slice(&slot.to_le_bytes());
+                self.code.push(OpCode::GetAdtField as u8);
+                self.code.push(0);
             }
-            Expr::Paren { inner, .. } => self.lower_expr(inner)?,
             Expr::Assert { cond, msg, .. } => {
                 self.lower_expr(cond)?;
                 if let Some(msg_expr) = msg {
@@ -516,21 +703,38 @@ impl<'a> Lowerer<'a> {
                     self.code.extend_from_slice(&msg_id.to_le_bytes());
                 }
             }
+            Expr::Require { .. } if self.ctx.profile == Profile::Release => {
+                self.code.push(OpCode::PushUnit as u8);
+            }
             Expr::Require { expr, .. } => {
                 self.lower_expr(expr)?;
                 let msg_id = self.intern_string("contract require failure");
                 self.code.push(OpCode::ContractConst as u8);
                 self.code.extend_from_slice(&msg_id.to_le_bytes());
             }
-            Expr::Ensure { expr, .. } => {
-                self.lower_expr(expr)?;
-                let msg_id = self.intern_string("contract ensure failure");
-                self.code.push(OpCode::ContractConst as u8);
-                self.code.extend_from_slice(&msg_id.to_le_bytes());
+            // Checked by `emit_return` on every exit of the function.
+            Expr::Ensure { .. } => self.code.push(OpCode::PushUnit as u8),
+            Expr::NameApp {
+                module: Some(alias),
+                name,
+                args,
+                ..
+            } => {
+                let symtab = self.ctx.symtab.as_deref();
+                let (alias, name) = (id_text(alias, symtab), id_text(name, symtab));
+                let module = self.ctx.imports.get(&alias).cloned().unwrap_or(alias);
+                if module.starts_with("core.") && self.lower_builtin_call(&name, args)? {
+                    return Ok(());
+                }
+                return Err(BytecodeError {
+                    message: format!(
+                        "cannot lower `{name}` from module `{module}`: only the entry module is compiled"
+                    ),
+                });
             }
             Expr::NameApp { name, args, .. } => {
                 let ctor_name = id_text(name, self.ctx.symtab.as_deref());
-                if !self.ctx.ctor_names.contains(&ctor_name) {
+                if !self.ctx.ctors.contains_key(&ctor_name) {
                     return Err(BytecodeError {
                         message: format!(
                             "name application `{}` is not a known constructor in this module",
@@ -550,13 +754,241 @@ impl<'a> Lowerer<'a> {
         Ok(())
     }
 
+    // Evaluates field initializers in source order into fresh slots.
+    fn lower_field_inits(
+        &mut self,
+        fields: &[FieldInit],
+    ) -> Result<HashMap<String, u32>, BytecodeError> {
+        let mut values = HashMap::new();
+        for init in fields {
+            self.lower_expr(&init.value)?;
+            let slot = self.alloc_local();
+            self.code.push(OpCode::StoreLocal as u8);
+            self.code.extend_from_slice(&slot.to_le_bytes());
+            values.insert(id_text(&init.name, self.ctx.symtab.as_deref()), slot);
+        }
+        Ok(values)
+    }
+
+    // Records declaring every one of `fields`, ordered by tag. Lowering is
+    // untyped, so field access and update dispatch on the tag when several
+    // records could be involved.
+    fn records_with_fields(&self, fields: &[String]) -> Vec<(String, CtorInfo)> {
+        let mut records = self
+            .ctx
+            .ctors
+            .iter()
+            .filter(|(_, info)| {
+                !info.field_names.is_empty()
+                    && fields.iter().all(|field| info.field_names.contains(field))
+            })
+            .map(|(tag, info)| (tag.clone(), info.clone()))
+            .collect::<Vec<_>>();
+        records.sort_by(|a, b| a.0.cmp(&b.0));
+        records
+    }
+
+    // Emits `emit(i)` for the record in `tags[i]` that the value in `slot`
+    // carries; the last tag needs no test.
+    fn lower_record_dispatch(
+        &mut self,
+        slot: u32,
+        tags: &[String],
+        mut emit: impl FnMut(&mut Self, usize),
+    ) {
+        let mut ends = Vec::new();
+        for (i, tag) in tags.iter().enumerate() {
+            if i + 1 == tags.len() {
+                emit(self, i);
+                break;
+            }
+            let miss = self.emit_head_test(slot, &MatchHead::Ctor(tag.clone()));
+            emit(self, i);
+            ends.push(self.emit_jump_placeholder(OpCode::Jump));
+            self.patch_jump_to_current(miss);
+        }
+        for end in ends {
+            self.patch_jump_to_current(end);
+        }
+    }
+
+    // Lowers a call to a builtin by name; returns false if `name` is not one.
+    fn lower_builtin_call(&mut self, name: &str, args: &[Expr]) -> Result<bool, BytecodeError> {
+        if matches!(name, "unwrap_or" | "map") && args.len() == 2 {
+            self.lower_optional_combinator(name, &args[0], &args[1])?;
+            return Ok(true);
+        }
+        let Some(builtin_id) = builtin_id(name) else {
+            return Ok(false);
+        };
+        for arg in args {
+            self.lower_expr(arg)?;
+        }
+        self.code.push(OpCode::CallBuiltin as u8);
+        self.code.push(builtin_id);
+        self.code.push(args.len() as u8);
+        Ok(true)
+    }
+
+    // `alias.name` through an import alias, as (module, name); the typechecker
+    // applies the same shadowing rules.
+    fn qualified_name(&self, expr: &Expr) -> Option<(String, String)> {
+        let Expr::Field { base, field, .. } = expr else {
+            return None;
+        };
+        let Expr::Name(alias) = &**base else {
+            return None;
+        };
+        let symtab = self.ctx.symtab.as_deref();
+        let alias = id_text(alias, symtab);
+        if self.locals.contains_key(&alias)
+            || self.ctx.value_ids.contains_key(&alias)
+            || self.ctx.fn_ids.contains_key(&alias)
+        {
+            return None;
+        }
+        let module = self.ctx.imports.get(&alias)?;
+        Some((module.clone(), id_text(field, symtab)))
+    }
+
+    // The prelude's `?T` combinators take a function argument, so they are
+    // expanded inline instead of going through CALL_BUILTIN.
+    fn lower_optional_combinator(
+        &mut self,
+        name: &str,
+        opt: &Expr,
+        arg: &Expr,
+    ) -> Result<(), BytecodeError> {
+        self.lower_expr(opt)?;
+        let opt_slot = self.alloc_local();
+        self.code.push(OpCode::StoreLocal as u8);
+        self.code.extend_from_slice(&opt_slot.to_le_bytes());
+        self.lower_expr(arg)?;
+        let arg_slot = self.alloc_local();
+        self.code.push(OpCode::StoreLocal as u8);
+        self.code.extend_from_slice(&arg_slot.to_le_bytes());
+
+        self.code.push(OpCode::LoadLocal as u8);
+        self.code.extend_from_slice(&opt_slot.to_le_bytes());
+        let some_id = self.intern_string("Some");
+        let on_some = self.emit_jump_if_tag_placeholder(some_id);
+        if name == "map" {
+            let none_id = self.intern_string("None");
+            self.code.push(OpCode::MkAdt as u8);
+            self.code.extend_from_slice(&none_id.to_le_bytes());
+            self.code.push(0);
+        } else {
+            self.code.push(OpCode::LoadLocal as u8);
+            self.code.extend_from_slice(&arg_slot.to_le_bytes());
+        }
+        let done = self.emit_jump_placeholder(OpCode::Jump);
+
+        self.patch_jump_to_current(on_some);
+        if name == "map" {
+            self.code.push(OpCode::LoadLocal as u8);
+            self.code.extend_from_slice(&arg_slot.to_le_bytes());
+        }
+        self.code.push(OpCode::LoadLocal as u8);
+        self.code.extend_from_slice(&opt_slot.to_le_bytes());
+        self.code.push(OpCode::GetAdtField as u8);
+        self.code.push(0);
+        if name == "map" {
+            self.code.push(OpCode::CallClosure as u8);
+            self.code.push(1);
+            self.code.push(OpCode::MkAdt as u8);
+            self.code.extend_from_slice(&some_id.to_le_bytes());
+            self.code.push(1);
+        }
+        self.patch_jump_to_current(done);
+        Ok(())
+    }
+
+    fn begin_body(&mut self, owner: String, body: &Expr) {
+        let mut ensures = Vec::new();
+        if self.ctx.profile == Profile::Debug {
+            collect_ensures(body, &mut ensures);
+        }
+        self.exit = ExitContracts {
+            owner,
+            ensures,
+            locals: self.locals.clone(),
+        };
+    }
+
+    // Returns the value on top of the stack, after checking the function's
+    // ensures against it.
+    fn emit_return(&mut self) -> Result<(), BytecodeError> {
+        if self.exit.ensures.is_empty() {
+            self.code.push(OpCode::Return as u8);
+            return Ok(());
+        }
+        let result = self.alloc_local();
+        self.code.push(OpCode::StoreLocal as u8);
+        self.code.extend_from_slice(&result.to_le_bytes());
+        let ensures = std::mem::take(&mut self.exit.ensures);
+        let mut scope = self.exit.locals.clone();
+        scope.insert("_r".to_string(), result);
+        let saved = std::mem::replace(&mut self.locals, scope);
+        for ensure in &ensures {
+            let Expr::Ensure { expr, .. } = ensure else {
+                unreachable!("collect_ensures only yields ensures");
+            };
+            self.lower_expr(expr)?;
+            let msg = format!(
+                "ensure `{}` failed in {}",
+                format_expr_readable(expr, self.module),
+                self.exit.owner
+            );
+            let msg_id = self.intern_string(&msg);
+            self.code.push(OpCode::ContractConst as u8);
+            self.code.extend_from_slice(&msg_id.to_le_bytes());
+            self.code.push(OpCode::Pop as u8);
+        }
+        self.locals = saved;
+        self.exit.ensures = ensures;
+        self.code.push(OpCode::LoadLocal as u8);
+        self.code.extend_from_slice(&result.to_le_bytes());
+        self.code.push(OpCode::Return as u8);
+        Ok(())
+    }
+
+    // Function ids are reserved before lowering a body, since lambdas nested
+    // in it are pushed first.
+    fn reserve_function(&mut self) -> u32 {
+        self.ctx.functions.push(FunctionBytecode {
+            arity: 0,
+            captures: 0,
+            code: Vec::new(),
+        });
+        (self.ctx.functions.len() - 1) as u32
+    }
+
+    fn emit_closure(&mut self, lambda_id: u32, captures: &[String]) -> Result<(), BytecodeError> {
+        for cap in captures {
+            let slot = self.locals.get(cap).ok_or_else(|| BytecodeError {
+                message: format!("missing capture `{cap}` during lambda lowering"),
+            })?;
+            self.code.push(OpCode::LoadLocal as u8);
+            self.code.extend_from_slice(&slot.to_le_bytes());
+        }
+        self.code.push(OpCode::MkClosure as u8);
+        self.code.extend_from_slice(&lambda_id.to_le_bytes());
+        self.code.push(captures.len() as u8);
+        Ok(())
+    }
+
+    // Lowers a lambda into the reserved `lambda_id`. Members of a recursive
+    // `group` (which all take `captures`) are rebuilt as closures on entry,
+    // unless a parameter shadows them.
     fn compile_lambda(
         &mut self,
+        lambda_id: u32,
+        owner: String,
         params: &[Param],
         body: &Expr,
         captures: &[String],
-    ) -> Result<u32, BytecodeError> {
-        let lambda_id = self.ctx.functions.len() as u32;
+        group: &[(String, u32)],
+    ) -> Result<(), BytecodeError> {
         let mut locals = BTreeMap::new();
         let mut slot = 0u32;
         for cap in captures {
@@ -567,20 +999,43 @@ impl<'a> Lowerer<'a> {
             locals.insert(id_text(&p.name, self.ctx.symtab.as_deref()), slot);
             slot += 1;
         }
+        let param_names = params
+            .iter()
+            .map(|p| id_text(&p.name, self.ctx.symtab.as_deref()))
+            .collect::<HashSet<_>>();
         let mut nested = Lowerer {
             ctx: self.ctx,
+            module: self.module,
             code: Vec::new(),
             locals,
             next_local: slot,
+            exit: ExitContracts::default(),
         };
+        for (name, member_id) in group {
+            if param_names.contains(name) {
+                continue;
+            }
+            for cap in 0..captures.len() as u32 {
+                nested.code.push(OpCode::LoadLocal as u8);
+                nested.code.extend_from_slice(&cap.to_le_bytes());
+            }
+            nested.code.push(OpCode::MkClosure as u8);
+            nested.code.extend_from_slice(&member_id.to_le_bytes());
+            nested.code.push(captures.len() as u8);
+            let member_slot = nested.alloc_local();
+            nested.code.push(OpCode::StoreLocal as u8);
+            nested.code.extend_from_slice(&member_slot.to_le_bytes());
+            nested.locals.insert(name.clone(), member_slot);
+        }
+        nested.begin_body(owner, body);
         nested.lower_expr(body)?;
-        nested.code.push(OpCode::Return as u8);
-        nested.ctx.functions.push(FunctionBytecode {
+        nested.emit_return()?;
+        nested.ctx.functions[lambda_id as usize] = FunctionBytecode {
             arity: params.len() as u8,
             captures: captures.len() as u8,
             code: nested.code,
-        });
-        Ok(lambda_id)
+        };
+        Ok(())
     }
 
     fn intern_string(&mut self, s: &str) -> u32 {
@@ -593,6 +1048,57 @@ impl<'a> Lowerer<'a> {
         id
     }
 
+    fn store_field(&mut self, slot: u32, idx: usize) -> u32 {
+        self.code.push(OpCode::LoadLocal as u8);
+        self.code.extend_from_slice(&slot.to_le_bytes());
+        self.code.push(OpCode::GetAdtField as u8);
+        self.code.push(idx as u8);
+        let field_slot = self.alloc_local();
+        self.code.push(OpCode::StoreLocal as u8);
+        self.code.extend_from_slice(&field_slot.to_le_bytes());
+        field_slot
+    }
+
+    fn bind_irrefutable(
+        &mut self,
+        pat: &Pattern,
+        slot: u32,
+        bound: &mut Vec<(String, Option<u32>)>,
+    ) -> Result<(), BytecodeError> {
+        match pat {
+            Pattern::Wildcard(_) => Ok(()),
+            Pattern::Name(id) => {
+                let name = id_text(id, self.ctx.symtab.as_deref());
+                if self.ctx.ctors.contains_key(&name) {
+                    return Err(BytecodeError {
+                        message: format!(
+                            "constructor `{name}` is not supported inside tuple patterns in bytecode lowering"
+                        ),
+                    });
+                }
+                let prev = self.locals.insert(name.clone(), slot);
+                bound.push((name, prev));
+                Ok(())
+            }
+            Pattern::Tuple { items, .. } => {
+                for (idx, item) in items.iter().enumerate() {
+                    if matches!(item, Pattern::Wildcard(_)) {
+                        continue;
+                    }
+                    let item_slot = self.store_field(slot, idx);
+                    self.bind_irrefutable(item, item_slot, bound)?;
+                }
+                Ok(())
+            }
+            Pattern::Paren { inner, .. } => self.bind_irrefutable(inner, slot, bound),
+            Pattern::Literal(_) | Pattern::Ctor { .. } | Pattern::Or { .. } => Err(BytecodeError {
+                message:
+                    "only identifier, wildcard and tuple patterns are supported inside tuple patterns in bytecode lowering"
+                        .to_string(),
+            }),
+        }
+    }
+
     fn alloc_local(&mut self) -> u32 {
         let slot = self.next_local;
         self.next_local += 1;
@@ -620,6 +1126,452 @@ impl<'a> Lowerer<'a> {
     }
 }
 
+// Match lowering compiles the arms into a decision tree over a clause matrix:
+// each row is an arm's remaining sub-patterns, each column a local slot holding
+// the value being tested. A test on one column is emitted once and shared by
+// every row that needs it; leaves copy bindings into per-arm slots and jump to
+// the arm body, which is emitted once after the tree. Or-patterns split a row
+// into one row per alternative, and a guarded leaf that fails continues with
+// the rows below it.
+#[derive(Clone)]
+struct MatchRow<'p> {
+    pats: Vec<Option<&'p Pattern>>,
+    bindings: Vec<(String, u32)>,
+    arm: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum MatchHead {
+    Ctor(String),
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+enum PatKind<'p> {
+    Wild,
+    Bind(String),
+    Tuple(&'p [Pattern]),
+    Head(MatchHead, &'p [Pattern]),
+    Or(&'p [Pattern]),
+}
+
+struct ArmTargets<'p> {
+    guard: Option<&'p Expr>,
+    slots: BTreeMap<String, u32>,
+    entries: Vec<usize>,
+}
+
+impl<'a> Lowerer<'a> {
+    fn lower_match(&mut self, scrutinee: &Expr, arms: &[MatchArm]) -> Result<(), BytecodeError> {
+        self.lower_expr(scrutinee)?;
+        let scrut_slot = self.alloc_local();
+        self.code.push(OpCode::StoreLocal as u8);
+        self.code.extend_from_slice(&scrut_slot.to_le_bytes());
+
+        let rows = arms
+            .iter()
+            .enumerate()
+            .map(|(arm, a)| MatchRow {
+                pats: vec![Some(&a.pattern)],
+                bindings: Vec::new(),
+                arm,
+            })
+            .collect();
+        let mut targets = arms
+            .iter()
+            .map(|a| ArmTargets {
+                guard: a.guard.as_ref(),
+                slots: BTreeMap::new(),
+                entries: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        self.lower_decision(rows, vec![scrut_slot], &mut targets)?;
+
+        let mut end_jumps = Vec::new();
+        for (arm, target) in arms.iter().zip(targets) {
+            if target.entries.is_empty() {
+                continue;
+            }
+            for patch in target.entries {
+                self.patch_jump_to_current(patch);
+            }
+            let mut bound = Vec::new();
+            for (name, slot) in target.slots {
+                let prev = self.locals.insert(name.clone(), slot);
+                bound.push((name, prev));
+            }
+            self.lower_expr(&arm.expr)?;
+            for (name, prev) in bound.into_iter().rev() {
+                restore_local(&mut self.locals, &name, prev);
+            }
+            end_jumps.push(self.emit_jump_placeholder(OpCode::Jump));
+        }
+        for patch in end_jumps {
+            self.patch_jump_to_current(patch);
+        }
+        Ok(())
+    }
+
+    fn lower_decision(
+        &mut self,
+        rows: Vec<MatchRow<'_>>,
+        occs: Vec<u32>,
+        targets: &mut [ArmTargets<'_>],
+    ) -> Result<(), BytecodeError> {
+        let rows = self.expand_or_rows(rows);
+        let Some(first) = rows.first() else {
+            let msg_id = self.intern_string("E4005: invalid match");
+            self.code.push(OpCode::Trap as u8);
+            self.code.extend_from_slice(&msg_id.to_le_bytes());
+            return Ok(());
+        };
+        let column = first
+            .pats
+            .iter()
+            .position(|pat| !matches!(self.classify(*pat), PatKind::Wild | PatKind::Bind(_)));
+        let Some(col) = column else {
+            return self.lower_leaf(rows, occs, targets);
+        };
+        match self.classify(first.pats[col]) {
+            PatKind::Tuple(items) => self.lower_tuple_column(rows, occs, col, items.len(), targets),
+            _ => self.lower_switch_column(rows, occs, col, targets),
+        }
+    }
+
+    fn expand_or_rows<'p>(&self, rows: Vec<MatchRow<'p>>) -> Vec<MatchRow<'p>> {
+        let mut out = Vec::new();
+        for row in rows {
+            let or_column =
+                row.pats
+                    .iter()
+                    .enumerate()
+                    .find_map(|(col, pat)| match self.classify(*pat) {
+                        PatKind::Or(alts) => Some((col, alts)),
+                        _ => None,
+                    });
+            let Some((col, alts)) = or_column else {
+                out.push(row);
+                continue;
+            };
+            let split = alts
+                .iter()
+                .map(|alt| {
+                    let mut alt_row = row.clone();
+                    alt_row.pats[col] = Some(alt);
+                    alt_row
+                })
+                .collect();
+            out.extend(self.expand_or_rows(split));
+        }
+        out
+    }
+
+    fn lower_leaf(
+        &mut self,
+        mut rows: Vec<MatchRow<'_>>,
+        occs: Vec<u32>,
+        targets: &mut [ArmTargets<'_>],
+    ) -> Result<(), BytecodeError> {
+        let row = rows.remove(0);
+        let mut bindings = row.bindings.clone();
+        for (pat, occ) in row.pats.iter().zip(&occs) {
+            if let PatKind::Bind(name) = self.classify(*pat) {
+                bindings.push((name, *occ));
+            }
+        }
+        for (name, occ) in bindings {
+            let slot = match targets[row.arm].slots.get(&name) {
+                Some(slot) => *slot,
+                None => {
+                    let slot = self.alloc_local();
+                    targets[row.arm].slots.insert(name, slot);
+                    slot
+                }
+            };
+            self.code.push(OpCode::LoadLocal as u8);
+            self.code.extend_from_slice(&occ.to_le_bytes());
+            self.code.push(OpCode::StoreLocal as u8);
+            self.code.extend_from_slice(&slot.to_le_bytes());
+        }
+        let Some(guard) = targets[row.arm].guard else {
+            let entry = self.emit_jump_placeholder(OpCode::Jump);
+            targets[row.arm].entries.push(entry);
+            return Ok(());
+        };
+        let mut bound = Vec::new();
+        for (name, slot) in targets[row.arm].slots.clone() {
+            let prev = self.locals.insert(name.clone(), slot);
+            bound.push((name, prev));
+        }
+        self.lower_expr(guard)?;
+        for (name, prev) in bound.into_iter().rev() {
+            restore_local(&mut self.locals, &name, prev);
+        }
+        let fail = self.emit_jump_placeholder(OpCode::JumpIfFalse);
+        let entry = self.emit_jump_placeholder(OpCode::Jump);
+        targets[row.arm].entries.push(entry);
+        self.patch_jump_to_current(fail);
+        self.lower_decision(rows, occs, targets)
+    }
+
+    fn lower_tuple_column(
+        &mut self,
+        rows: Vec<MatchRow<'_>>,
+        occs: Vec<u32>,
+        col: usize,
+        arity: usize,
+        targets: &mut [ArmTargets<'_>],
+    ) -> Result<(), BytecodeError> {
+        let mut expanded = Vec::new();
+        for mut row in rows {
+            let items = match self.classify(row.pats[col]) {
+                PatKind::Tuple(items) if items.len() == arity => items.iter().map(Some).collect(),
+                PatKind::Wild => vec![None; arity],
+                PatKind::Bind(name) => {
+                    row.bindings.push((name, occs[col]));
+                    vec![None; arity]
+                }
+                _ => {
+                    return Err(BytecodeError {
+                        message: "tuple pattern does not match the other patterns in its column"
+                            .to_string(),
+                    });
+                }
+            };
+            row.pats.splice(col..=col, items);
+            expanded.push(row);
+        }
+        let occs = self.expand_occurrences(&mut expanded, &occs, col, arity);
+        self.lower_decision(expanded, occs, targets)
+    }
+
+    fn lower_switch_column(
+        &mut self,
+        rows: Vec<MatchRow<'_>>,
+        occs: Vec<u32>,
+        col: usize,
+        targets: &mut [ArmTargets<'_>],
+    ) -> Result<(), BytecodeError> {
+        let mut heads: Vec<MatchHead> = Vec::new();
+        for row in &rows {
+            match self.classify(row.pats[col]) {
+                PatKind::Head(head, _) => {
+                    if !heads.contains(&head) {
+                        heads.push(head);
+                    }
+                }
+                PatKind::Tuple(_) => {
+                    return Err(BytecodeError {
+                        message: "tuple pattern does not match the other patterns in its column"
+                            .to_string(),
+                    });
+                }
+                PatKind::Wild | PatKind::Bind(_) => {}
+                PatKind::Or(_) => unreachable!("or-patterns are expanded before switching"),
+            }
+        }
+        let complete = self.heads_are_complete(&heads);
+
+        for (idx, head) in heads.iter().enumerate() {
+            let unconditional = complete && idx + 1 == heads.len();
+            let fail_patch = if unconditional {
+                None
+            } else {
+                Some(self.emit_head_test(occs[col], head))
+            };
+            let arity = self.head_arity(head, &rows, col);
+            let mut specialized = Vec::new();
+            for row in &rows {
+                let mut bindings = row.bindings.clone();
+                let fields = match self.classify(row.pats[col]) {
+                    PatKind::Head(other, args) if other == *head => {
+                        let mut fields = args.iter().map(Some).collect::<Vec<_>>();
+                        fields.resize(arity, None);
+                        fields
+                    }
+                    PatKind::Head(..) | PatKind::Tuple(_) | PatKind::Or(_) => continue,
+                    PatKind::Wild => vec![None; arity],
+                    PatKind::Bind(name) => {
+                        bindings.push((name, occs[col]));
+                        vec![None; arity]
+                    }
+                };
+                let mut pats = row.pats.clone();
+                pats.splice(col..=col, fields);
+                specialized.push(MatchRow {
+                    pats,
+                    bindings,
+                    arm: row.arm,
+                });
+            }
+            let sub_occs = self.expand_occurrences(&mut specialized, &occs, col, arity);
+            self.lower_decision(specialized, sub_occs, targets)?;
+            if let Some(patch) = fail_patch {
+                self.patch_jump_to_current(patch);
+            }
+        }
+
+        if !complete {
+            let mut defaults = Vec::new();
+            for row in &rows {
+                let mut bindings = row.bindings.clone();
+                match self.classify(row.pats[col]) {
+                    PatKind::Wild => {}
+                    PatKind::Bind(name) => bindings.push((name, occs[col])),
+                    _ => continue,
+                }
+                let mut pats = row.pats.clone();
+                pats.remove(col);
+                defaults.push(MatchRow {
+                    pats,
+                    bindings,
+                    arm: row.arm,
+                });
+            }
+            let mut rest = occs;
+            rest.remove(col);
+            self.lower_decision(defaults, rest, targets)?;
+        }
+        Ok(())
+    }
+
+    // Replaces column `col` with `width` sub-columns (fields or tuple items of
+    // the value in `occs[col]`). Sub-columns that no row inspects are dropped
+    // instead of being loaded into a slot.
+    fn expand_occurrences(
+        &mut self,
+        rows: &mut [MatchRow<'_>],
+        occs: &[u32],
+        col: usize,
+        width: usize,
+    ) -> Vec<u32> {
+        let mut out = occs[..col].to_vec();
+        let mut keep = Vec::new();
+        for idx in 0..width {
+            let used = rows
+                .iter()
+                .any(|row| !matches!(self.classify(row.pats[col + idx]), PatKind::Wild));
+            if used {
+                out.push(self.store_field(occs[col], idx));
+            }
+            keep.push(used);
+        }
+        for row in rows.iter_mut() {
+            let mut idx = 0;
+            row.pats.retain(|_| {
+                let in_range = idx >= col && idx < col + width;
+                let retained = !in_range || keep[idx - col];
+                idx += 1;
+                retained
+            });
+        }
+        out.extend_from_slice(&occs[col + 1..]);
+        out
+    }
+
+    fn head_arity(&self, head: &MatchHead, rows: &[MatchRow<'_>], col: usize) -> usize {
+        let MatchHead::Ctor(name) = head else {
+            return 0;
+        };
+        if let Some(info) = self.ctx.ctors.get(name) {
+            return info.arity;
+        }
+        rows.iter()
+            .filter_map(|row| match self.classify(row.pats[col]) {
+                PatKind::Head(other, args) if other == *head => Some(args.len()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn heads_are_complete(&self, heads: &[MatchHead]) -> bool {
+        match heads.first() {
+            Some(MatchHead::Ctor(name)) => self.ctx.ctors.get(name).is_some_and(|info| {
+                info.family
+                    .iter()
+                    .all(|ctor| heads.contains(&MatchHead::Ctor(ctor.clone())))
+            }),
+            Some(MatchHead::Bool(_)) => {
+                heads.contains(&MatchHead::Bool(true)) && heads.contains(&MatchHead::Bool(false))
+            }
+            _ => false,
+        }
+    }
+
+    // Emits a test of the value in `slot` against `head`, falling through when
+    // it matches. Returns the patch position of the jump taken on mismatch.
+    fn emit_head_test(&mut self, slot: u32, head: &MatchHead) -> usize {
+        self.code.push(OpCode::LoadLocal as u8);
+        self.code.extend_from_slice(&slot.to_le_bytes());
+        match head {
+            MatchHead::Ctor(name) => {
+                let tag_id = self.intern_string(name);
+                let hit = self.emit_jump_if_tag_placeholder(tag_id);
+                let miss = self.emit_jump_placeholder(OpCode::Jump);
+                self.patch_jump_to_current(hit);
+                miss
+            }
+            MatchHead::Bool(true) => self.emit_jump_placeholder(OpCode::JumpIfFalse),
+            MatchHead::Bool(false) => {
+                let hit = self.emit_jump_placeholder(OpCode::JumpIfFalse);
+                let miss = self.emit_jump_placeholder(OpCode::Jump);
+                self.patch_jump_to_current(hit);
+                miss
+            }
+            MatchHead::Int(v) => {
+                self.code.push(OpCode::PushInt as u8);
+                self.code.extend_from_slice(&v.to_le_bytes());
+                self.emit_equality_test()
+            }
+            MatchHead::Str(v) => {
+                let id = self.intern_string(v);
+                self.code.push(OpCode::PushString as u8);
+                self.code.extend_from_slice(&id.to_le_bytes());
+                self.emit_equality_test()
+            }
+        }
+    }
+
+    fn emit_equality_test(&mut self) -> usize {
+        self.code.push(OpCode::CallBuiltin as u8);
+        self.code
+            .push(builtin_id("==").expect("== is a builtin operator"));
+        self.code.push(2);
+        self.emit_jump_placeholder(OpCode::JumpIfFalse)
+    }
+
+    fn classify<'p>(&self, pat: Option<&'p Pattern>) -> PatKind<'p> {
+        let Some(pat) = pat else {
+            return PatKind::Wild;
+        };
+        match pat {
+            Pattern::Wildcard(_) => PatKind::Wild,
+            Pattern::Paren { inner, .. } => self.classify(Some(inner)),
+            Pattern::Name(id) => {
+                let name = id_text(id, self.ctx.symtab.as_deref());
+                if self.ctx.ctors.contains_key(&name) {
+                    PatKind::Head(MatchHead::Ctor(name), &[])
+                } else {
+                    PatKind::Bind(name)
+                }
+            }
+            Pattern::Ctor { name, args, .. } => PatKind::Head(
+                MatchHead::Ctor(id_text(name, self.ctx.symtab.as_deref())),
+                args,
+            ),
+            Pattern::Literal(Literal::Bool(v, _)) => PatKind::Head(MatchHead::Bool(*v), &[]),
+            Pattern::Literal(Literal::Int(v, _)) => PatKind::Head(MatchHead::Int(*v), &[]),
+            Pattern::Literal(Literal::String(v, _)) => {
+                PatKind::Head(MatchHead::Str(v.clone()), &[])
+            }
+            Pattern::Tuple { items, .. } => PatKind::Tuple(items),
+            Pattern::Or { alts, .. } => PatKind::Or(alts),
+        }
+    }
+}
+
 fn restore_local(locals: &mut BTreeMap<String, u32>, name: &str, prev: Option<u32>) {
     if let Some(old) = prev {
         locals.insert(name.to_string(), old);
@@ -628,6 +1580,79 @@ fn restore_local(locals: &mut BTreeMap<String, u32>, name: &str, prev: Option<u3
     }
 }
 
+// Ensures in `expr` that belong to the enclosing function; lambdas check
+// their own.
+fn collect_ensures(expr: &Expr, out: &mut Vec<Expr>) {
+    match expr {
+        Expr::Ensure { .. } => out.push(expr.clone()),
+        Expr::Lambda { .. } | Expr::Unit(_) | Expr::Name(_) | Expr::Literal(_) => {}
+        Expr::Block { prefix, tail, .. } => {
+            for item in prefix {
+                collect_ensures(item, out);
+            }
+            collect_ensures(tail, out);
+        }
+        Expr::Let { value, body, .. } | Expr::LetPattern { value, body, .. } => {
+            collect_ensures(value, out);
+            collect_ensures(body, out);
+        }
+        Expr::LetRec { body, .. } => collect_ensures(body, out),
+        Expr::If {
+            cond,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            collect_ensures(cond, out);
+            collect_ensures(then_branch, out);
+            collect_ensures(else_branch, out);
+        }
+        Expr::Match {
+            scrutinee, arms, ..
+        } => {
+            collect_ensures(scrutinee, out);
+            for arm in arms {
+                if let Some(guard) = &arm.guard {
+                    collect_ensures(guard, out);
+                }
+                collect_ensures(&arm.expr, out);
+            }
+        }
+        Expr::Call { callee, args, .. } => {
+            collect_ensures(callee, out);
+            for arg in args {
+                collect_ensures(arg, out);
+            }
+        }
+        Expr::NameApp { args, .. } | Expr::Tuple { items: args, .. } => {
+            for arg in args {
+                collect_ensures(arg, out);
+            }
+        }
+        Expr::Assert { cond, msg, .. } => {
+            collect_ensures(cond, out);
+            if let Some(msg) = msg {
+                collect_ensures(msg, out);
+            }
+        }
+        Expr::Require { expr, .. }
+        | Expr::Paren { inner: expr, .. }
+        | Expr::Try { inner: expr, .. }
+        | Expr::Field { base: expr, .. } => collect_ensures(expr, out),
+        Expr::Record { fields, .. } => {
+            for field in fields {
+                collect_ensures(&field.value, out);
+            }
+        }
+        Expr::Update { base, fields, .. } => {
+            collect_ensures(base, out);
+            for field in fields {
+                collect_ensures(&field.value, out);
+            }
+        }
+    }
+}
+
 fn capture_plan(
     locals: &BTreeMap<String, u32>,
     params: &[Param],
@@ -644,18 +1669,80 @@ fn capture_plan(
         .collect()
 }
 
-fn collect_ctors(program: &Program) -> HashSet<String> {
-    let mut set = HashSet::new();
-    set.insert("Ok".to_string());
-    set.insert("Er".to_string());
+// Every ADT family in declaration order, builtins first, plus per-constructor
+// lowering info.
+fn collect_ctors(program: &Program) -> (Vec<Vec<String>>, HashMap<String, CtorInfo>) {
+    let symtab = program.module.symtab.as_deref();
+    let mut families = Vec::new();
+    let mut infos = HashMap::new();
+    let result_family = vec!["Ok".to_string(), "Er".to_string()];
+    for name in &result_family {
+        infos.insert(
+            name.clone(),
+            CtorInfo {
+                arity: 1,
+                family: result_family.clone(),
+                field_names: Vec::new(),
+            },
+        );
+    }
+    families.push(result_family);
+    let declared = program
+        .module
+        .decls
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Type(td) => Some(td),
+            _ => None,
+        })
+        .flat_map(|td| td.ctors.iter().map(|ctor| id_text(&ctor.name, symtab)))
+        .collect::<HashSet<_>>();
+    // Builtin `?T` constructors, each unless the module declares its own
+    // constructor with that name.
+    let optional_family = vec!["None".to_string(), "Some".to_string()];
+    let mut builtin_optional = Vec::new();
+    for (name, arity) in optional_family.iter().zip([0, 1]) {
+        if declared.contains(name) {
+            continue;
+        }
+        infos.insert(
+            name.clone(),
+            CtorInfo {
+                arity,
+                family: optional_family.clone(),
+                field_names: Vec::new(),
+            },
+        );
+        builtin_optional.push(name.clone());
+    }
+    if !builtin_optional.is_empty() {
+        families.push(builtin_optional);
+    }
     for decl in &program.module.decls {
         if let Decl::Type(td) = decl {
-            for ctor in &td.ctors {
-                set.insert(id_text(&ctor.name, program.module.symtab.as_deref()));
+            let family = td
+                .ctors
+                .iter()
+                .map(|ctor| id_text(&ctor.name, symtab))
+                .collect::<Vec<_>>();
+            for (ctor, name) in td.ctors.iter().zip(&family) {
+                infos.insert(
+                    name.clone(),
+                    CtorInfo {
+                        arity: ctor.fields.len(),
+                        family: family.clone(),
+                        field_names: ctor
+                            .field_names
+                            .iter()
+                            .map(|field| id_text(field, symtab))
+                            .collect(),
+                    },
+                );
             }
+            families.push(family);
         }
     }
-    set
+    (families, infos)
 }
 
 fn builtin_id(name: &str) -> Option<u8> {
@@ -669,6 +1756,14 @@ fn builtin_id(name: &str) -> Option<u8> {
         "stringify" => Some(7),
         "run" => Some(8),
         "get" => Some(9),
+        "request" => Some(10),
+        "run_capture" => Some(11),
+        "list_dir" => Some(12),
+        "exists" => Some(13),
+        "append" => Some(14),
+        "mkdir" => Some(15),
+        "remove" => Some(16),
+        "read_lines" => Some(17),
         "+" => Some(20),
         "-" => Some(21),
         "*" => Some(22),
@@ -701,6 +1796,14 @@ fn builtin_name(id: u8) -> Option<&'static str> {
         7 => Some("stringify"),
         8 => Some("run"),
         9 => Some("get"),
+        10 => Some("request"),
+        11 => Some("run_capture"),
+        12 => Some("list_dir"),
+        13 => Some("exists"),
+        14 => Some("append"),
+        15 => Some("mkdir"),
+        16 => Some("remove"),
+        17 => Some("read_lines"),
         20 => Some("+"),
         21 => Some("-"),
         22 => Some("*"),
@@ -723,20 +1826,55 @@ fn builtin_name(id: u8) -> Option<&'static str> {
 }
 
 pub fn encode(decoded: &DecodedBytecode) -> Vec<u8> {
-    encode_parts(&decoded.strings, &decoded.functions, decoded.entry_fn)
+    encode_parts(
+        &decoded.metadata,
+        &decoded.strings,
+        &decoded.ctor_families,
+        &decoded.functions,
+        decoded.entry_fn,
+    )
 }
 
 pub fn decode(bytecode: &[u8]) -> Result<DecodedBytecode, DecodeError> {
     let mut cursor = 0usize;
-    if bytecode.len() < 4 || &bytecode[0..4] != MAGIC {
-        return Err(DecodeError {
-            code: DecodeErrorCode::InvalidHeader,
-            offset: 0,
-            message: "invalid bytecode header".to_string(),
-        });
-    }
+    let legacy = match bytecode.get(0..4) {
+        Some(magic) if magic == MAGIC => false,
+        Some(magic) if magic == MAGIC_V1 => true,
+        _ => {
+            return Err(DecodeError {
+                code: DecodeErrorCode::InvalidHeader,
+                offset: 0,
+                message: "invalid bytecode header".to_string(),
+            });
+        }
+    };
     cursor += 4;
 
+    let mut metadata = BTreeMap::new();
+    if !legacy {
+        let nmeta = read_u32(bytecode, &mut cursor)? as usize;
+        let remain = bytecode.len().saturating_sub(cursor);
+        if nmeta > remain / 8 {
+            return Err(DecodeError {
+                code: DecodeErrorCode::InvalidLength,
+                offset: cursor,
+                message: "metadata count exceeds stream capacity".to_string(),
+            });
+        }
+        for _ in 0..nmeta {
+            let offset = cursor;
+            let key = read_string(bytecode, &mut cursor, "metadata")?;
+            let value = read_string(bytecode, &mut cursor, "metadata")?;
+            if metadata.insert(key, value).is_some() {
+                return Err(DecodeError {
+                    code: DecodeErrorCode::InvalidHeader,
+                    offset,
+                    message: "duplicate bytecode metadata key".to_string(),
+                });
+            }
+        }
+    }
+
     let nstrings = read_u32(bytecode, &mut cursor)? as usize;
     let remain = bytecode.len().saturating_sub(cursor);
     if nstrings > remain / 4 {
@@ -748,28 +1886,17 @@ pub fn decode(bytecode: &[u8]) -> Result<DecodedBytecode, DecodeError> {
     }
     let mut strings = Vec::with_capacity(nstrings);
     for _ in 0..nstrings {
-        let len = read_u32(bytecode, &mut cursor)? as usize;
-        let end = cursor.checked_add(len).ok_or_else(|| DecodeError {
-            code: DecodeErrorCode::InvalidLength,
-            offset: cursor,
-            message: "string length overflow".to_string(),
-        })?;
-        if end > bytecode.len() {
-            return Err(DecodeError {
-                code: DecodeErrorCode::Truncated,
-                offset: cursor,
-                message: "corrupt bytecode string table".to_string(),
-            });
-        }
-        let s = std::str::from_utf8(&bytecode[cursor..end]).map_err(|_| DecodeError {
-            code: DecodeErrorCode::InvalidUtf8,
-            offset: cursor,
-            message: "bytecode string table contains invalid utf-8".to_string(),
-        })?;
-        strings.push(s.to_string());
-        cursor = end;
+        strings.push(read_string(bytecode, &mut cursor, "string table")?);
     }
 
+    let ctor_families = if !legacy {
+        read_ctor_families(bytecode, &mut cursor, strings.len())?
+    } else {
+        // Older compilers interned every constructor first, in declaration
+        // order, so one family spanning the string table ranks them the same.
+        vec![(0..strings.len() as u32).collect()]
+    };
+
     let nfuncs = read_u32(bytecode, &mut cursor)? as usize;
     let remain = bytecode.len().saturating_sub(cursor);
     if nfuncs > remain / 6 {
@@ -823,12 +1950,57 @@ pub fn decode(bytecode: &[u8]) -> Result<DecodedBytecode, DecodeError> {
     validate_function_code(&strings, &functions)?;
 
     Ok(DecodedBytecode {
+        metadata,
         strings,
+        ctor_families,
         functions,
         entry_fn,
     })
 }
 
+fn read_ctor_families(
+    bytecode: &[u8],
+    cursor: &mut usize,
+    nstrings: usize,
+) -> Result<Vec<Vec<u32>>, DecodeError> {
+    let nfamilies = read_u32(bytecode, cursor)? as usize;
+    let remain = bytecode.len().saturating_sub(*cursor);
+    if nfamilies > remain / 4 {
+        return Err(DecodeError {
+            code: DecodeErrorCode::InvalidLength,
+            offset: *cursor,
+            message: "constructor family count exceeds stream capacity".to_string(),
+        });
+    }
+    let mut families = Vec::with_capacity(nfamilies);
+    for _ in 0..nfamilies {
+        let nctors = read_u32(bytecode, cursor)? as usize;
+        let remain = bytecode.len().saturating_sub(*cursor);
+        if nctors > remain / 4 {
+            return Err(DecodeError {
+                code: DecodeErrorCode::InvalidLength,
+                offset: *cursor,
+                message: "constructor count exceeds stream capacity".to_string(),
+            });
+        }
+        let mut family = Vec::with_capacity(nctors);
+        for _ in 0..nctors {
+            let offset = *cursor;
+            let tag = read_u32(bytecode, cursor)?;
+            if tag as usize >= nstrings {
+                return Err(DecodeError {
+                    code: DecodeErrorCode::InvalidIndex,
+                    offset,
+                    message: "constructor tag index out of bounds".to_string(),
+                });
+            }
+            family.push(tag);
+        }
+        families.push(family);
+    }
+    Ok(families)
+}
+
 fn validate_function_code(
     strings: &[String],
     functions: &[FunctionBytecode],
@@ -915,7 +2087,7 @@ fn validate_function_code(
                         });
                     }
                 }
-                OpCode::GetAdtField | OpCode::CallClosure => {
+                OpCode::GetAdtField | OpCode::CallClosure | OpCode::MkTuple => {
                     let _ = read_u8(code, &mut ip)?;
                 }
                 OpCode::CallFn | OpCode::MkClosure => {
@@ -935,6 +2107,29 @@ fn validate_function_code(
     Ok(())
 }
 
+fn read_string(bytes: &[u8], cursor: &mut usize, section: &str) -> Result<String, DecodeError> {
+    let len = read_u32(bytes, cursor)? as usize;
+    let end = cursor.checked_add(len).ok_or_else(|| DecodeError {
+        code: DecodeErrorCode::InvalidLength,
+        offset: *cursor,
+        message: "string length overflow".to_string(),
+    })?;
+    if end > bytes.len() {
+        return Err(DecodeError {
+            code: DecodeErrorCode::Truncated,
+            offset: *cursor,
+            message: format!("corrupt bytecode {section}"),
+        });
+    }
+    let s = std::str::from_utf8(&bytes[*cursor..end]).map_err(|_| DecodeError {
+        code: DecodeErrorCode::InvalidUtf8,
+        offset: *cursor,
+        message: format!("bytecode {section} contains invalid utf-8"),
+    })?;
+    *cursor = end;
+    Ok(s.to_string())
+}
+
 fn read_u8(bytes: &[u8], cursor: &mut usize) -> Result<u8, DecodeError> {
     if *cursor >= bytes.len() {
         return Err(DecodeError {
@@ -976,14 +2171,30 @@ fn read_i64(bytes: &[u8], cursor: &mut usize) -> Result<i64, DecodeError> {
     Ok(i64::from_le_bytes(buf))
 }
 
-fn encode_parts(strings: &[String], functions: &[FunctionBytecode], entry_fn: u32) -> Vec<u8> {
+fn encode_parts(
+    metadata: &BTreeMap<String, String>,
+    strings: &[String],
+    ctor_families: &[Vec<u32>],
+    functions: &[FunctionBytecode],
+    entry_fn: u32,
+) -> Vec<u8> {
     let mut out = Vec::new();
     out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    for (key, value) in metadata {
+        write_string(&mut out, key);
+        write_string(&mut out, value);
+    }
     out.extend_from_slice(&(strings.len() as u32).to_le_bytes());
     for s in strings {
-        let bytes = s.as_bytes();
-        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
-        out.extend_from_slice(bytes);
+        write_string(&mut out, s);
+    }
+    out.extend_from_slice(&(ctor_families.len() as u32).to_le_bytes());
+    for family in ctor_families {
+        out.extend_from_slice(&(family.len() as u32).to_le_bytes());
+        for tag in family {
+            out.extend_from_slice(&tag.to_le_bytes());
+        }
     }
     out.extend_from_slice(&(functions.len() as u32).to_le_bytes());
     for f in functions {
@@ -995,3 +2206,8 @@ fn encode_parts(strings: &[String], functions: &[FunctionBytecode], entry_fn: u3
     out.extend_from_slice(&entry_fn.to_le_bytes());
     out
 }
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
@@ -1,16 +1,54 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::ast::Span;
-use crate::ast::{Decl, Program};
-use crate::bytecode;
-use crate::fmt::{FmtMode, collect_mu_files, parse_and_format_mode};
-use crate::parser::{ParseError, parse_str};
-use crate::typecheck::{TypeError, check_program_with_modules, validate_modules};
-use crate::vm::run_bytecode;
+use crate::ast::{Decl, Program, Span};
+use crate::bytecode::{self, Profile};
+use crate::diagnostic::{self, DiagnosticFormat, Note};
+use crate::fmt::{FmtMode, collect_mu_files, format_program_mode};
+use crate::http_fixture::{HttpFixtureHost, HttpFixtures};
+use crate::parser::{parse_str, parse_str_recovering};
+use crate::typecheck::{check_program_with_modules, validate_modules};
+use crate::vfs::{VfsHost, VirtualFs};
+use crate::vm::{DEFAULT_FUEL, RealHost, VmHost, run_bytecode_with_fuel_and_host};
+
+const HELP: &str = "muc - muScript compiler toolchain (v0.2)\n\nUSAGE:\n  muc fmt <file|dir> [--mode=readable|compressed] [--check] [--format=short|rich|json]\n  muc check <file|dir> [--format=short|rich|json]\n  muc fix <file|dir> [--format=short|rich|json]\n  muc run <file.mu|file.mub> [--format=short|rich|json] [--vfs=<dir|manifest.json>] [--vfs-out=<manifest.json>] [--http-fixtures=<fixtures.json>|--http-record=<fixtures.json>] [--release] [-- args...]\n  muc build <file.mu> -o <out.mub> [--release] [--format=short|rich|json]\n";
+const RUN_USAGE: &str = "usage: muc run <file.mu|file.mub> [--format=short|rich|json] [--vfs=<dir|manifest.json>] [--vfs-out=<manifest.json>] [--http-fixtures=<fixtures.json>|--http-record=<fixtures.json>] [--release] [-- args...]";
+const BUILD_USAGE: &str =
+    "usage: muc build <file.mu> -o <out.mub> [--release] [--format=short|rich|json]";
+const FMT_USAGE: &str =
+    "usage: muc fmt <file|dir> [--mode=readable|compressed] [--check] [--format=short|rich|json]";
+const CHECK_USAGE: &str = "usage: muc check <file|dir> [--format=short|rich|json]";
+const FIX_USAGE: &str = "usage: muc fix <file|dir> [--format=short|rich|json]";
+
+#[derive(Debug)]
+struct FmtOptions {
+    path: PathBuf,
+    check: bool,
+    mode: FmtMode,
+    format: DiagnosticFormat,
+}
+
+#[derive(Debug, Default)]
+struct RunOptions {
+    file: PathBuf,
+    args: Vec<String>,
+    vfs: Option<PathBuf>,
+    vfs_out: Option<PathBuf>,
+    http_fixtures: Option<PathBuf>,
+    http_record: Option<PathBuf>,
+    profile: Profile,
+    format: DiagnosticFormat,
+}
 
-const HELP: &str = "muc - muScript compiler toolchain (v0.2)\n\nUSAGE:\n  muc fmt <file|dir> [--mode=readable|compressed] [--check]\n  muc check <file|dir>\n  muc run <file.mu|file.mub> [-- args...]\n  muc build <file.mu> -o <out.mub>\n";
+#[derive(Debug, Default)]
+struct BuildOptions {
+    file: PathBuf,
+    output: PathBuf,
+    profile: Profile,
+    format: DiagnosticFormat,
+}
 
 pub fn run() -> Result<(), String> {
     let mut args: Vec<String> = env::args().collect();
@@ -28,23 +66,43 @@ pub fn run() -> Result<(), String> {
     let command_args = &args[1..];
 
     match command.as_str() {
-        "fmt" => {
-            parse_fmt(command_args).and_then(|(path, check, mode)| cmd_fmt(&path, check, mode))
-        }
-        "check" => parse_check(command_args).and_then(|path| cmd_check(&path)),
-        "run" => parse_run(command_args).and_then(|(file, rest)| cmd_run(&file, &rest)),
-        "build" => parse_build(command_args).and_then(|(file, out)| cmd_build(&file, &out)),
+        "fmt" => parse_fmt(command_args).and_then(|opts| {
+            let mut reporter = Reporter::new(opts.format);
+            let result = cmd_fmt(&opts, &mut reporter);
+            reporter.finish(None, result)
+        }),
+        "check" => parse_path_and_format(command_args, CHECK_USAGE).and_then(|(path, format)| {
+            let mut reporter = Reporter::new(format);
+            let result = cmd_check(&path, &mut reporter);
+            reporter.finish(None, result)
+        }),
+        "fix" => parse_path_and_format(command_args, FIX_USAGE).and_then(|(path, format)| {
+            let mut reporter = Reporter::new(format);
+            let result = cmd_fix(&path, &mut reporter);
+            reporter.finish(None, result)
+        }),
+        "run" => parse_run(command_args).and_then(|opts| {
+            let mut reporter = Reporter::new(opts.format);
+            let result = cmd_run(&opts, &mut reporter);
+            reporter.finish(Some(&opts.file), result)
+        }),
+        "build" => parse_build(command_args).and_then(|opts| {
+            let mut reporter = Reporter::new(opts.format);
+            let result = cmd_build(&opts, &mut reporter);
+            reporter.finish(Some(&opts.file), result)
+        }),
         other => Err(format!("unknown command `{other}`\n\n{HELP}")),
     }
 }
 
-fn parse_fmt(args: &[String]) -> Result<(PathBuf, bool, FmtMode), String> {
+fn parse_fmt(args: &[String]) -> Result<FmtOptions, String> {
     if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
-        return Err("usage: muc fmt <file|dir> [--mode=readable|compressed] [--check]".to_string());
+        return Err(FMT_USAGE.to_string());
     }
     let mut path: Option<PathBuf> = None;
     let mut check = false;
     let mut mode = FmtMode::Readable;
+    let mut format = DiagnosticFormat::default();
     for arg in args {
         if arg == "--check" {
             check = true;
@@ -58,63 +116,139 @@ fn parse_fmt(args: &[String]) -> Result<(PathBuf, bool, FmtMode), String> {
                     ));
                 }
             };
+        } else if let Some(v) = arg.strip_prefix("--format=") {
+            format = parse_format(v)?;
         } else if path.is_none() {
             path = Some(PathBuf::from(arg));
         } else {
             return Err(format!("unknown argument for fmt: `{arg}`"));
         }
     }
-    let path = path.ok_or_else(|| {
-        "usage: muc fmt <file|dir> [--mode=readable|compressed] [--check]".to_string()
-    })?;
-    Ok((path, check, mode))
+    let path = path.ok_or_else(|| FMT_USAGE.to_string())?;
+    Ok(FmtOptions {
+        path,
+        check,
+        mode,
+        format,
+    })
 }
 
-fn parse_check(args: &[String]) -> Result<PathBuf, String> {
-    if args.len() != 1 || args[0] == "--help" || args[0] == "-h" {
-        return Err("usage: muc check <file|dir>".to_string());
+fn parse_path_and_format(
+    args: &[String],
+    usage: &str,
+) -> Result<(PathBuf, DiagnosticFormat), String> {
+    let mut path = None;
+    let mut format = DiagnosticFormat::default();
+    for arg in args {
+        if arg == "--help" || arg == "-h" {
+            return Err(usage.to_string());
+        } else if let Some(v) = arg.strip_prefix("--format=") {
+            format = parse_format(v)?;
+        } else if path.is_none() {
+            path = Some(PathBuf::from(arg));
+        } else {
+            return Err(usage.to_string());
+        }
     }
-    Ok(PathBuf::from(&args[0]))
+    let path = path.ok_or_else(|| usage.to_string())?;
+    Ok((path, format))
+}
+
+fn parse_format(name: &str) -> Result<DiagnosticFormat, String> {
+    DiagnosticFormat::from_name(name)
+        .ok_or_else(|| format!("invalid --format `{name}`; expected `short`, `rich` or `json`"))
 }
 
-fn parse_run(args: &[String]) -> Result<(PathBuf, Vec<String>), String> {
+fn parse_run(args: &[String]) -> Result<RunOptions, String> {
     if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
-        return Err("usage: muc run <file.mu|file.mub> [-- args...]".to_string());
+        return Err(RUN_USAGE.to_string());
     }
-    let file = PathBuf::from(&args[0]);
-    if let Some((idx, _)) = args.iter().enumerate().find(|(_, a)| *a == "--") {
-        return Ok((file, args[(idx + 1)..].to_vec()));
+    let mut opts = RunOptions {
+        file: PathBuf::from(&args[0]),
+        ..RunOptions::default()
+    };
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--" {
+            opts.args = rest.cloned().collect();
+            break;
+        } else if let Some(v) = arg.strip_prefix("--vfs=") {
+            opts.vfs = Some(PathBuf::from(v));
+        } else if let Some(v) = arg.strip_prefix("--vfs-out=") {
+            opts.vfs_out = Some(PathBuf::from(v));
+        } else if let Some(v) = arg.strip_prefix("--http-fixtures=") {
+            opts.http_fixtures = Some(PathBuf::from(v));
+        } else if let Some(v) = arg.strip_prefix("--http-record=") {
+            opts.http_record = Some(PathBuf::from(v));
+        } else if arg == "--release" {
+            opts.profile = Profile::Release;
+        } else if let Some(v) = arg.strip_prefix("--format=") {
+            opts.format = parse_format(v)?;
+        } else {
+            return Err(format!("unknown argument for run: `{arg}`\n{RUN_USAGE}"));
+        }
+    }
+    if opts.vfs_out.is_some() && opts.vfs.is_none() {
+        return Err("`--vfs-out` requires `--vfs`".to_string());
     }
-    Ok((file, Vec::new()))
+    if opts.http_fixtures.is_some() && opts.http_record.is_some() {
+        return Err("`--http-fixtures` and `--http-record` are mutually exclusive".to_string());
+    }
+    Ok(opts)
 }
 
-fn parse_build(args: &[String]) -> Result<(PathBuf, PathBuf), String> {
+fn parse_build(args: &[String]) -> Result<BuildOptions, String> {
     if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
-        return Err("usage: muc build <file.mu> -o <out.mub>".to_string());
+        return Err(BUILD_USAGE.to_string());
+    }
+    let mut opts = BuildOptions::default();
+    let mut positional = Vec::new();
+    for arg in args {
+        if arg == "--release" {
+            opts.profile = Profile::Release;
+        } else if let Some(v) = arg.strip_prefix("--format=") {
+            opts.format = parse_format(v)?;
+        } else {
+            positional.push(arg);
+        }
     }
-    if args.len() != 3 || args[1] != "-o" {
-        return Err("usage: muc build <file.mu> -o <out.mub>".to_string());
+    let [file, flag, output] = positional[..] else {
+        return Err(BUILD_USAGE.to_string());
+    };
+    if flag != "-o" {
+        return Err(BUILD_USAGE.to_string());
     }
-    Ok((PathBuf::from(&args[0]), PathBuf::from(&args[2])))
+    opts.file = PathBuf::from(file);
+    opts.output = PathBuf::from(output);
+    Ok(opts)
 }
 
-fn cmd_fmt(path: &Path, check: bool, mode: FmtMode) -> Result<(), String> {
+fn cmd_fmt(opts: &FmtOptions, reporter: &mut Reporter) -> Result<(), String> {
+    let FmtOptions {
+        path, check, mode, ..
+    } = opts;
     let files = collect_mu_files(path)?;
     if files.is_empty() {
         return Err(format!("no .mu files found under {}", path.display()));
     }
 
     let mut changed = Vec::new();
+    let mut syntax_errors = 0;
 
     for file in files {
         let src = fs::read_to_string(&file)
             .map_err(|e| format!("failed reading {}: {e}", file.display()))?;
-        let formatted =
-            parse_and_format_mode(&src, mode).map_err(|e| format_parse_error(&file, &src, &e))?;
+        let formatted = match parse_reporting_all(&file, &src, reporter) {
+            Ok(program) => format_program_mode(&program, *mode),
+            Err(count) => {
+                syntax_errors += count;
+                continue;
+            }
+        };
 
         if src != formatted {
-            if check {
-                changed.push(file);
+            if *check {
+                changed.push((file, src, formatted));
             } else {
                 fs::write(&file, formatted)
                     .map_err(|e| format!("failed writing {}: {e}", file.display()))?;
@@ -123,62 +257,239 @@ fn cmd_fmt(path: &Path, check: bool, mode: FmtMode) -> Result<(), String> {
         }
     }
 
-    if check && !changed.is_empty() {
-        for file in changed {
-            eprintln!("would reformat {}", file.display());
+    if *check && !changed.is_empty() {
+        for (file, src, formatted) in changed {
+            if reporter.format == DiagnosticFormat::Json {
+                let (span, replacement) = diagnostic::minimal_edit(&src, &formatted);
+                let fix = Note::Fix {
+                    span,
+                    replacement,
+                    message: "apply the canonical formatting".to_string(),
+                };
+                reporter.report(
+                    &file,
+                    &src,
+                    FMT_CHECK_CODE,
+                    "file is not canonically formatted",
+                    span,
+                    &[fix],
+                );
+            } else {
+                eprintln!("would reformat {}", file.display());
+            }
+        }
+        if syntax_errors == 0 {
+            return Err("format check failed".to_string());
         }
-        return Err("format check failed".to_string());
+    }
+    if syntax_errors > 0 {
+        return Err(parse_failed(syntax_errors));
     }
 
     Ok(())
 }
 
-fn cmd_check(path: &Path) -> Result<(), String> {
-    let loaded = if path.is_file() {
-        load_entry_workspace(path)?
-    } else {
-        let files = collect_mu_files(path)?;
-        if files.is_empty() {
-            return Err(format!("no .mu files found under {}", path.display()));
+fn cmd_check(path: &Path, reporter: &mut Reporter) -> Result<(), String> {
+    let loaded = load_for_check(path, reporter)?;
+    check_loaded_modules(&loaded, reporter)?;
+
+    println!("check ok");
+    Ok(())
+}
+
+// Applies the fixes carried by diagnostics, reformats the files it changed
+// (keeping their formatting mode) and then checks what is left.
+fn cmd_fix(path: &Path, reporter: &mut Reporter) -> Result<(), String> {
+    let mut fixed = BTreeMap::<PathBuf, usize>::new();
+    for _ in 0..MAX_FIX_ROUNDS {
+        let loaded = load_for_check(path, reporter)?;
+        let mut applied = 0;
+        for (idx, edits) in collect_fixes(&loaded) {
+            let (file, src, _) = &loaded[idx];
+            let (text, count) = apply_edits(src, edits);
+            if count == 0 {
+                continue;
+            }
+            fs::write(file, text).map_err(|e| format!("failed writing {}: {e}", file.display()))?;
+            *fixed.entry(file.clone()).or_default() += count;
+            applied += count;
         }
-        load_programs(files)?
-    };
-    check_loaded_modules(&loaded)?;
+        if applied == 0 {
+            break;
+        }
+    }
+
+    for (file, count) in &fixed {
+        let src = fs::read_to_string(file)
+            .map_err(|e| format!("failed reading {}: {e}", file.display()))?;
+        if let Ok(program) = parse_str(&src) {
+            let mode = match program.module.symtab {
+                Some(_) => FmtMode::Compressed,
+                None => FmtMode::Readable,
+            };
+            fs::write(file, format_program_mode(&program, mode))
+                .map_err(|e| format!("failed writing {}: {e}", file.display()))?;
+        }
+        match count {
+            1 => println!("fixed {} (1 fix)", file.display()),
+            n => println!("fixed {} ({n} fixes)", file.display()),
+        }
+    }
 
+    let loaded = load_for_check(path, reporter)?;
+    check_loaded_modules(&loaded, reporter)?;
     println!("check ok");
     Ok(())
 }
 
-fn cmd_run(file: &PathBuf, args: &[String]) -> Result<(), String> {
+// A fix can expose errors an earlier, fail-fast phase was hiding, so `fix`
+// re-checks after applying; this bounds how often.
+const MAX_FIX_ROUNDS: usize = 8;
+
+// The `Note::Fix` edits of every type error in `loaded`, by file index.
+fn collect_fixes(loaded: &[(PathBuf, String, Program)]) -> BTreeMap<usize, Vec<(Span, String)>> {
+    let programs = loaded
+        .iter()
+        .map(|(_, _, program)| program.clone())
+        .collect::<Vec<_>>();
+    let errors = match validate_modules(&programs) {
+        Err((idx, err)) => vec![(idx, err)],
+        Ok(()) => programs
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, program)| {
+                let errors = check_program_with_modules(program, &programs).err();
+                errors.into_iter().flatten().map(move |err| (idx, err))
+            })
+            .collect(),
+    };
+    let mut fixes = BTreeMap::<usize, Vec<(Span, String)>>::new();
+    for (idx, err) in errors {
+        for note in err.notes {
+            if let Note::Fix {
+                span, replacement, ..
+            } = note
+            {
+                fixes.entry(idx).or_default().push((span, replacement));
+            }
+        }
+    }
+    fixes
+}
+
+// Applies the edits that do not overlap an earlier one; returns the new text
+// and how many were applied.
+fn apply_edits(src: &str, mut edits: Vec<(Span, String)>) -> (String, usize) {
+    edits.sort_by_key(|(span, _)| (span.start, span.end));
+    let mut kept: Vec<(Span, String)> = Vec::new();
+    for (span, replacement) in edits {
+        if span.end > src.len() || kept.last().is_some_and(|(prev, _)| span.start < prev.end) {
+            continue;
+        }
+        kept.push((span, replacement));
+    }
+    let mut out = src.to_string();
+    for (span, replacement) in kept.iter().rev() {
+        out.replace_range(span.start..span.end, replacement);
+    }
+    (out, kept.len())
+}
+
+// The modules `check` and `fix` work on: an entry file with the local modules
+// it imports, or every file under a directory.
+fn load_for_check(
+    path: &Path,
+    reporter: &mut Reporter,
+) -> Result<Vec<(PathBuf, String, Program)>, String> {
+    if path.is_file() {
+        return load_entry_workspace(path, reporter);
+    }
+    let files = collect_mu_files(path)?;
+    if files.is_empty() {
+        return Err(format!("no .mu files found under {}", path.display()));
+    }
+    load_programs(files, reporter)
+}
+
+fn cmd_run(opts: &RunOptions, reporter: &mut Reporter) -> Result<(), String> {
+    let file = &opts.file;
     let is_mub = file.extension().and_then(|s| s.to_str()) == Some("mub");
-    if is_mub {
-        let bytes =
-            fs::read(file).map_err(|e| format!("failed reading {}: {e}", file.display()))?;
-        return run_bytecode(&bytes, args).map_err(|e| e.to_string());
+    if is_mub && opts.profile == Profile::Release {
+        return Err(format!(
+            "`--release` only applies to .mu sources; {} keeps the profile it was built with",
+            file.display()
+        ));
     }
+    let bytecode = if is_mub {
+        fs::read(file).map_err(|e| format!("failed reading {}: {e}", file.display()))?
+    } else {
+        let loaded = load_entry_workspace(file, reporter)?;
+        check_loaded_modules(&loaded, reporter)?;
+        let program = entry_program(&loaded, file)?;
+        bytecode::compile_with_profile(&program, opts.profile)
+            .map_err(|e| format!("{}: {}", file.display(), e))?
+    };
 
-    let loaded = load_entry_workspace(file)?;
-    check_loaded_modules(&loaded)?;
-    let program = entry_program(&loaded, file)?;
-    let bytecode = bytecode::compile(&program).map_err(|e| format!("{}: {}", file.display(), e))?;
-    run_bytecode(&bytecode, args).map_err(|e| e.to_string())
+    if let Some(path) = &opts.http_fixtures {
+        let fixtures = HttpFixtures::load(path)?;
+        let (_, result) =
+            run_with_vfs(opts, &bytecode, HttpFixtureHost::replay(RealHost, fixtures))?;
+        return result;
+    }
+    if let Some(path) = &opts.http_record {
+        let (host, result) = run_with_vfs(opts, &bytecode, HttpFixtureHost::record(RealHost))?;
+        fs::write(path, host.fixtures.to_json())
+            .map_err(|e| format!("failed writing {}: {e}", path.display()))?;
+        return result;
+    }
+    let (_, result) = run_with_vfs(opts, &bytecode, RealHost)?;
+    result
+}
+
+fn run_with_vfs<H: VmHost>(
+    opts: &RunOptions,
+    bytecode: &[u8],
+    mut host: H,
+) -> Result<(H, Result<(), String>), String> {
+    let Some(vfs_path) = &opts.vfs else {
+        let result = run_bytecode_with_fuel_and_host(bytecode, &opts.args, DEFAULT_FUEL, &mut host);
+        return Ok((host, result.map_err(|e| e.to_string())));
+    };
+    let vfs = VirtualFs::load(vfs_path)?;
+    let mut host = VfsHost::new(host, vfs);
+    let result = run_bytecode_with_fuel_and_host(bytecode, &opts.args, DEFAULT_FUEL, &mut host);
+    if let Some(out) = &opts.vfs_out {
+        fs::write(out, host.vfs.to_json_manifest())
+            .map_err(|e| format!("failed writing {}: {e}", out.display()))?;
+    }
+    Ok((host.inner, result.map_err(|e| e.to_string())))
 }
 
-fn cmd_build(file: &Path, output: &Path) -> Result<(), String> {
-    let loaded = load_entry_workspace(file)?;
-    check_loaded_modules(&loaded)?;
+fn cmd_build(opts: &BuildOptions, reporter: &mut Reporter) -> Result<(), String> {
+    let BuildOptions {
+        file,
+        output,
+        profile,
+        ..
+    } = opts;
+    let loaded = load_entry_workspace(file, reporter)?;
+    check_loaded_modules(&loaded, reporter)?;
     let program = entry_program(&loaded, file)?;
-    let bytecode = bytecode::compile(&program).map_err(|e| format!("{}: {}", file.display(), e))?;
+    let bytecode = bytecode::compile_with_profile(&program, *profile)
+        .map_err(|e| format!("{}: {}", file.display(), e))?;
     fs::write(output, bytecode).map_err(|e| format!("failed writing {}: {e}", output.display()))?;
-    println!("built {}", output.display());
+    println!("built {} ({})", output.display(), profile.as_str());
     Ok(())
 }
 
-fn load_entry_workspace(entry_file: &Path) -> Result<Vec<(PathBuf, String, Program)>, String> {
+fn load_entry_workspace(
+    entry_file: &Path,
+    reporter: &mut Reporter,
+) -> Result<Vec<(PathBuf, String, Program)>, String> {
     let entry_src = fs::read_to_string(entry_file)
         .map_err(|e| format!("failed reading {}: {e}", entry_file.display()))?;
     let entry_program =
-        parse_str(&entry_src).map_err(|e| format_parse_error(entry_file, &entry_src, &e))?;
+        parse_reporting_all(entry_file, &entry_src, reporter).map_err(parse_failed)?;
     let mut loaded = vec![(entry_file.to_path_buf(), entry_src, entry_program)];
 
     let root = entry_file.parent().unwrap_or_else(|| Path::new("."));
@@ -222,17 +533,47 @@ fn load_entry_workspace(entry_file: &Path) -> Result<Vec<(PathBuf, String, Progr
     Ok(loaded)
 }
 
-fn load_programs(files: Vec<PathBuf>) -> Result<Vec<(PathBuf, String, Program)>, String> {
+fn load_programs(
+    files: Vec<PathBuf>,
+    reporter: &mut Reporter,
+) -> Result<Vec<(PathBuf, String, Program)>, String> {
     let mut loaded = Vec::new();
+    let mut failed = 0;
     for file in files {
         let src = fs::read_to_string(&file)
             .map_err(|e| format!("failed reading {}: {e}", file.display()))?;
-        let program = parse_str(&src).map_err(|e| format_parse_error(&file, &src, &e))?;
-        loaded.push((file, src, program));
+        match parse_reporting_all(&file, &src, reporter) {
+            Ok(program) => loaded.push((file, src, program)),
+            Err(n) => failed += n,
+        }
+    }
+    if failed > 0 {
+        return Err(parse_failed(failed));
     }
     Ok(loaded)
 }
 
+// Parses `src`, printing every syntax error; returns their count on failure.
+fn parse_reporting_all(path: &Path, src: &str, reporter: &mut Reporter) -> Result<Program, usize> {
+    match parse_str_recovering(src) {
+        (Some(program), errors) if errors.is_empty() => Ok(program),
+        (_, errors) => {
+            for err in &errors {
+                let code = err.code.as_str();
+                reporter.report(path, src, code, &err.message, err.span, &[]);
+            }
+            Err(errors.len())
+        }
+    }
+}
+
+fn parse_failed(count: usize) -> String {
+    match count {
+        1 => "parse failed with 1 syntax error".to_string(),
+        n => format!("parse failed with {n} syntax errors"),
+    }
+}
+
 fn collect_local_mu_files(root: &Path) -> Vec<PathBuf> {
     let mut files = Vec::new();
     let mut stack = vec![root.to_path_buf()];
@@ -258,17 +599,35 @@ fn collect_local_mu_files(root: &Path) -> Vec<PathBuf> {
     files
 }
 
-fn check_loaded_modules(loaded: &[(PathBuf, String, Program)]) -> Result<(), String> {
+fn check_loaded_modules(
+    loaded: &[(PathBuf, String, Program)],
+    reporter: &mut Reporter,
+) -> Result<(), String> {
     let programs = loaded
         .iter()
         .map(|(_, _, program)| program.clone())
         .collect::<Vec<_>>();
-    validate_modules(&programs).map_err(|e| format!("check failed: {e}"))?;
+    if let Err((idx, err)) = validate_modules(&programs) {
+        let (file, src, _) = &loaded[idx];
+        let code = err.code.as_str();
+        reporter.report(file, src, code, &err.message, err.span, &err.notes);
+        return Err("check failed with 1 type error".to_string());
+    }
+    let mut count = 0;
     for (file, src, program) in loaded {
-        check_program_with_modules(program, &programs)
-            .map_err(|e| format_type_error(file, src, &e))?;
+        if let Err(errors) = check_program_with_modules(program, &programs) {
+            for err in &errors {
+                let code = err.code.as_str();
+                reporter.report(file, src, code, &err.message, err.span, &err.notes);
+            }
+            count += errors.len();
+        }
+    }
+    match count {
+        0 => Ok(()),
+        1 => Err("check failed with 1 type error".to_string()),
+        n => Err(format!("check failed with {n} type errors")),
     }
-    Ok(())
 }
 
 fn entry_program(
@@ -330,44 +689,78 @@ fn builtin_module_names() -> [&'static str; 6] {
     ]
 }
 
-fn format_parse_error(path: &Path, src: &str, err: &ParseError) -> String {
-    let (line, col) = line_col(src, err.span);
-    format!(
-        "{}:{}:{}: {}: {}",
-        path.display(),
-        line,
-        col,
-        err.code.as_str(),
-        err.message
-    )
-}
-
-fn format_type_error(path: &Path, src: &str, err: &TypeError) -> String {
-    let (line, col) = line_col(src, err.span);
-    format!(
-        "{}:{}:{}: {}: {}",
-        path.display(),
-        line,
-        col,
-        err.code.as_str(),
-        err.message
-    )
-}
-
-fn line_col(src: &str, span: Span) -> (usize, usize) {
-    let target = span.start.min(src.len());
-    let mut line = 1usize;
-    let mut col = 1usize;
-    for (idx, ch) in src.char_indices() {
-        if idx >= target {
-            break;
+// Code of the `fmt --check` diagnostic for a file that would be reformatted.
+const FMT_CHECK_CODE: &str = "E5001";
+
+// Sends diagnostics to stderr: printed as they come for the text formats, or
+// gathered into the one document `finish` prints for `--format=json`.
+struct Reporter {
+    format: DiagnosticFormat,
+    json: Vec<serde_json::Value>,
+}
+
+impl Reporter {
+    fn new(format: DiagnosticFormat) -> Self {
+        Reporter {
+            format,
+            json: Vec::new(),
         }
-        if ch == '\n' {
-            line += 1;
-            col = 1;
-        } else {
-            col += 1;
+    }
+
+    fn report(
+        &mut self,
+        file: &Path,
+        src: &str,
+        code: &str,
+        message: &str,
+        span: Span,
+        notes: &[Note],
+    ) {
+        match self.format {
+            DiagnosticFormat::Json => self.json.push(diagnostic::to_json(
+                Some(file),
+                src,
+                Some(code),
+                message,
+                Some(span),
+                notes,
+            )),
+            format => {
+                let rendered = diagnostic::render(format, file, src, code, message, span, notes);
+                eprintln!("{rendered}");
+            }
+        }
+    }
+
+    // In JSON mode prints the document and empties the error, which the
+    // document already carries. A failure with no diagnostics behind it (a
+    // runtime trap, an I/O error) is added as one without a source location.
+    fn finish(self, file: Option<&Path>, result: Result<(), String>) -> Result<(), String> {
+        if self.format != DiagnosticFormat::Json {
+            return result;
+        }
+        let mut items = self.json;
+        if let Err(message) = &result
+            && items.is_empty()
+        {
+            let (code, message) = split_code(message);
+            items.push(diagnostic::to_json(file, "", code, message, None, &[]));
+        }
+        eprintln!("{}", diagnostic::json_report(result.is_ok(), items));
+        result.map_err(|_| String::new())
+    }
+}
+
+// Splits a leading `E1234: ` code off a runtime or decode error message.
+fn split_code(message: &str) -> (Option<&str>, &str) {
+    match message.split_once(": ") {
+        Some((code, rest))
+            if code.len() == 5
+                && code.starts_with('E')
+                && code[1..].bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (Some(code), rest)
         }
+        _ => (None, message),
     }
-    (line, col)
 }
@@ -0,0 +1,297 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde_json::{Map, Value, json};
+
+use crate::ast::Span;
+
+// Extra information attached to an error: a secondary source label, a
+// suggested replacement for a span of source, or a replacement safe to apply
+// without review (`muc fix` applies only those).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Note {
+    Label {
+        span: Span,
+        message: String,
+    },
+    Suggestion {
+        span: Span,
+        replacement: String,
+        message: String,
+    },
+    Fix {
+        span: Span,
+        replacement: String,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+    // One `file:line:col: CODE: message` line.
+    #[default]
+    Short,
+    // The message followed by the source lines it refers to, underlined.
+    Rich,
+    // One JSON document for the whole command; see `json_report`.
+    Json,
+}
+
+impl DiagnosticFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "short" => Some(DiagnosticFormat::Short),
+            "rich" => Some(DiagnosticFormat::Rich),
+            "json" => Some(DiagnosticFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Renders one error for stderr, including its `error` prefix. JSON output is
+// built with `to_json` instead; asking for it here gives the short form.
+pub fn render(
+    format: DiagnosticFormat,
+    path: &Path,
+    src: &str,
+    code: &str,
+    message: &str,
+    span: Span,
+    notes: &[Note],
+) -> String {
+    match format {
+        DiagnosticFormat::Short | DiagnosticFormat::Json => {
+            format!("error: {}", render_short(path, src, code, message, span))
+        }
+        DiagnosticFormat::Rich => render_rich(path, src, code, message, span, notes),
+    }
+}
+
+pub fn render_short(path: &Path, src: &str, code: &str, message: &str, span: Span) -> String {
+    let (line, col) = line_col(src, span.start);
+    format!("{}:{line}:{col}: {code}: {message}", path.display())
+}
+
+pub fn render_rich(
+    path: &Path,
+    src: &str,
+    code: &str,
+    message: &str,
+    span: Span,
+    notes: &[Note],
+) -> String {
+    let (line, col) = line_col(src, span.start);
+    let last_line = notes
+        .iter()
+        .filter_map(|note| match note {
+            Note::Label { span, .. } => Some(*span),
+            Note::Suggestion { .. } | Note::Fix { .. } => None,
+        })
+        .chain([span])
+        .map(|span| line_col(src, span.end.max(span.start)).0)
+        .max()
+        .unwrap_or(line);
+    let width = last_line.to_string().len();
+    let gutter = " ".repeat(width);
+
+    let mut out = format!(
+        "error[{code}]: {message}\n{gutter}--> {}:{line}:{col}\n",
+        path.display()
+    );
+    let _ = writeln!(out, "{gutter} |");
+    let mut shown = snippet(&mut out, src, span, '^', "", width, None);
+    for note in notes {
+        match note {
+            Note::Label { span, message } => {
+                shown = snippet(&mut out, src, *span, '-', message, width, shown);
+            }
+            Note::Suggestion { message, .. } | Note::Fix { message, .. } => {
+                let _ = writeln!(out, "{gutter} = help: {message}");
+            }
+        }
+    }
+    out
+}
+
+// Version of the `--format=json` schema, bumped on incompatible changes.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+// One diagnostic as a JSON object. `file` and `span` are absent for problems
+// without a source location (runtime traps, I/O failures); `code` is absent
+// for the latter. Suggestions and fixes become `fixes` holding the text edits
+// to apply; only the latter are marked `applicable`.
+pub fn to_json(
+    file: Option<&Path>,
+    src: &str,
+    code: Option<&str>,
+    message: &str,
+    span: Option<Span>,
+    notes: &[Note],
+) -> Value {
+    let mut labels = Vec::new();
+    let mut fixes = Vec::new();
+    for note in notes {
+        match note {
+            Note::Label { span, message } => {
+                let mut label = location_json(src, *span);
+                label.insert("message".to_string(), json!(message));
+                labels.push(Value::Object(label));
+            }
+            Note::Suggestion {
+                span,
+                replacement,
+                message,
+            } => fixes.push(fix_json(message, *span, replacement, false)),
+            Note::Fix {
+                span,
+                replacement,
+                message,
+            } => fixes.push(fix_json(message, *span, replacement, true)),
+        }
+    }
+    let mut item = Map::new();
+    item.insert("code".to_string(), json!(code));
+    item.insert("severity".to_string(), json!("error"));
+    item.insert(
+        "file".to_string(),
+        json!(file.map(|path| path.display().to_string())),
+    );
+    match span {
+        Some(span) => item.extend(location_json(src, span)),
+        None => {
+            for key in ["span", "line", "col", "end_line", "end_col"] {
+                item.insert(key.to_string(), Value::Null);
+            }
+        }
+    }
+    item.insert("message".to_string(), json!(message));
+    item.insert("labels".to_string(), Value::Array(labels));
+    item.insert("fixes".to_string(), Value::Array(fixes));
+    Value::Object(item)
+}
+
+// The document printed once per command in JSON mode.
+pub fn json_report(ok: bool, diagnostics: Vec<Value>) -> String {
+    json!({
+        "version": JSON_SCHEMA_VERSION,
+        "ok": ok,
+        "diagnostics": diagnostics,
+    })
+    .to_string()
+}
+
+// A suggestion replacing `before` with `after` by its smallest differing
+// range, so the edit stays readable when most of the text is unchanged.
+pub fn minimal_edit(before: &str, after: &str) -> (Span, String) {
+    let prefix = before
+        .char_indices()
+        .zip(after.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((idx, _), _)| idx)
+        .unwrap_or_else(|| before.len().min(after.len()));
+    let suffix = before[prefix..]
+        .chars()
+        .rev()
+        .zip(after[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    let span = Span {
+        start: prefix,
+        end: before.len() - suffix,
+    };
+    (span, after[prefix..after.len() - suffix].to_string())
+}
+
+fn location_json(src: &str, span: Span) -> Map<String, Value> {
+    let (line, col) = line_col(src, span.start);
+    let (end_line, end_col) = line_col(src, span.end.max(span.start));
+    let mut out = Map::new();
+    out.insert(
+        "span".to_string(),
+        json!({"start": span.start, "end": span.end}),
+    );
+    out.insert("line".to_string(), json!(line));
+    out.insert("col".to_string(), json!(col));
+    out.insert("end_line".to_string(), json!(end_line));
+    out.insert("end_col".to_string(), json!(end_col));
+    out
+}
+
+fn fix_json(message: &str, span: Span, replacement: &str, applicable: bool) -> Value {
+    json!({
+        "message": message,
+        "applicable": applicable,
+        "edits": [{
+            "span": {"start": span.start, "end": span.end},
+            "replacement": replacement,
+        }],
+    })
+}
+
+// 1-based line and column (in chars) of a byte offset.
+pub fn line_col(src: &str, offset: usize) -> (usize, usize) {
+    let target = offset.min(src.len());
+    let mut line = 1usize;
+    let mut col = 1usize;
+    for (idx, ch) in src.char_indices() {
+        if idx >= target {
+            break;
+        }
+        if ch == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+    }
+    (line, col)
+}
+
+// Writes the lines covered by `span` with the covered text marked. Long spans
+// keep their first two lines and their last. A one-line span on the line just
+// shown (`shown`) only adds its marks. Returns the last line shown.
+fn snippet(
+    out: &mut String,
+    src: &str,
+    span: Span,
+    marker: char,
+    label: &str,
+    width: usize,
+    shown: Option<usize>,
+) -> Option<usize> {
+    let start = span.start.min(src.len());
+    let end = span.end.clamp(start, src.len());
+    let (first, _) = line_col(src, start);
+    let (last, _) = line_col(src, if end > start { end - 1 } else { end });
+    let lines = src.split('\n').collect::<Vec<_>>();
+    let mut offset = lines[..first - 1]
+        .iter()
+        .map(|l| l.len() + 1)
+        .sum::<usize>();
+    for (idx, text) in lines.iter().enumerate().take(last).skip(first - 1) {
+        let number = idx + 1;
+        let line_start = offset;
+        offset += text.len() + 1;
+        if last - first > 3 && number > first + 1 && number < last {
+            if number == first + 2 {
+                let _ = writeln!(out, "{:width$} | ...", "");
+            }
+            continue;
+        }
+        let from = start.max(line_start) - line_start;
+        let to = end.min(line_start + text.len()) - line_start;
+        let pad = text[..from].chars().count();
+        let len = text[from..to.max(from)].chars().count().max(1);
+        if first != last || shown != Some(number) {
+            let _ = writeln!(out, "{number:>width$} | {text}");
+        }
+        let marks = marker.to_string().repeat(len);
+        if number == last && !label.is_empty() {
+            let _ = writeln!(out, "{:width$} | {:pad$}{marks} {label}", "", "");
+        } else {
+            let _ = writeln!(out, "{:width$} | {:pad$}{marks}", "", "");
+        }
+    }
+    Some(last)
+}
@@ -3,8 +3,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::ast::{
-    Decl, EffectAtom, EffectSet, Expr, FunctionType, Ident, Literal, MatchArm, Module, Name, Param,
-    Pattern, PrimType, Program, TypeExpr,
+    Decl, EffectAtom, EffectSet, Expr, FieldInit, FunctionType, Ident, Literal, MatchArm, Module,
+    Name, Param, Pattern, PrimType, Program, TypeExpr,
 };
 use crate::parser::{ParseError, parse_str};
 
@@ -23,6 +23,13 @@ pub fn parse_and_format_mode(src: &str, mode: FmtMode) -> Result<String, ParseEr
     Ok(format_program_mode(&program, mode))
 }
 
+// Readable source text of a single expression of `module`.
+pub fn format_expr_readable(expr: &Expr, module: &Module) -> String {
+    let mut out = String::new();
+    format_expr(expr, module, None, FmtMode::Readable, &mut out);
+    out
+}
+
 pub fn format_program(program: &Program) -> String {
     format_program_mode(program, FmtMode::Readable)
 }
@@ -106,6 +113,13 @@ fn collect_decl_bindings(decl: &Decl, module: &Module, out: &mut BTreeSet<String
                 collect_binding_ident(name, module, out);
             }
         }
+        Decl::Alias(d) => {
+            collect_binding_ident(&d.name, module, out);
+            for p in &d.params {
+                collect_binding_ident(p, module, out);
+            }
+            collect_type_bindings(&d.ty, module, out);
+        }
         Decl::Type(d) => {
             collect_binding_ident(&d.name, module, out);
             for p in &d.params {
@@ -116,6 +130,9 @@ fn collect_decl_bindings(decl: &Decl, module: &Module, out: &mut BTreeSet<String
                 for field in &ctor.fields {
                     collect_type_bindings(field, module, out);
                 }
+                for field_name in &ctor.field_names {
+                    collect_binding_ident(field_name, module, out);
+                }
             }
         }
         Decl::Value(d) => {
@@ -128,6 +145,9 @@ fn collect_decl_bindings(decl: &Decl, module: &Module, out: &mut BTreeSet<String
             for tp in &d.type_params {
                 collect_binding_ident(tp, module, out);
             }
+            for p in &d.param_names {
+                collect_binding_ident(p, module, out);
+            }
             collect_function_type_bindings(&d.sig, module, out);
             collect_expr_bindings(&d.expr, module, out);
         }
@@ -144,7 +164,15 @@ fn collect_function_type_bindings(sig: &FunctionType, module: &Module, out: &mut
 fn collect_type_bindings(ty: &TypeExpr, module: &Module, out: &mut BTreeSet<String>) {
     match ty {
         TypeExpr::Prim(_, _) => {}
-        TypeExpr::Named { name, args, .. } => {
+        TypeExpr::Named {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                collect_binding_ident(alias, module, out);
+            }
             collect_binding_ident(name, module, out);
             for arg in args {
                 collect_type_bindings(arg, module, out);
@@ -195,6 +223,27 @@ fn collect_expr_bindings(expr: &Expr, module: &Module, out: &mut BTreeSet<String
             collect_expr_bindings(value, module, out);
             collect_expr_bindings(body, module, out);
         }
+        Expr::LetRec { bindings, body, .. } => {
+            for binding in bindings {
+                collect_binding_ident(&binding.name, module, out);
+                collect_expr_bindings(&binding.value, module, out);
+            }
+            collect_expr_bindings(body, module, out);
+        }
+        Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            body,
+            ..
+        } => {
+            collect_pattern_bindings(pattern, module, out);
+            if let Some(ty) = ty {
+                collect_type_bindings(ty, module, out);
+            }
+            collect_expr_bindings(value, module, out);
+            collect_expr_bindings(body, module, out);
+        }
         Expr::If {
             cond,
             then_branch,
@@ -211,6 +260,9 @@ fn collect_expr_bindings(expr: &Expr, module: &Module, out: &mut BTreeSet<String
             collect_expr_bindings(scrutinee, module, out);
             for arm in arms {
                 collect_pattern_bindings(&arm.pattern, module, out);
+                if let Some(guard) = &arm.guard {
+                    collect_expr_bindings(guard, module, out);
+                }
                 collect_expr_bindings(&arm.expr, module, out);
             }
         }
@@ -238,16 +290,48 @@ fn collect_expr_bindings(expr: &Expr, module: &Module, out: &mut BTreeSet<String
         }
         Expr::Require { expr, .. }
         | Expr::Ensure { expr, .. }
-        | Expr::Paren { inner: expr, .. } => {
+        | Expr::Paren { inner: expr, .. }
+        | Expr::Try { inner: expr, .. } => {
             collect_expr_bindings(expr, module, out);
         }
         Expr::Name(_) => {}
-        Expr::NameApp { name, args, .. } => {
+        Expr::NameApp {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                collect_binding_ident(alias, module, out);
+            }
             collect_binding_ident(name, module, out);
             for arg in args {
                 collect_expr_bindings(arg, module, out);
             }
         }
+        Expr::Tuple { items, .. } => {
+            for item in items {
+                collect_expr_bindings(item, module, out);
+            }
+        }
+        Expr::Record { name, fields, .. } => {
+            collect_binding_ident(name, module, out);
+            for init in fields {
+                collect_binding_ident(&init.name, module, out);
+                collect_expr_bindings(&init.value, module, out);
+            }
+        }
+        Expr::Field { base, field, .. } => {
+            collect_expr_bindings(base, module, out);
+            collect_binding_ident(field, module, out);
+        }
+        Expr::Update { base, fields, .. } => {
+            collect_expr_bindings(base, module, out);
+            for init in fields {
+                collect_binding_ident(&init.name, module, out);
+                collect_expr_bindings(&init.value, module, out);
+            }
+        }
     }
 }
 
@@ -255,15 +339,23 @@ fn collect_pattern_bindings(pat: &Pattern, module: &Module, out: &mut BTreeSet<S
     match pat {
         Pattern::Wildcard(_) | Pattern::Literal(_) => {}
         Pattern::Name(id) => collect_binding_ident(id, module, out),
-        Pattern::Ctor { name, args, .. } => {
+        Pattern::Ctor {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                collect_binding_ident(alias, module, out);
+            }
             collect_binding_ident(name, module, out);
             for arg in args {
                 collect_pattern_bindings(arg, module, out);
             }
         }
-        Pattern::Tuple { items, .. } => {
-            for item in items {
-                collect_pattern_bindings(item, module, out);
+        Pattern::Tuple { items: alts, .. } | Pattern::Or { alts, .. } => {
+            for alt in alts {
+                collect_pattern_bindings(alt, module, out);
             }
         }
         Pattern::Paren { inner, .. } => collect_pattern_bindings(inner, module, out),
@@ -293,6 +385,13 @@ fn count_decl_names(
                 count_ident(name, module, eligible, out);
             }
         }
+        Decl::Alias(d) => {
+            count_ident(&d.name, module, eligible, out);
+            for p in &d.params {
+                count_ident(p, module, eligible, out);
+            }
+            count_type_names(&d.ty, module, eligible, out);
+        }
         Decl::Type(d) => {
             count_ident(&d.name, module, eligible, out);
             for p in &d.params {
@@ -303,6 +402,9 @@ fn count_decl_names(
                 for field in &ctor.fields {
                     count_type_names(field, module, eligible, out);
                 }
+                for field_name in &ctor.field_names {
+                    count_ident(field_name, module, eligible, out);
+                }
             }
         }
         Decl::Value(d) => {
@@ -315,6 +417,9 @@ fn count_decl_names(
             for tp in &d.type_params {
                 count_ident(tp, module, eligible, out);
             }
+            for p in &d.param_names {
+                count_ident(p, module, eligible, out);
+            }
             count_function_type_names(&d.sig, module, eligible, out);
             count_expr_names(&d.expr, module, eligible, out);
         }
@@ -341,7 +446,15 @@ fn count_type_names(
 ) {
     match ty {
         TypeExpr::Prim(_, _) => {}
-        TypeExpr::Named { name, args, .. } => {
+        TypeExpr::Named {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                count_ident(alias, module, eligible, out);
+            }
             count_ident(name, module, eligible, out);
             for arg in args {
                 count_type_names(arg, module, eligible, out);
@@ -397,6 +510,27 @@ fn count_expr_names(
             count_expr_names(value, module, eligible, out);
             count_expr_names(body, module, eligible, out);
         }
+        Expr::LetRec { bindings, body, .. } => {
+            for binding in bindings {
+                count_ident(&binding.name, module, eligible, out);
+                count_expr_names(&binding.value, module, eligible, out);
+            }
+            count_expr_names(body, module, eligible, out);
+        }
+        Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            body,
+            ..
+        } => {
+            count_pattern_names(pattern, module, eligible, out);
+            if let Some(ty) = ty {
+                count_type_names(ty, module, eligible, out);
+            }
+            count_expr_names(value, module, eligible, out);
+            count_expr_names(body, module, eligible, out);
+        }
         Expr::If {
             cond,
             then_branch,
@@ -413,6 +547,9 @@ fn count_expr_names(
             count_expr_names(scrutinee, module, eligible, out);
             for arm in arms {
                 count_pattern_names(&arm.pattern, module, eligible, out);
+                if let Some(guard) = &arm.guard {
+                    count_expr_names(guard, module, eligible, out);
+                }
                 count_expr_names(&arm.expr, module, eligible, out);
             }
         }
@@ -440,16 +577,48 @@ fn count_expr_names(
         }
         Expr::Require { expr, .. }
         | Expr::Ensure { expr, .. }
-        | Expr::Paren { inner: expr, .. } => {
+        | Expr::Paren { inner: expr, .. }
+        | Expr::Try { inner: expr, .. } => {
             count_expr_names(expr, module, eligible, out);
         }
         Expr::Name(name) => count_ident(name, module, eligible, out),
-        Expr::NameApp { name, args, .. } => {
+        Expr::NameApp {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                count_ident(alias, module, eligible, out);
+            }
             count_ident(name, module, eligible, out);
             for arg in args {
                 count_expr_names(arg, module, eligible, out);
             }
         }
+        Expr::Tuple { items, .. } => {
+            for item in items {
+                count_expr_names(item, module, eligible, out);
+            }
+        }
+        Expr::Record { name, fields, .. } => {
+            count_ident(name, module, eligible, out);
+            for init in fields {
+                count_ident(&init.name, module, eligible, out);
+                count_expr_names(&init.value, module, eligible, out);
+            }
+        }
+        Expr::Field { base, field, .. } => {
+            count_expr_names(base, module, eligible, out);
+            count_ident(field, module, eligible, out);
+        }
+        Expr::Update { base, fields, .. } => {
+            count_expr_names(base, module, eligible, out);
+            for init in fields {
+                count_ident(&init.name, module, eligible, out);
+                count_expr_names(&init.value, module, eligible, out);
+            }
+        }
     }
 }
 
@@ -462,15 +631,23 @@ fn count_pattern_names(
     match pat {
         Pattern::Wildcard(_) | Pattern::Literal(_) => {}
         Pattern::Name(id) => count_ident(id, module, eligible, out),
-        Pattern::Ctor { name, args, .. } => {
+        Pattern::Ctor {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                count_ident(alias, module, eligible, out);
+            }
             count_ident(name, module, eligible, out);
             for arg in args {
                 count_pattern_names(arg, module, eligible, out);
             }
         }
-        Pattern::Tuple { items, .. } => {
-            for item in items {
-                count_pattern_names(item, module, eligible, out);
+        Pattern::Tuple { items: alts, .. } | Pattern::Or { alts, .. } => {
+            for alt in alts {
+                count_pattern_names(alt, module, eligible, out);
             }
         }
         Pattern::Paren { inner, .. } => count_pattern_names(inner, module, eligible, out),
@@ -493,7 +670,7 @@ fn count_ident(
 fn is_core_literal_name(name: &str) -> bool {
     matches!(
         name,
-        "E" | "T" | "V" | "F" | "v" | "i" | "m" | "l" | "c" | "a" | "t" | "f"
+        "E" | "T" | "V" | "F" | "v" | "w" | "i" | "m" | "l" | "c" | "a" | "t" | "f"
     )
 }
 
@@ -554,15 +731,35 @@ fn format_decl(
         Decl::Export(d) => {
             out.push('E');
             out.push('[');
-            for (i, name) in d.names.iter().enumerate() {
+            for (i, (name, opaque)) in d.names.iter().zip(&d.opaque).enumerate() {
                 if i > 0 {
                     out.push(',');
                 }
+                if *opaque {
+                    out.push('~');
+                }
                 out.push_str(&render_name(module, name, compressed_table, mode));
             }
             out.push(']');
             out.push(';');
         }
+        Decl::Alias(d) => {
+            out.push_str("A ");
+            out.push_str(&render_name(module, &d.name, compressed_table, mode));
+            if !d.params.is_empty() {
+                out.push('[');
+                for (i, p) in d.params.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&render_name(module, p, compressed_table, mode));
+                }
+                out.push(']');
+            }
+            out.push('=');
+            format_type(&d.ty, module, compressed_table, mode, out);
+            out.push(';');
+        }
         Decl::Type(d) => {
             out.push_str("T ");
             out.push_str(&render_name(module, &d.name, compressed_table, mode));
@@ -577,20 +774,37 @@ fn format_decl(
                 out.push(']');
             }
             out.push('=');
-            for (i, ctor) in d.ctors.iter().enumerate() {
-                if i > 0 {
-                    out.push('|');
+            if let [record] = d.ctors.as_slice()
+                && !record.field_names.is_empty()
+            {
+                out.push('{');
+                for (j, (field_name, ty)) in
+                    record.field_names.iter().zip(&record.fields).enumerate()
+                {
+                    if j > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&render_name(module, field_name, compressed_table, mode));
+                    out.push(':');
+                    format_type(ty, module, compressed_table, mode, out);
                 }
-                out.push_str(&render_name(module, &ctor.name, compressed_table, mode));
-                if !ctor.fields.is_empty() {
-                    out.push('(');
-                    for (j, ty) in ctor.fields.iter().enumerate() {
-                        if j > 0 {
-                            out.push(',');
+                out.push('}');
+            } else {
+                for (i, ctor) in d.ctors.iter().enumerate() {
+                    if i > 0 {
+                        out.push('|');
+                    }
+                    out.push_str(&render_name(module, &ctor.name, compressed_table, mode));
+                    if !ctor.fields.is_empty() {
+                        out.push('(');
+                        for (j, ty) in ctor.fields.iter().enumerate() {
+                            if j > 0 {
+                                out.push(',');
+                            }
+                            format_type(ty, module, compressed_table, mode, out);
                         }
-                        format_type(ty, module, compressed_table, mode, out);
+                        out.push(')');
                     }
-                    out.push(')');
                 }
             }
             out.push(';');
@@ -618,7 +832,7 @@ fn format_decl(
                 out.push(']');
             }
             out.push(':');
-            format_function_type(&d.sig, module, compressed_table, mode, out);
+            format_function_sig(&d.param_names, &d.sig, module, compressed_table, mode, out);
             out.push('=');
             format_expr(&d.expr, module, compressed_table, mode, out);
             out.push(';');
@@ -628,7 +842,8 @@ fn format_decl(
 
 fn format_effect_set(effects: &EffectSet, mode: FmtMode, out: &mut String) {
     let atoms = canonical_effect_atoms(effects);
-    if atoms.is_empty() {
+    let vars = effects.vars.iter().collect::<BTreeSet<_>>();
+    if atoms.is_empty() && vars.is_empty() {
         return;
     }
     out.push_str("!{");
@@ -653,6 +868,12 @@ fn format_effect_set(effects: &EffectSet, mode: FmtMode, out: &mut String) {
             (FmtMode::Compressed, EffectAtom::St) => "S",
         });
     }
+    for (i, var) in vars.into_iter().enumerate() {
+        if i > 0 || !atoms.is_empty() {
+            out.push(',');
+        }
+        out.push_str(var);
+    }
     out.push('}');
 }
 
@@ -674,6 +895,33 @@ fn canonical_effect_atoms(effects: &EffectSet) -> Vec<EffectAtom> {
     out
 }
 
+fn format_function_sig(
+    names: &[Ident],
+    sig: &FunctionType,
+    module: &Module,
+    compressed_table: Option<&Vec<String>>,
+    mode: FmtMode,
+    out: &mut String,
+) {
+    if names.len() != sig.params.len() {
+        format_function_type(sig, module, compressed_table, mode, out);
+        return;
+    }
+    out.push('(');
+    for (i, (name, ty)) in names.iter().zip(sig.params.iter()).enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&render_name(module, name, compressed_table, mode));
+        out.push(':');
+        format_type(ty, module, compressed_table, mode, out);
+    }
+    out.push(')');
+    out.push_str("->");
+    format_type(&sig.ret, module, compressed_table, mode, out);
+    format_effect_set(&sig.effects, mode, out);
+}
+
 fn format_function_type(
     sig: &FunctionType,
     module: &Module,
@@ -713,7 +961,16 @@ fn format_type(
             PrimType::F64 => "f64",
             PrimType::Unit => "unit",
         }),
-        TypeExpr::Named { name, args, .. } => {
+        TypeExpr::Named {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                out.push_str(&render_name(module, alias, compressed_table, mode));
+                out.push('.');
+            }
             out.push_str(&render_name(module, name, compressed_table, mode));
             if !args.is_empty() {
                 out.push('[');
@@ -836,6 +1093,61 @@ fn format_expr(
                 out.push(']');
             }
         },
+        Expr::LetRec { bindings, body, .. } => match mode {
+            FmtMode::Readable => {
+                out.push_str("w(");
+                for binding in bindings {
+                    out.push_str(&render_name(module, &binding.name, compressed_table, mode));
+                    out.push('=');
+                    format_expr(&binding.value, module, compressed_table, mode, out);
+                    out.push(',');
+                }
+                format_expr(body, module, compressed_table, mode, out);
+                out.push(')');
+            }
+            FmtMode::Compressed => {
+                out.push_str("[w ");
+                for binding in bindings {
+                    out.push('{');
+                    out.push_str(&render_name(module, &binding.name, compressed_table, mode));
+                    out.push(' ');
+                    format_expr(&binding.value, module, compressed_table, mode, out);
+                    out.push_str("} ");
+                }
+                format_expr(body, module, compressed_table, mode, out);
+                out.push(']');
+            }
+        },
+        Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            body,
+            ..
+        } => match mode {
+            FmtMode::Readable => {
+                out.push_str("v(");
+                format_pattern(pattern, module, compressed_table, mode, out);
+                if let Some(ty) = ty {
+                    out.push(':');
+                    format_type(ty, module, compressed_table, mode, out);
+                }
+                out.push('=');
+                format_expr(value, module, compressed_table, mode, out);
+                out.push(',');
+                format_expr(body, module, compressed_table, mode, out);
+                out.push(')');
+            }
+            FmtMode::Compressed => {
+                out.push_str("[v ");
+                format_pattern(pattern, module, compressed_table, mode, out);
+                out.push(' ');
+                format_expr(value, module, compressed_table, mode, out);
+                out.push(' ');
+                format_expr(body, module, compressed_table, mode, out);
+                out.push(']');
+            }
+        },
         Expr::If {
             cond,
             then_branch,
@@ -870,6 +1182,7 @@ fn format_expr(
                 out.push_str("){");
                 for arm in arms {
                     format_pattern(&arm.pattern, module, compressed_table, mode, out);
+                    format_match_guard(arm, module, compressed_table, mode, out);
                     out.push_str("=>");
                     format_expr(&arm.expr, module, compressed_table, mode, out);
                     out.push(';');
@@ -952,7 +1265,16 @@ fn format_expr(
             format_expr(expr, module, compressed_table, mode, out);
         }
         Expr::Name(id) => out.push_str(&render_name(module, id, compressed_table, mode)),
-        Expr::NameApp { name, args, .. } => {
+        Expr::NameApp {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                out.push_str(&render_name(module, alias, compressed_table, mode));
+                out.push('.');
+            }
             out.push_str(&render_name(module, name, compressed_table, mode));
             out.push('(');
             for (i, arg) in args.iter().enumerate() {
@@ -964,11 +1286,42 @@ fn format_expr(
             out.push(')');
         }
         Expr::Literal(lit) => format_literal(lit, out),
+        Expr::Try { inner, .. } => {
+            format_expr(inner, module, compressed_table, mode, out);
+            out.push('?');
+        }
+        Expr::Record { name, fields, .. } => {
+            out.push_str(&render_name(module, name, compressed_table, mode));
+            out.push('(');
+            format_field_inits(fields, module, compressed_table, mode, out);
+            out.push(')');
+        }
+        Expr::Field { base, field, .. } => {
+            format_expr(base, module, compressed_table, mode, out);
+            out.push('.');
+            out.push_str(&render_name(module, field, compressed_table, mode));
+        }
+        Expr::Update { base, fields, .. } => {
+            format_expr(base, module, compressed_table, mode, out);
+            out.push('{');
+            format_field_inits(fields, module, compressed_table, mode, out);
+            out.push('}');
+        }
         Expr::Paren { inner, .. } => {
             out.push('(');
             format_expr(inner, module, compressed_table, mode, out);
             out.push(')');
         }
+        Expr::Tuple { items, .. } => {
+            out.push('(');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                format_expr(item, module, compressed_table, mode, out);
+            }
+            out.push(')');
+        }
     }
 }
 
@@ -989,6 +1342,23 @@ fn format_params(
     }
 }
 
+fn format_field_inits(
+    fields: &[FieldInit],
+    module: &Module,
+    compressed_table: Option<&Vec<String>>,
+    mode: FmtMode,
+    out: &mut String,
+) {
+    for (i, init) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&render_name(module, &init.name, compressed_table, mode));
+        out.push('=');
+        format_expr(&init.value, module, compressed_table, mode, out);
+    }
+}
+
 fn format_compressed_match_arm(
     arm: &MatchArm,
     module: &Module,
@@ -998,11 +1368,25 @@ fn format_compressed_match_arm(
 ) {
     out.push('{');
     format_pattern(&arm.pattern, module, compressed_table, mode, out);
+    format_match_guard(arm, module, compressed_table, mode, out);
     out.push(' ');
     format_expr(&arm.expr, module, compressed_table, mode, out);
     out.push('}');
 }
 
+fn format_match_guard(
+    arm: &MatchArm,
+    module: &Module,
+    compressed_table: Option<&Vec<String>>,
+    mode: FmtMode,
+    out: &mut String,
+) {
+    if let Some(guard) = &arm.guard {
+        out.push_str(" if ");
+        format_expr(guard, module, compressed_table, mode, out);
+    }
+}
+
 fn format_pattern(
     pat: &Pattern,
     module: &Module,
@@ -1014,7 +1398,16 @@ fn format_pattern(
         Pattern::Wildcard(_) => out.push('_'),
         Pattern::Literal(lit) => format_literal(lit, out),
         Pattern::Name(id) => out.push_str(&render_name(module, id, compressed_table, mode)),
-        Pattern::Ctor { name, args, .. } => {
+        Pattern::Ctor {
+            module: alias,
+            name,
+            args,
+            ..
+        } => {
+            if let Some(alias) = alias {
+                out.push_str(&render_name(module, alias, compressed_table, mode));
+                out.push('.');
+            }
             out.push_str(&render_name(module, name, compressed_table, mode));
             if !args.is_empty() {
                 out.push('(');
@@ -1042,6 +1435,14 @@ fn format_pattern(
             format_pattern(inner, module, compressed_table, mode, out);
             out.push(')');
         }
+        Pattern::Or { alts, .. } => {
+            for (i, alt) in alts.iter().enumerate() {
+                if i > 0 {
+                    out.push('|');
+                }
+                format_pattern(alt, module, compressed_table, mode, out);
+            }
+        }
     }
 }
 
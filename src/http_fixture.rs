@@ -0,0 +1,280 @@
+use std::fs;
+use std::path::Path;
+
+use crate::vm::{HttpRequest, HttpResponse, ProcOutput, ProcRequest, VmError, VmHost};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpFixture {
+    pub method: String,
+    pub url: String,
+    pub response: HttpResponse,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpFixtures {
+    entries: Vec<HttpFixture>,
+    served: Vec<bool>,
+}
+
+impl HttpFixtures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_json(src: &str) -> Result<Self, String> {
+        let value = serde_json::from_str::<serde_json::Value>(src)
+            .map_err(|e| format!("invalid http fixtures: {e}"))?;
+        let serde_json::Value::Array(items) = value else {
+            return Err("invalid http fixtures: expected a JSON array of exchanges".into());
+        };
+        let mut fixtures = HttpFixtures::new();
+        for (idx, item) in items.into_iter().enumerate() {
+            fixtures.push(fixture_from_json(idx, item)?);
+        }
+        Ok(fixtures)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let src = fs::read_to_string(path)
+            .map_err(|e| format!("failed reading {}: {e}", path.display()))?;
+        Self::from_json(&src)
+    }
+
+    pub fn push(&mut self, fixture: HttpFixture) {
+        self.entries.push(fixture);
+        self.served.push(false);
+    }
+
+    pub fn entries(&self) -> &[HttpFixture] {
+        &self.entries
+    }
+
+    // Serves matching entries in file order; once all have been served the
+    // last match answers every further request.
+    pub fn replay(&mut self, method: &str, url: &str) -> Option<HttpResponse> {
+        let mut last = None;
+        for (idx, fixture) in self.entries.iter().enumerate() {
+            if !fixture.method.eq_ignore_ascii_case(method) || fixture.url != url {
+                continue;
+            }
+            if !self.served[idx] {
+                self.served[idx] = true;
+                return Some(fixture.response.clone());
+            }
+            last = Some(idx);
+        }
+        last.map(|idx| self.entries[idx].response.clone())
+    }
+
+    pub fn to_json(&self) -> String {
+        let items = self
+            .entries
+            .iter()
+            .map(|fixture| {
+                let headers = fixture
+                    .response
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        serde_json::Value::Array(vec![
+                            serde_json::Value::String(name.clone()),
+                            serde_json::Value::String(value.clone()),
+                        ])
+                    })
+                    .collect();
+                serde_json::json!({
+                    "method": fixture.method,
+                    "url": fixture.url,
+                    "status": fixture.response.status,
+                    "headers": serde_json::Value::Array(headers),
+                    "body": fixture.response.body,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&serde_json::Value::Array(items))
+            .expect("fixtures should serialize")
+    }
+}
+
+fn fixture_from_json(idx: usize, item: serde_json::Value) -> Result<HttpFixture, String> {
+    let serde_json::Value::Object(fields) = item else {
+        return Err(format!("invalid http fixture #{idx}: expected an object"));
+    };
+    let text = |key: &str, default: Option<&str>| -> Result<String, String> {
+        match fields.get(key) {
+            Some(serde_json::Value::String(s)) => Ok(s.clone()),
+            None => default
+                .map(str::to_string)
+                .ok_or_else(|| format!("invalid http fixture #{idx}: missing `{key}`")),
+            Some(_) => Err(format!(
+                "invalid http fixture #{idx}: `{key}` must be a string"
+            )),
+        }
+    };
+    let method = text("method", Some("GET"))?.to_ascii_uppercase();
+    let url = text("url", None)?;
+    let body = text("body", Some(""))?;
+    let status = match fields.get("status") {
+        None => 200,
+        Some(v) => v
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .ok_or_else(|| format!("invalid http fixture #{idx}: `status` must be an integer"))?,
+    };
+    let headers = match fields.get("headers") {
+        None => Vec::new(),
+        Some(serde_json::Value::Object(map)) => map
+            .iter()
+            .map(|(name, value)| match value {
+                serde_json::Value::String(v) => Ok((name.clone(), v.clone())),
+                _ => Err(format!(
+                    "invalid http fixture #{idx}: header `{name}` must be a string"
+                )),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(serde_json::Value::Array(pairs)) => pairs
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some(
+                    [
+                        serde_json::Value::String(name),
+                        serde_json::Value::String(value),
+                    ],
+                ) => Ok((name.clone(), value.clone())),
+                _ => Err(format!(
+                    "invalid http fixture #{idx}: headers must be [name, value] string pairs"
+                )),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => {
+            return Err(format!(
+                "invalid http fixture #{idx}: `headers` must be an object or array"
+            ));
+        }
+    };
+    Ok(HttpFixture {
+        method,
+        url,
+        response: HttpResponse {
+            status,
+            headers,
+            body,
+        },
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpFixtureMode {
+    Replay,
+    Record,
+}
+
+pub struct HttpFixtureHost<H> {
+    pub inner: H,
+    pub fixtures: HttpFixtures,
+    pub mode: HttpFixtureMode,
+}
+
+impl<H: VmHost> HttpFixtureHost<H> {
+    pub fn replay(inner: H, fixtures: HttpFixtures) -> Self {
+        Self {
+            inner,
+            fixtures,
+            mode: HttpFixtureMode::Replay,
+        }
+    }
+
+    pub fn record(inner: H) -> Self {
+        Self {
+            inner,
+            fixtures: HttpFixtures::new(),
+            mode: HttpFixtureMode::Record,
+        }
+    }
+}
+
+impl<H: VmHost> VmHost for HttpFixtureHost<H> {
+    fn io_print(&mut self, text: &str) -> Result<(), VmError> {
+        self.inner.io_print(text)
+    }
+
+    fn io_println(&mut self, text: &str) -> Result<(), VmError> {
+        self.inner.io_println(text)
+    }
+
+    fn io_readln(&mut self) -> Result<String, VmError> {
+        self.inner.io_readln()
+    }
+
+    fn fs_read_to_string(&mut self, path: &str) -> Result<String, String> {
+        self.inner.fs_read_to_string(path)
+    }
+
+    fn fs_write_string(&mut self, path: &str, data: &str) -> Result<(), String> {
+        self.inner.fs_write_string(path, data)
+    }
+
+    fn fs_append_string(&mut self, path: &str, data: &str) -> Result<(), String> {
+        self.inner.fs_append_string(path, data)
+    }
+
+    fn fs_list_dir(&mut self, path: &str) -> Result<Vec<String>, String> {
+        self.inner.fs_list_dir(path)
+    }
+
+    fn fs_exists(&mut self, path: &str) -> Result<bool, String> {
+        self.inner.fs_exists(path)
+    }
+
+    fn fs_create_dir(&mut self, path: &str) -> Result<(), String> {
+        self.inner.fs_create_dir(path)
+    }
+
+    fn fs_remove_file(&mut self, path: &str) -> Result<(), String> {
+        self.inner.fs_remove_file(path)
+    }
+
+    fn proc_run(&mut self, cmd: &str, args: &[String]) -> Result<i32, String> {
+        self.inner.proc_run(cmd, args)
+    }
+
+    fn proc_run_capture(&mut self, req: &ProcRequest) -> Result<ProcOutput, String> {
+        self.inner.proc_run_capture(req)
+    }
+
+    fn http_get(&mut self, url: &str) -> Result<String, String> {
+        let resp = self.http_request(&HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: String::new(),
+        })?;
+        if (200..300).contains(&resp.status) {
+            Ok(resp.body)
+        } else {
+            Err(format!("get failed: http status {}", resp.status))
+        }
+    }
+
+    fn http_request(&mut self, req: &HttpRequest) -> Result<HttpResponse, String> {
+        match self.mode {
+            HttpFixtureMode::Replay => {
+                self.fixtures.replay(&req.method, &req.url).ok_or_else(|| {
+                    format!(
+                        "http fixture: no response recorded for {} {}",
+                        req.method, req.url
+                    )
+                })
+            }
+            HttpFixtureMode::Record => {
+                let resp = self.inner.http_request(req)?;
+                self.fixtures.push(HttpFixture {
+                    method: req.method.clone(),
+                    url: req.url.clone(),
+                    response: resp.clone(),
+                });
+                Ok(resp)
+            }
+        }
+    }
+}
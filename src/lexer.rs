@@ -69,6 +69,7 @@ pub enum TokenKind {
     Bang,
     Question,
     Caret,
+    Tilde,
     LParen,
     RParen,
     LBracket,
@@ -89,6 +90,8 @@ pub enum TokenKind {
     Gt,
     Ge,
     Underscore,
+    // `if` is the only reserved word; it introduces a match guard.
+    If,
     SymRef(u32),
     Ident(String),
     Int(i64),
@@ -191,6 +194,7 @@ impl<'a> Lexer<'a> {
                 }
                 '?' => self.simple(idx, TokenKind::Question),
                 '^' => self.simple(idx, TokenKind::Caret),
+                '~' => self.simple(idx, TokenKind::Tilde),
                 '#' => self.lex_sym_ref()?,
                 '+' => self.simple(idx, TokenKind::Plus),
                 '*' => self.simple(idx, TokenKind::Star),
@@ -372,7 +376,11 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        let kind = TokenKind::Ident(name);
+        let kind = if name == "if" {
+            TokenKind::If
+        } else {
+            TokenKind::Ident(name)
+        };
         Token {
             kind,
             span: Span {
@@ -1,9 +1,12 @@
 pub mod ast;
 pub mod bytecode;
 pub mod cli;
+pub mod diagnostic;
 pub mod fmt;
+pub mod http_fixture;
 pub mod lexer;
 pub mod parser;
 pub mod stdlib;
 pub mod typecheck;
+pub mod vfs;
 pub mod vm;
@@ -1,6 +1,9 @@
 fn main() {
     if let Err(err) = muc::cli::run() {
-        eprintln!("error: {err}");
+        // An empty error was already reported as a JSON diagnostic.
+        if !err.is_empty() {
+            eprintln!("error: {err}");
+        }
         std::process::exit(1);
     }
 }
@@ -1,9 +1,9 @@
 use std::fmt;
 
 use crate::ast::{
-    CtorDecl, Decl, EffectAtom, EffectSet, ExportDecl, Expr, FunctionDecl, FunctionType, Ident,
-    ImportDecl, Literal, MatchArm, ModId, Module, Name, Param, Pattern, PrimType, Program, Span,
-    TypeDecl, TypeExpr, ValueDecl,
+    AliasDecl, CtorDecl, Decl, EffectAtom, EffectSet, ExportDecl, Expr, FieldInit, FunctionDecl,
+    FunctionType, Ident, ImportDecl, Literal, MatchArm, ModId, Module, Name, Param, Pattern,
+    PrimType, Program, Span, TypeDecl, TypeExpr, ValueDecl,
 };
 use crate::lexer::{LexError, Token, TokenKind, tokenize};
 
@@ -65,25 +65,50 @@ impl From<LexError> for ParseError {
 }
 
 pub fn parse_str(src: &str) -> Result<Program, ParseError> {
-    let tokens = tokenize(src)?;
+    match parse_str_recovering(src) {
+        (Some(program), errors) if errors.is_empty() => Ok(program),
+        (_, errors) => Err(errors.into_iter().next().expect("parse errors")),
+    }
+}
+
+// Parses `src`, skipping to the next declaration after a syntax error, and
+// returns every error found. The program holds the declarations that parsed;
+// it is `None` only when the lexer or the module header fails.
+pub fn parse_str_recovering(src: &str) -> (Option<Program>, Vec<ParseError>) {
+    let tokens = match tokenize(src) {
+        Ok(tokens) => tokens,
+        Err(err) => return (None, vec![err.into()]),
+    };
     let mut p = Parser {
         tokens,
         pos: 0,
         current_symtab: None,
+        errors: Vec::new(),
     };
-    p.parse_program()
+    let program = p.parse_program();
+    let mut errors = p.errors;
+    match program {
+        Ok(program) => (Some(program), errors),
+        Err(err) => {
+            errors.insert(0, err);
+            (None, errors)
+        }
+    }
 }
 
 struct Parser {
     tokens: Vec<Token>,
     pos: usize,
     current_symtab: Option<Vec<String>>,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
     fn parse_program(&mut self) -> Result<Program, ParseError> {
         let module = self.parse_module()?;
-        self.expect_simple(TokenKind::Eof, "expected end of file")?;
+        if let Err(err) = self.expect_simple(TokenKind::Eof, "expected end of file") {
+            self.errors.push(err);
+        }
         Ok(Program { module })
     }
 
@@ -98,10 +123,22 @@ impl Parser {
         };
         self.current_symtab = symtab.clone();
         let mut decls = Vec::new();
-        while !self.at_simple(TokenKind::RBrace) {
-            decls.push(self.parse_decl()?);
+        while !self.at_simple(TokenKind::RBrace) && !self.at_simple(TokenKind::Eof) {
+            match self.parse_decl() {
+                Ok(decl) => decls.push(decl),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.skip_to_next_decl();
+                }
+            }
         }
-        let end = self.expect_simple(TokenKind::RBrace, "expected `}` to close module")?;
+        let end = match self.expect_simple(TokenKind::RBrace, "expected `}` to close module") {
+            Ok(end) => end,
+            Err(err) => {
+                self.errors.push(err);
+                self.peek().clone()
+            }
+        };
         self.current_symtab = None;
         Ok(Module {
             mod_id,
@@ -111,6 +148,41 @@ impl Parser {
         })
     }
 
+    // Skips the rest of a declaration that failed to parse. Stops at a
+    // declaration start that follows a `;` (or is where parsing stopped), at
+    // the module's closing `}`, or at end of file.
+    fn skip_to_next_decl(&mut self) {
+        let failed_at = self.pos;
+        loop {
+            match self.peek().kind {
+                TokenKind::Eof => return,
+                TokenKind::RBrace if matches!(self.peek_nth(1).kind, TokenKind::Eof) => return,
+                _ => {}
+            }
+            let after_semicolon =
+                self.pos > 0 && matches!(self.tokens[self.pos - 1].kind, TokenKind::Semicolon);
+            if (after_semicolon || self.pos == failed_at) && self.at_decl_start() {
+                return;
+            }
+            self.bump();
+        }
+    }
+
+    fn at_decl_start(&self) -> bool {
+        let named = matches!(
+            self.peek_nth(1).kind,
+            TokenKind::Ident(_) | TokenKind::SymRef(_)
+        );
+        match &self.peek().kind {
+            TokenKind::Ident(kw) if matches!(kw.as_str(), "T" | "F" | "V" | "A") => named,
+            TokenKind::Ident(kw) if kw == "E" => {
+                matches!(self.peek_nth(1).kind, TokenKind::LBracket)
+            }
+            TokenKind::Colon => named && matches!(self.peek_nth(2).kind, TokenKind::Eq),
+            _ => false,
+        }
+    }
+
     fn parse_symtab_decl(&mut self) -> Result<Vec<String>, ParseError> {
         self.expect_simple(
             TokenKind::Dollar,
@@ -162,6 +234,9 @@ impl Parser {
         if self.at_ident_text("T") {
             return self.parse_type_decl().map(Decl::Type);
         }
+        if self.at_ident_text("A") {
+            return self.parse_alias_decl().map(Decl::Alias);
+        }
         if self.at_ident_text("V") {
             return self.parse_value_decl().map(Decl::Value);
         }
@@ -192,13 +267,41 @@ impl Parser {
         let start = self.expect_ident_text("E", "expected `E`")?;
         self.expect_simple(TokenKind::LBracket, "expected `[` after E")?;
         let mut names = Vec::new();
-        if !self.at_simple(TokenKind::RBracket) {
-            names = self.parse_name_list()?;
+        let mut opaque = Vec::new();
+        while !self.at_simple(TokenKind::RBracket) {
+            if !names.is_empty() {
+                self.expect_simple(TokenKind::Comma, "expected `,` between export names")?;
+            }
+            opaque.push(self.at_simple(TokenKind::Tilde));
+            if self.at_simple(TokenKind::Tilde) {
+                self.bump();
+            }
+            names.push(self.expect_name("expected identifier")?);
         }
         self.expect_simple(TokenKind::RBracket, "expected `]` in export decl")?;
         let end = self.expect_simple(TokenKind::Semicolon, "expected `;` after export")?;
         Ok(ExportDecl {
             names,
+            opaque,
+            span: start.span.merge(end.span),
+        })
+    }
+
+    fn parse_alias_decl(&mut self) -> Result<AliasDecl, ParseError> {
+        let start = self.expect_ident_text("A", "expected `A`")?;
+        let name = self.expect_name("expected type alias name")?;
+        let params = if self.at_simple(TokenKind::LBracket) {
+            self.parse_type_params()?
+        } else {
+            Vec::new()
+        };
+        self.expect_simple(TokenKind::Eq, "expected `=` in type alias")?;
+        let ty = self.parse_type()?;
+        let end = self.expect_simple(TokenKind::Semicolon, "expected `;` after type alias")?;
+        Ok(AliasDecl {
+            name,
+            params,
+            ty,
             span: start.span.merge(end.span),
         })
     }
@@ -212,6 +315,17 @@ impl Parser {
             Vec::new()
         };
         self.expect_simple(TokenKind::Eq, "expected `=` in type declaration")?;
+        if self.at_simple(TokenKind::LBrace) {
+            let record = self.parse_record_decl(&name)?;
+            let end =
+                self.expect_simple(TokenKind::Semicolon, "expected `;` after type declaration")?;
+            return Ok(TypeDecl {
+                name,
+                params,
+                ctors: vec![record],
+                span: start.span.merge(end.span),
+            });
+        }
         let mut ctors = vec![self.parse_ctor_decl()?];
         while self.at_simple(TokenKind::Pipe) {
             self.bump();
@@ -227,6 +341,29 @@ impl Parser {
         })
     }
 
+    // A record declares a single constructor named after the type.
+    fn parse_record_decl(&mut self, type_name: &Ident) -> Result<CtorDecl, ParseError> {
+        let open = self.expect_simple(TokenKind::LBrace, "expected `{` in record declaration")?;
+        let mut fields = Vec::new();
+        let mut field_names = Vec::new();
+        loop {
+            field_names.push(self.expect_name("expected record field name")?);
+            self.expect_simple(TokenKind::Colon, "expected `:` after record field name")?;
+            fields.push(self.parse_type()?);
+            if !self.at_simple(TokenKind::Comma) {
+                break;
+            }
+            self.bump();
+        }
+        let close = self.expect_simple(TokenKind::RBrace, "expected `}` in record declaration")?;
+        Ok(CtorDecl {
+            name: type_name.clone(),
+            fields,
+            field_names,
+            span: open.span.merge(close.span),
+        })
+    }
+
     fn parse_ctor_decl(&mut self) -> Result<CtorDecl, ParseError> {
         let name = self.expect_name("expected constructor name")?;
         if !self.at_simple(TokenKind::LParen) {
@@ -234,6 +371,7 @@ impl Parser {
                 span: name.span,
                 name,
                 fields: Vec::new(),
+                field_names: Vec::new(),
             });
         }
         self.bump();
@@ -246,6 +384,7 @@ impl Parser {
             span: name.span.merge(end.span),
             name,
             fields,
+            field_names: Vec::new(),
         })
     }
 
@@ -275,7 +414,11 @@ impl Parser {
             Vec::new()
         };
         self.expect_simple(TokenKind::Colon, "expected `:` after function name")?;
-        let sig = self.parse_function_type()?;
+        let (param_names, sig) = if self.at_named_params() {
+            self.parse_named_function_type()?
+        } else {
+            (Vec::new(), self.parse_function_type()?)
+        };
         self.expect_simple(TokenKind::Eq, "expected `=` in function declaration")?;
         let expr = self.parse_expr()?;
         let end = self.expect_simple(
@@ -285,6 +428,7 @@ impl Parser {
         Ok(FunctionDecl {
             name,
             type_params,
+            param_names,
             sig,
             expr,
             span: start.span.merge(end.span),
@@ -475,6 +619,28 @@ impl Parser {
         self.finish_function_type(open.span, close.span, params)
     }
 
+    fn at_named_params(&self) -> bool {
+        self.at_simple(TokenKind::LParen)
+            && matches!(
+                self.peek_nth(1).kind,
+                TokenKind::Ident(_) | TokenKind::SymRef(_)
+            )
+            && matches!(self.peek_nth(2).kind, TokenKind::Colon)
+    }
+
+    fn parse_named_function_type(&mut self) -> Result<(Vec<Ident>, FunctionType), ParseError> {
+        let open = self.expect_simple(TokenKind::LParen, "expected `(` in function type")?;
+        let mut params = vec![self.parse_param()?];
+        while self.at_simple(TokenKind::Comma) {
+            self.bump();
+            params.push(self.parse_param()?);
+        }
+        let close = self.expect_simple(TokenKind::RParen, "expected `)` in function type")?;
+        let (names, types) = params.into_iter().map(|p| (p.name, p.ty)).unzip();
+        let sig = self.finish_function_type(open.span, close.span, types)?;
+        Ok((names, sig))
+    }
+
     fn finish_function_type(
         &mut self,
         open: Span,
@@ -483,55 +649,57 @@ impl Parser {
     ) -> Result<FunctionType, ParseError> {
         self.expect_simple(TokenKind::Arrow, "expected `->` in function type")?;
         let ret = self.parse_type()?;
-        let effects = if self.at_simple(TokenKind::Bang) {
-            self.parse_effect_set()?
-        } else {
-            EffectSet::default()
-        };
+        let (effects, effects_span) = self.parse_optional_effect_set(ret.span())?;
         let span = open.merge(close).merge(ret.span());
         Ok(FunctionType {
             params,
             ret: Box::new(ret),
             effects,
+            effects_span,
             span,
         })
     }
 
-    fn parse_effect_set(&mut self) -> Result<EffectSet, ParseError> {
-        self.expect_simple(TokenKind::Bang, "expected `!` for effect set")?;
+    // An effect set if one follows, with its span; otherwise an empty set and
+    // an empty span at the end of `ret`.
+    fn parse_optional_effect_set(&mut self, ret: Span) -> Result<(EffectSet, Span), ParseError> {
+        if self.at_simple(TokenKind::Bang) {
+            self.parse_effect_set()
+        } else {
+            let at = Span {
+                start: ret.end,
+                end: ret.end,
+            };
+            Ok((EffectSet::default(), at))
+        }
+    }
+
+    fn parse_effect_set(&mut self) -> Result<(EffectSet, Span), ParseError> {
+        let bang = self.expect_simple(TokenKind::Bang, "expected `!` for effect set")?;
         self.expect_simple(TokenKind::LBrace, "expected `{` in effect set")?;
-        let mut atoms = vec![self.parse_effect_atom()?];
-        while self.at_simple(TokenKind::Comma) {
+        let mut effects = EffectSet::default();
+        loop {
+            let ident = self.expect_plain_ident("expected effect atom")?;
+            match effect_atom(&ident) {
+                Some(atom) => effects.atoms.push(atom),
+                None if ident.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                    effects.vars.push(ident)
+                }
+                None => {
+                    return Err(ParseError {
+                        code: ParseErrorCode::ExpectedToken,
+                        span: self.tokens[self.pos - 1].span,
+                        message: format!("unknown effect atom `{ident}`"),
+                    });
+                }
+            }
+            if !self.at_simple(TokenKind::Comma) {
+                break;
+            }
             self.bump();
-            atoms.push(self.parse_effect_atom()?);
-        }
-        self.expect_simple(TokenKind::RBrace, "expected `}` in effect set")?;
-        Ok(EffectSet { atoms })
-    }
-
-    fn parse_effect_atom(&mut self) -> Result<EffectAtom, ParseError> {
-        let ident = self.expect_plain_ident("expected effect atom")?;
-        match ident.as_str() {
-            "io" => Ok(EffectAtom::Io),
-            "fs" => Ok(EffectAtom::Fs),
-            "net" => Ok(EffectAtom::Net),
-            "proc" => Ok(EffectAtom::Proc),
-            "rand" => Ok(EffectAtom::Rand),
-            "time" => Ok(EffectAtom::Time),
-            "st" => Ok(EffectAtom::St),
-            "I" => Ok(EffectAtom::Io),
-            "F" => Ok(EffectAtom::Fs),
-            "N" => Ok(EffectAtom::Net),
-            "P" => Ok(EffectAtom::Proc),
-            "R" => Ok(EffectAtom::Rand),
-            "T" => Ok(EffectAtom::Time),
-            "S" => Ok(EffectAtom::St),
-            _ => Err(ParseError {
-                code: ParseErrorCode::ExpectedToken,
-                span: self.tokens[self.pos - 1].span,
-                message: format!("unknown effect atom `{ident}`"),
-            }),
         }
+        let close = self.expect_simple(TokenKind::RBrace, "expected `}` in effect set")?;
+        Ok((effects, bang.span.merge(close.span)))
     }
 
     fn parse_named_or_prim_type(&mut self) -> Result<TypeExpr, ParseError> {
@@ -554,8 +722,16 @@ impl Parser {
         if let Some(p) = prim {
             return Ok(TypeExpr::Prim(p, name.span));
         }
-        let mut args = Vec::new();
+        let mut module = None;
+        let mut name = name;
         let mut span = name.span;
+        if self.at_simple(TokenKind::Dot) {
+            self.bump();
+            module = Some(name);
+            name = self.expect_name("expected type name after `.`")?;
+            span = span.merge(name.span);
+        }
+        let mut args = Vec::new();
         if self.at_simple(TokenKind::LBracket) && !self.lookahead_is_simple(1, TokenKind::RBracket)
         {
             self.bump();
@@ -563,10 +739,76 @@ impl Parser {
             let close = self.expect_simple(TokenKind::RBracket, "expected `]` in type args")?;
             span = span.merge(close.span);
         }
-        Ok(TypeExpr::Named { name, args, span })
+        Ok(TypeExpr::Named {
+            module,
+            name,
+            args,
+            span,
+        })
     }
 
     fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_prefix_expr()?;
+        loop {
+            if self.at_simple(TokenKind::Question) {
+                let question = self.bump();
+                let span = expr.span().merge(question.span);
+                expr = Expr::Try {
+                    inner: Box::new(expr),
+                    span,
+                };
+            } else if self.at_simple(TokenKind::Dot) {
+                self.bump();
+                let field = self.expect_name("expected field name after `.`")?;
+                let span = expr.span().merge(field.span);
+                expr = Expr::Field {
+                    base: Box::new(expr),
+                    field,
+                    span,
+                };
+            } else if self.at_simple(TokenKind::LBrace) && self.at_field_init(1) {
+                self.bump();
+                let fields = self.parse_field_inits()?;
+                let close =
+                    self.expect_simple(TokenKind::RBrace, "expected `}` in record update")?;
+                let span = expr.span().merge(close.span);
+                expr = Expr::Update {
+                    base: Box::new(expr),
+                    fields,
+                    span,
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn at_field_init(&self, n: usize) -> bool {
+        matches!(
+            self.peek_nth(n).kind,
+            TokenKind::Ident(_) | TokenKind::SymRef(_)
+        ) && self.lookahead_is_simple(n + 1, TokenKind::Eq)
+    }
+
+    fn parse_field_inits(&mut self) -> Result<Vec<FieldInit>, ParseError> {
+        let mut fields = Vec::new();
+        loop {
+            let name = self.expect_name("expected field name")?;
+            self.expect_simple(TokenKind::Eq, "expected `=` after field name")?;
+            let value = self.parse_expr()?;
+            fields.push(FieldInit {
+                span: name.span.merge(value.span()),
+                name,
+                value,
+            });
+            if !self.at_simple(TokenKind::Comma) {
+                return Ok(fields);
+            }
+            self.bump();
+        }
+    }
+
+    fn parse_prefix_expr(&mut self) -> Result<Expr, ParseError> {
         if self.at_simple(TokenKind::LBrace) {
             return self.parse_block_expr();
         }
@@ -576,6 +818,9 @@ impl Parser {
         if self.at_ident_text("v") && self.lookahead_is_simple(1, TokenKind::LParen) {
             return self.parse_let_expr();
         }
+        if self.at_ident_text("w") && self.lookahead_is_simple(1, TokenKind::LParen) {
+            return self.parse_let_rec_expr();
+        }
         if self.at_ident_text("i") && self.lookahead_is_simple(1, TokenKind::LParen) {
             return self.parse_if_expr();
         }
@@ -623,6 +868,18 @@ impl Parser {
                     span: open.span.merge(close.span),
                 });
             }
+            if self.at_simple(TokenKind::Comma) {
+                let mut items = vec![first];
+                while self.at_simple(TokenKind::Comma) {
+                    self.bump();
+                    items.push(self.parse_expr()?);
+                }
+                let close = self.expect_simple(TokenKind::RParen, "expected `)` in tuple")?;
+                return Ok(Expr::Tuple {
+                    items,
+                    span: open.span.merge(close.span),
+                });
+            }
             let mut args = Vec::new();
             while !self.at_simple(TokenKind::RParen) {
                 args.push(self.parse_expr()?);
@@ -668,9 +925,49 @@ impl Parser {
 
     fn parse_name_or_name_app(&mut self) -> Result<Expr, ParseError> {
         let name = self.expect_name("expected identifier")?;
+        if self.at_simple(TokenKind::Dot)
+            && matches!(
+                self.peek_nth(1).kind,
+                TokenKind::Ident(_) | TokenKind::SymRef(_)
+            )
+            && self.lookahead_is_simple(2, TokenKind::LParen)
+        {
+            let checkpoint = self.pos;
+            self.bump();
+            let ctor = self.expect_name("expected constructor name after `.`")?;
+            if let Some(app) = self.parse_name_app(Some(name.clone()), ctor)? {
+                return Ok(app);
+            }
+            self.pos = checkpoint;
+            return Ok(Expr::Name(name));
+        }
         if !self.at_simple(TokenKind::LParen) {
             return Ok(Expr::Name(name));
         }
+        if self.at_field_init(1) {
+            self.bump();
+            let fields = self.parse_field_inits()?;
+            let close = self.expect_simple(TokenKind::RParen, "expected `)` in record literal")?;
+            return Ok(Expr::Record {
+                span: name.span.merge(close.span),
+                name,
+                fields,
+            });
+        }
+        match self.parse_name_app(None, name.clone())? {
+            Some(app) => Ok(app),
+            None => Ok(Expr::Name(name)),
+        }
+    }
+
+    // Parses `(args)` after a constructor name. In compressed call forms a
+    // name may be followed by an unrelated parenthesized argument, so this
+    // backtracks and returns `None` when the parens do not hold a list.
+    fn parse_name_app(
+        &mut self,
+        module: Option<Ident>,
+        name: Ident,
+    ) -> Result<Option<Expr>, ParseError> {
         let checkpoint = self.pos;
         self.bump();
         let mut args = Vec::new();
@@ -678,7 +975,7 @@ impl Parser {
             let first = self.parse_expr()?;
             if !self.at_simple(TokenKind::Comma) && !self.at_simple(TokenKind::RParen) {
                 self.pos = checkpoint;
-                return Ok(Expr::Name(name));
+                return Ok(None);
             }
             args.push(first);
             while self.at_simple(TokenKind::Comma) {
@@ -687,11 +984,13 @@ impl Parser {
             }
         }
         let close = self.expect_simple(TokenKind::RParen, "expected `)` in name application")?;
-        Ok(Expr::NameApp {
-            span: name.span.merge(close.span),
+        let start = module.as_ref().map_or(name.span, |alias| alias.span);
+        Ok(Some(Expr::NameApp {
+            span: start.merge(close.span),
+            module,
             name,
             args,
-        })
+        }))
     }
 
     fn parse_symbol_name_expr(&mut self) -> Result<Expr, ParseError> {
@@ -752,6 +1051,9 @@ impl Parser {
     }
 
     fn parse_let_expr(&mut self) -> Result<Expr, ParseError> {
+        if self.lookahead_is_simple(2, TokenKind::LParen) {
+            return self.parse_let_pattern_expr();
+        }
         let start = self.expect_ident_text("v", "expected `v`")?;
         self.expect_simple(TokenKind::LParen, "expected `(` in let expression")?;
         let name = self.expect_name("expected let binding name")?;
@@ -775,6 +1077,46 @@ impl Parser {
         })
     }
 
+    fn parse_let_rec_expr(&mut self) -> Result<Expr, ParseError> {
+        let start = self.expect_ident_text("w", "expected `w`")?;
+        self.expect_simple(TokenKind::LParen, "expected `(` in recursive let")?;
+        let mut bindings = Vec::new();
+        loop {
+            let name = self.expect_name("expected recursive binding name")?;
+            self.expect_simple(TokenKind::Eq, "expected `=` in recursive let")?;
+            bindings.push(self.parse_rec_binding(name)?);
+            self.expect_simple(TokenKind::Comma, "expected `,` in recursive let")?;
+            let at_binding = matches!(self.peek().kind, TokenKind::Ident(_) | TokenKind::SymRef(_))
+                && self.lookahead_is_simple(1, TokenKind::Eq);
+            if !at_binding {
+                break;
+            }
+        }
+        let body = self.parse_expr()?;
+        let close = self.expect_simple(TokenKind::RParen, "expected `)` in recursive let")?;
+        Ok(Expr::LetRec {
+            bindings,
+            body: Box::new(body),
+            span: start.span.merge(close.span),
+        })
+    }
+
+    fn parse_rec_binding(&mut self, name: Ident) -> Result<FieldInit, ParseError> {
+        let value = self.parse_expr()?;
+        if !matches!(value, Expr::Lambda { .. }) {
+            return Err(ParseError {
+                code: ParseErrorCode::ExpectedExpr,
+                span: value.span(),
+                message: format!("recursive binding `{}` must be a lambda", name.display()),
+            });
+        }
+        Ok(FieldInit {
+            span: name.span.merge(value.span()),
+            name,
+            value,
+        })
+    }
+
     fn parse_if_expr(&mut self) -> Result<Expr, ParseError> {
         let start = self.expect_ident_text("i", "expected `i`")?;
         self.expect_simple(TokenKind::LParen, "expected `(` in if expression")?;
@@ -812,16 +1154,26 @@ impl Parser {
 
     fn parse_match_arm(&mut self) -> Result<MatchArm, ParseError> {
         let pattern = self.parse_pattern()?;
+        let guard = self.parse_match_guard()?;
         self.expect_simple(TokenKind::FatArrow, "expected `=>` in match arm")?;
         let expr = self.parse_expr()?;
         let semi = self.expect_simple(TokenKind::Semicolon, "expected `;` after match arm")?;
         Ok(MatchArm {
             span: pattern.span().merge(semi.span),
             pattern,
+            guard,
             expr,
         })
     }
 
+    fn parse_match_guard(&mut self) -> Result<Option<Expr>, ParseError> {
+        if !self.at_simple(TokenKind::If) {
+            return Ok(None);
+        }
+        self.bump();
+        Ok(Some(self.parse_expr()?))
+    }
+
     fn parse_call_expr(&mut self) -> Result<Expr, ParseError> {
         let start = self.expect_ident_text("c", "expected `c`")?;
         self.expect_simple(TokenKind::LParen, "expected `(` in call expression")?;
@@ -850,17 +1202,14 @@ impl Parser {
         self.expect_simple(TokenKind::RParen, "expected `)` in lambda params")?;
         self.expect_simple(TokenKind::Colon, "expected `:` before lambda return type")?;
         let ret = self.parse_type()?;
-        let effects = if self.at_simple(TokenKind::Bang) {
-            self.parse_effect_set()?
-        } else {
-            EffectSet::default()
-        };
+        let (effects, effects_span) = self.parse_optional_effect_set(ret.span())?;
         self.expect_simple(TokenKind::Eq, "expected `=` in lambda")?;
         let body = self.parse_expr()?;
         Ok(Expr::Lambda {
             params,
             ret,
             effects,
+            effects_span,
             span: start.span.merge(body.span()),
             body: Box::new(body),
         })
@@ -899,6 +1248,20 @@ impl Parser {
         let open = self.expect_simple(TokenKind::LBracket, "expected `[`")?;
         let head = self.expect_plain_ident("expected bracket form head")?;
         let expr = match head.as_str() {
+            "v" if self.at_simple(TokenKind::LParen) => {
+                let pattern = self.parse_let_tuple_pattern()?;
+                let value = self.parse_expr()?;
+                let body = self.parse_expr()?;
+                let close =
+                    self.expect_simple(TokenKind::RBracket, "expected `]` in bracket let")?;
+                Expr::LetPattern {
+                    pattern,
+                    ty: None,
+                    value: Box::new(value),
+                    body: Box::new(body),
+                    span: open.span.merge(close.span),
+                }
+            }
             "v" => {
                 let name = self.expect_name("expected let binding name")?;
                 let value = self.parse_expr()?;
@@ -913,6 +1276,30 @@ impl Parser {
                     span: open.span.merge(close.span),
                 }
             }
+            "w" => {
+                let mut bindings = Vec::new();
+                while self.at_simple(TokenKind::LBrace) {
+                    self.bump();
+                    let name = self.expect_name("expected recursive binding name")?;
+                    bindings.push(self.parse_rec_binding(name)?);
+                    self.expect_simple(TokenKind::RBrace, "expected `}` in bracket recursive let")?;
+                }
+                if bindings.is_empty() {
+                    return Err(ParseError {
+                        code: ParseErrorCode::ExpectedExpr,
+                        span: self.peek().span,
+                        message: "bracket recursive let requires at least one binding".to_string(),
+                    });
+                }
+                let body = self.parse_expr()?;
+                let close = self
+                    .expect_simple(TokenKind::RBracket, "expected `]` in bracket recursive let")?;
+                Expr::LetRec {
+                    bindings,
+                    body: Box::new(body),
+                    span: open.span.merge(close.span),
+                }
+            }
             "i" => {
                 let cond = self.parse_expr()?;
                 let then_branch = self.parse_expr()?;
@@ -932,12 +1319,14 @@ impl Parser {
                 while self.at_simple(TokenKind::LBrace) {
                     let arm_open = self.bump();
                     let pattern = self.parse_pattern()?;
+                    let guard = self.parse_match_guard()?;
                     let arm_expr = self.parse_expr()?;
                     let arm_close =
                         self.expect_simple(TokenKind::RBrace, "expected `}` in bracket match arm")?;
                     arms.push(MatchArm {
                         span: arm_open.span.merge(arm_close.span),
                         pattern,
+                        guard,
                         expr: arm_expr,
                     });
                 }
@@ -966,11 +1355,7 @@ impl Parser {
                 self.expect_simple(TokenKind::RParen, "expected `)` in bracket lambda params")?;
                 self.expect_simple(TokenKind::Colon, "expected `:` in bracket lambda")?;
                 let ret = self.parse_type()?;
-                let effects = if self.at_simple(TokenKind::Bang) {
-                    self.parse_effect_set()?
-                } else {
-                    EffectSet::default()
-                };
+                let (effects, effects_span) = self.parse_optional_effect_set(ret.span())?;
                 let body = self.parse_expr()?;
                 let close =
                     self.expect_simple(TokenKind::RBracket, "expected `]` in bracket lambda")?;
@@ -978,6 +1363,7 @@ impl Parser {
                     params,
                     ret,
                     effects,
+                    effects_span,
                     body: Box::new(body),
                     span: open.span.merge(close.span),
                 }
@@ -1008,7 +1394,57 @@ impl Parser {
         }
     }
 
+    fn parse_let_pattern_expr(&mut self) -> Result<Expr, ParseError> {
+        let start = self.expect_ident_text("v", "expected `v`")?;
+        self.expect_simple(TokenKind::LParen, "expected `(` in let expression")?;
+        let pattern = self.parse_let_tuple_pattern()?;
+        let ty = if self.at_simple(TokenKind::Colon) {
+            self.bump();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect_simple(TokenKind::Eq, "expected `=` in let expression")?;
+        let value = self.parse_expr()?;
+        self.expect_simple(TokenKind::Comma, "expected `,` in let expression")?;
+        let body = self.parse_expr()?;
+        let close = self.expect_simple(TokenKind::RParen, "expected `)` in let expression")?;
+        Ok(Expr::LetPattern {
+            pattern,
+            ty,
+            value: Box::new(value),
+            body: Box::new(body),
+            span: start.span.merge(close.span),
+        })
+    }
+
+    fn parse_let_tuple_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let pattern = self.parse_pattern()?;
+        if !matches!(pattern, Pattern::Tuple { .. }) {
+            return Err(ParseError {
+                code: ParseErrorCode::UnexpectedToken,
+                span: pattern.span(),
+                message: "let destructuring expects a tuple pattern".to_string(),
+            });
+        }
+        Ok(pattern)
+    }
+
     fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let first = self.parse_single_pattern()?;
+        if !self.at_simple(TokenKind::Pipe) {
+            return Ok(first);
+        }
+        let mut alts = vec![first];
+        while self.at_simple(TokenKind::Pipe) {
+            self.bump();
+            alts.push(self.parse_single_pattern()?);
+        }
+        let span = alts[0].span().merge(alts[alts.len() - 1].span());
+        Ok(Pattern::Or { alts, span })
+    }
+
+    fn parse_single_pattern(&mut self) -> Result<Pattern, ParseError> {
         if self.at_simple(TokenKind::Underscore) {
             return Ok(Pattern::Wildcard(self.bump().span));
         }
@@ -1044,9 +1480,24 @@ impl Parser {
             });
         }
 
-        let name = self.expect_name("expected pattern")?;
+        let mut name = self.expect_name("expected pattern")?;
+        let mut module = None;
+        let start = name.span;
+        if self.at_simple(TokenKind::Dot) {
+            self.bump();
+            module = Some(name);
+            name = self.expect_name("expected constructor name after `.`")?;
+        }
         if !self.at_simple(TokenKind::LParen) {
-            return Ok(Pattern::Name(name));
+            if module.is_none() {
+                return Ok(Pattern::Name(name));
+            }
+            return Ok(Pattern::Ctor {
+                span: start.merge(name.span),
+                module,
+                name,
+                args: Vec::new(),
+            });
         }
         self.bump();
         let mut args = Vec::new();
@@ -1055,7 +1506,8 @@ impl Parser {
         }
         let close = self.expect_simple(TokenKind::RParen, "expected `)` in constructor pattern")?;
         Ok(Pattern::Ctor {
-            span: name.span.merge(close.span),
+            span: start.merge(close.span),
+            module,
             name,
             args,
         })
@@ -1106,6 +1558,11 @@ impl Parser {
                 }
                 Ok(Ident::from_sym(idx, token.span))
             }
+            TokenKind::If => Err(ParseError {
+                code: ParseErrorCode::ExpectedIdent,
+                span: token.span,
+                message: "`if` is a reserved word and cannot be used as a name".to_string(),
+            }),
             _ => Err(ParseError {
                 code: ParseErrorCode::ExpectedIdent,
                 span: token.span,
@@ -1166,6 +1623,11 @@ impl Parser {
         &self.tokens[self.pos]
     }
 
+    fn peek_nth(&self, n: usize) -> &Token {
+        let idx = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[idx]
+    }
+
     fn bump(&mut self) -> Token {
         let token = self.tokens[self.pos].clone();
         if !matches!(token.kind, TokenKind::Eof) {
@@ -1174,3 +1636,16 @@ impl Parser {
         token
     }
 }
+
+fn effect_atom(ident: &str) -> Option<EffectAtom> {
+    match ident {
+        "io" | "I" => Some(EffectAtom::Io),
+        "fs" | "F" => Some(EffectAtom::Fs),
+        "net" | "N" => Some(EffectAtom::Net),
+        "proc" | "P" => Some(EffectAtom::Proc),
+        "rand" | "R" => Some(EffectAtom::Rand),
+        "time" | "T" => Some(EffectAtom::Time),
+        "st" | "S" => Some(EffectAtom::St),
+        _ => None,
+    }
+}
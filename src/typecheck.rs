@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
 
@@ -5,6 +6,7 @@ use crate::ast::{
     Decl, EffectAtom, EffectSet, Expr, FunctionType, Literal, Pattern, PrimType, Program, Span,
     TypeExpr,
 };
+use crate::diagnostic::Note;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeErrorCode {
@@ -22,6 +24,10 @@ pub enum TypeErrorCode {
     InvalidEffectSet,
     InvalidReturnMagic,
     InvalidMainSignature,
+    UnreachableArm,
+    InvalidTry,
+    InvalidField,
+    InvalidTypeAlias,
 }
 
 impl TypeErrorCode {
@@ -41,6 +47,10 @@ impl TypeErrorCode {
             TypeErrorCode::InvalidEffectSet => "E3012",
             TypeErrorCode::InvalidReturnMagic => "E3013",
             TypeErrorCode::InvalidMainSignature => "E3014",
+            TypeErrorCode::UnreachableArm => "E3015",
+            TypeErrorCode::InvalidTry => "E3016",
+            TypeErrorCode::InvalidField => "E3017",
+            TypeErrorCode::InvalidTypeAlias => "E3018",
         }
     }
 }
@@ -50,6 +60,7 @@ pub struct TypeError {
     pub code: TypeErrorCode,
     pub span: Span,
     pub message: String,
+    pub notes: Vec<Note>,
 }
 
 impl fmt::Display for TypeError {
@@ -67,6 +78,31 @@ impl fmt::Display for TypeError {
 
 impl std::error::Error for TypeError {}
 
+impl TypeError {
+    fn new(code: TypeErrorCode, span: Span, message: String) -> Self {
+        TypeError {
+            code,
+            span,
+            message,
+            notes: Vec::new(),
+        }
+    }
+
+    fn with_label(mut self, span: Span, message: String) -> Self {
+        self.notes.push(Note::Label { span, message });
+        self
+    }
+
+    fn with_fix(mut self, span: Span, replacement: String, message: String) -> Self {
+        self.notes.push(Note::Fix {
+            span,
+            replacement,
+            message,
+        });
+        self
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     Bool,
@@ -90,13 +126,19 @@ pub enum Type {
     },
     Result(Box<Type>, Box<Type>),
     TypeVar(String),
+    Var(u32),
+    // The type of an expression that already failed to check. It unifies with
+    // everything, so one mistake is reported once rather than at every use.
+    Error,
 }
 
 #[derive(Debug, Clone)]
 struct CtorSig {
+    module: String,
     parent: String,
     type_params: Vec<String>,
     fields: Vec<TypeExpr>,
+    field_names: Vec<String>,
     symtab: Option<Vec<String>>,
 }
 
@@ -104,18 +146,47 @@ struct CtorSig {
 struct ModuleSigs {
     values: BTreeMap<String, Type>,
     ctors: BTreeMap<String, CtorSig>,
+    types: BTreeSet<String>,
+    aliases: BTreeMap<String, AliasSig>,
     exports: BTreeSet<String>,
+    opaque: BTreeSet<String>,
     imports: BTreeMap<String, String>,
 }
 
+// A type alias body, converted in its own module with the parameters left as
+// `TypeVar`s.
+#[derive(Debug, Clone)]
+struct AliasSig {
+    params: Vec<String>,
+    ty: Type,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Infer {
+    subst: Vec<Option<Type>>,
+    effects: Vec<Option<EffectSet>>,
+}
+
 #[derive(Debug, Clone)]
 struct CheckCtx<'a> {
     module_name: &'a str,
     module: &'a ModuleSigs,
+    modules: &'a BTreeMap<String, ModuleSigs>,
     symtab: Option<&'a [String]>,
+    infer: &'a RefCell<Infer>,
+    errors: &'a RefCell<Vec<TypeError>>,
+    type_vars: HashMap<String, Type>,
     locals: HashMap<String, Type>,
     return_type: Option<Type>,
     allow_return_magic: bool,
+    // Locals visible on entry to the enclosing function body. Ensures run
+    // when that function returns, so they may only use these (and `_r`).
+    exit_locals: HashMap<String, Type>,
+}
+
+enum UnifyError {
+    Mismatch,
+    Occurs(u32, Type),
 }
 
 #[derive(Debug, Clone)]
@@ -132,69 +203,134 @@ pub fn check_program(program: &Program) -> Result<(), TypeError> {
     check_programs(std::slice::from_ref(program))
 }
 
+// Checks one module against its workspace and reports every error in it.
+// Signature errors (duplicate declarations, bad imports/exports/aliases) stop
+// at the first one.
 pub fn check_program_with_modules(
     program: &Program,
     programs: &[Program],
-) -> Result<(), TypeError> {
-    let modules = build_module_sigs(programs)?;
+) -> Result<(), Vec<TypeError>> {
+    let modules = build_module_sigs(programs).map_err(|err| vec![err])?;
     let module_name = modid_to_string(&program.module.mod_id.parts);
-    check_one_module(program, &module_name, &modules)
+    let errors = check_one_module(program, &module_name, &modules);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
 }
 
-pub fn validate_modules(programs: &[Program]) -> Result<(), TypeError> {
-    let _ = build_module_sigs(programs)?;
-    Ok(())
+// Checks module signatures only. A failure carries the index of the program
+// whose signatures were being built.
+pub fn validate_modules(programs: &[Program]) -> Result<(), (usize, TypeError)> {
+    let mut current = 0;
+    match build_module_sigs_tracking(programs, &mut current) {
+        Ok(_) => Ok(()),
+        Err(err) => Err((current, err)),
+    }
 }
 
+// Like `check_programs_all`, but returns only the first error.
 pub fn check_programs(programs: &[Program]) -> Result<(), TypeError> {
-    let modules = build_module_sigs(programs)?;
+    check_programs_all(programs).map_err(|errors| errors.into_iter().next().expect("errors"))
+}
+
+// Checks every module, returning all errors grouped by module in input order.
+pub fn check_programs_all(programs: &[Program]) -> Result<(), Vec<TypeError>> {
+    let modules = build_module_sigs(programs).map_err(|err| vec![err])?;
+    let mut errors = Vec::new();
     for program in programs {
         let name = modid_to_string(&program.module.mod_id.parts);
-        check_one_module(program, &name, &modules)?;
+        errors.extend(check_one_module(program, &name, &modules));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
     }
-    Ok(())
 }
 
 fn build_module_sigs(programs: &[Program]) -> Result<BTreeMap<String, ModuleSigs>, TypeError> {
+    build_module_sigs_tracking(programs, &mut 0)
+}
+
+// `current` is left at the index of the program being processed when an
+// error is returned.
+fn build_module_sigs_tracking(
+    programs: &[Program],
+    current: &mut usize,
+) -> Result<BTreeMap<String, ModuleSigs>, TypeError> {
     let mut modules = builtin_module_sigs();
-    for program in programs {
+    for (idx, program) in programs.iter().enumerate() {
+        *current = idx;
         let module_name = modid_to_string(&program.module.mod_id.parts);
         if modules.contains_key(&module_name) {
-            return Err(TypeError {
-                code: TypeErrorCode::DuplicateModule,
-                span: program.module.span,
-                message: format!("duplicate module `{module_name}`"),
-            });
+            return Err(TypeError::new(
+                TypeErrorCode::DuplicateModule,
+                program.module.span,
+                format!("duplicate module `{module_name}`"),
+            ));
         }
         let mut values = BTreeMap::new();
         let mut ctors = BTreeMap::new();
+        let mut types = BTreeSet::new();
+        let mut aliases = BTreeMap::new();
         let mut exports = BTreeSet::new();
+        let mut opaque = BTreeSet::new();
         let mut imports = BTreeMap::new();
 
+        // Aliases are collected first so signatures can mention `alias.Type`
+        // regardless of declaration order.
         for decl in &program.module.decls {
-            match decl {
-                Decl::Import(d) => {
-                    let alias = id_text(&d.alias, program.module.symtab.as_deref());
-                    let prev = imports.insert(alias.clone(), modid_to_string(&d.module.parts));
-                    if prev.is_some() {
-                        return Err(TypeError {
-                            code: TypeErrorCode::DuplicateSymbol,
-                            span: d.span,
-                            message: format!("duplicate import alias `{alias}`"),
-                        });
-                    }
+            if let Decl::Import(d) = decl {
+                let alias = id_text(&d.alias, program.module.symtab.as_deref());
+                let prev = imports.insert(alias.clone(), modid_to_string(&d.module.parts));
+                if prev.is_some() {
+                    return Err(TypeError::new(
+                        TypeErrorCode::DuplicateSymbol,
+                        d.span,
+                        format!("duplicate import alias `{alias}`"),
+                    ));
                 }
+            }
+        }
+
+        for decl in &program.module.decls {
+            match decl {
+                Decl::Import(_) => {}
                 Decl::Export(d) => {
-                    for name in &d.names {
+                    for (name, is_opaque) in d.names.iter().zip(&d.opaque) {
                         let exported = id_text(name, program.module.symtab.as_deref());
                         if !exports.insert(exported.clone()) {
-                            return Err(TypeError {
-                                code: TypeErrorCode::DuplicateSymbol,
-                                span: name.span,
-                                message: format!("duplicate export name `{exported}`"),
-                            });
+                            return Err(TypeError::new(
+                                TypeErrorCode::DuplicateSymbol,
+                                name.span,
+                                format!("duplicate export name `{exported}`"),
+                            ));
                         }
+                        if *is_opaque {
+                            opaque.insert(exported);
+                        }
+                    }
+                }
+                Decl::Alias(d) => {
+                    let symtab = program.module.symtab.as_deref();
+                    let alias_name = id_text(&d.name, symtab);
+                    if !types.insert(alias_name.clone()) {
+                        return Err(TypeError::new(
+                            TypeErrorCode::DuplicateSymbol,
+                            d.span,
+                            format!("duplicate type `{alias_name}`"),
+                        ));
                     }
+                    let vars = rigid_type_vars(&d.params, symtab);
+                    aliases.insert(
+                        alias_name,
+                        AliasSig {
+                            params: d.params.iter().map(|p| id_text(p, symtab)).collect(),
+                            ty: ast_type_to_type_with_vars(&d.ty, &vars, symtab, &imports),
+                        },
+                    );
                 }
                 Decl::Type(d) => {
                     let type_params = d
@@ -203,21 +339,42 @@ fn build_module_sigs(programs: &[Program]) -> Result<BTreeMap<String, ModuleSigs
                         .map(|p| id_text(p, program.module.symtab.as_deref()))
                         .collect::<Vec<_>>();
                     let parent_name = id_text(&d.name, program.module.symtab.as_deref());
+                    if !types.insert(parent_name.clone()) {
+                        return Err(TypeError::new(
+                            TypeErrorCode::DuplicateSymbol,
+                            d.span,
+                            format!("duplicate type `{parent_name}`"),
+                        ));
+                    }
                     for ctor in &d.ctors {
                         let ctor_name = id_text(&ctor.name, program.module.symtab.as_deref());
                         if ctors.contains_key(&ctor_name) {
-                            return Err(TypeError {
-                                code: TypeErrorCode::DuplicateSymbol,
-                                span: ctor.span,
-                                message: format!("duplicate constructor `{ctor_name}`"),
-                            });
+                            return Err(TypeError::new(
+                                TypeErrorCode::DuplicateSymbol,
+                                ctor.span,
+                                format!("duplicate constructor `{ctor_name}`"),
+                            ));
+                        }
+                        let mut field_names = Vec::new();
+                        for field_name in &ctor.field_names {
+                            let text = id_text(field_name, program.module.symtab.as_deref());
+                            if field_names.contains(&text) {
+                                return Err(TypeError::new(
+                                    TypeErrorCode::DuplicateSymbol,
+                                    field_name.span,
+                                    format!("duplicate field `{text}` in record `{ctor_name}`"),
+                                ));
+                            }
+                            field_names.push(text);
                         }
                         ctors.insert(
                             ctor_name,
                             CtorSig {
+                                module: module_name.clone(),
                                 parent: parent_name.clone(),
                                 type_params: type_params.clone(),
                                 fields: ctor.fields.clone(),
+                                field_names,
                                 symtab: program.module.symtab.clone(),
                             },
                         );
@@ -226,42 +383,37 @@ fn build_module_sigs(programs: &[Program]) -> Result<BTreeMap<String, ModuleSigs
                 Decl::Value(d) => {
                     let value_name = id_text(&d.name, program.module.symtab.as_deref());
                     if values.contains_key(&value_name) {
-                        return Err(TypeError {
-                            code: TypeErrorCode::DuplicateSymbol,
-                            span: d.span,
-                            message: format!("duplicate value `{value_name}`"),
-                        });
+                        return Err(TypeError::new(
+                            TypeErrorCode::DuplicateSymbol,
+                            d.span,
+                            format!("duplicate value `{value_name}`"),
+                        ));
                     }
                     values.insert(
                         value_name,
-                        ast_type_to_type(&d.ty, program.module.symtab.as_deref())?,
+                        ast_type_to_type(&d.ty, program.module.symtab.as_deref(), &imports)?,
                     );
                 }
                 Decl::Function(d) => {
-                    validate_effect_set(&d.sig.effects, d.sig.span)?;
+                    validate_effect_set(&d.sig.effects, d.sig.effects_span)?;
+                    check_effect_vars_bound(&d.sig)?;
                     let fn_name = id_text(&d.name, program.module.symtab.as_deref());
                     if values.contains_key(&fn_name) {
-                        return Err(TypeError {
-                            code: TypeErrorCode::DuplicateSymbol,
-                            span: d.span,
-                            message: format!("duplicate value `{fn_name}`"),
-                        });
+                        return Err(TypeError::new(
+                            TypeErrorCode::DuplicateSymbol,
+                            d.span,
+                            format!("duplicate value `{fn_name}`"),
+                        ));
                     }
+                    let vars = rigid_type_vars(&d.type_params, program.module.symtab.as_deref());
                     values.insert(
                         fn_name,
-                        Type::Function {
-                            params: d
-                                .sig
-                                .params
-                                .iter()
-                                .map(|t| ast_type_to_type(t, program.module.symtab.as_deref()))
-                                .collect::<Result<_, _>>()?,
-                            ret: Box::new(ast_type_to_type(
-                                &d.sig.ret,
-                                program.module.symtab.as_deref(),
-                            )?),
-                            effects: d.sig.effects.clone(),
-                        },
+                        function_type_to_type(
+                            &d.sig,
+                            &vars,
+                            program.module.symtab.as_deref(),
+                            &imports,
+                        ),
                     );
                 }
             }
@@ -272,148 +424,536 @@ fn build_module_sigs(programs: &[Program]) -> Result<BTreeMap<String, ModuleSigs
             ModuleSigs {
                 values,
                 ctors,
+                types,
+                aliases,
                 exports,
+                opaque,
                 imports,
             },
         );
     }
 
-    for program in programs {
+    for (idx, program) in programs.iter().enumerate() {
+        *current = idx;
         let module_name = modid_to_string(&program.module.mod_id.parts);
         let sigs = modules.get(&module_name).expect("module must exist");
         for target in sigs.imports.values() {
             if !modules.contains_key(target) {
-                return Err(TypeError {
-                    code: TypeErrorCode::UnknownModule,
-                    span: program.module.span,
-                    message: format!("unknown imported module `{target}`"),
-                });
+                return Err(TypeError::new(
+                    TypeErrorCode::UnknownModule,
+                    program.module.span,
+                    format!("unknown imported module `{target}`"),
+                ));
             }
         }
-        for exported in &sigs.exports {
-            if !sigs.values.contains_key(exported) && !sigs.ctors.contains_key(exported) {
-                return Err(TypeError {
-                    code: TypeErrorCode::InvalidExport,
-                    span: program.module.span,
-                    message: format!("exported name `{exported}` is not declared"),
-                });
+        let symtab = program.module.symtab.as_deref();
+        for decl in &program.module.decls {
+            let Decl::Export(d) = decl else {
+                continue;
+            };
+            for (idx, name) in d.names.iter().enumerate() {
+                let exported = id_text(name, symtab);
+                if !sigs.values.contains_key(&exported)
+                    && !sigs.ctors.contains_key(&exported)
+                    && !sigs.types.contains(&exported)
+                {
+                    let err = TypeError::new(
+                        TypeErrorCode::InvalidExport,
+                        name.span,
+                        format!("exported name `{exported}` is not declared"),
+                    );
+                    let message = format!("remove `{exported}` from the export list");
+                    return Err(err.with_fix(d.span, export_without(d, idx, symtab), message));
+                }
+            }
+        }
+        for name in &sigs.opaque {
+            if !sigs.types.contains(name) || sigs.aliases.contains_key(name) {
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidExport,
+                    program.module.span,
+                    format!(
+                        "`~{name}` must name a type declared with `T`; only those can be opaque"
+                    ),
+                ));
+            }
+            // A constructor named like its type is covered by the type's `~`.
+            if let Some((ctor, _)) = sigs.ctors.iter().find(|(ctor, sig)| {
+                &sig.parent == name && *ctor != name && sigs.exports.contains(*ctor)
+            }) {
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidExport,
+                    program.module.span,
+                    format!("constructor `{ctor}` of opaque type `{name}` cannot be exported"),
+                ));
+            }
+        }
+        for decl in &program.module.decls {
+            match decl {
+                Decl::Alias(d) => {
+                    check_type_refs(&d.ty, sigs, &modules, symtab)?;
+                    let name = id_text(&d.name, symtab);
+                    if alias_is_cyclic(&modules, &module_name, &name, &mut Vec::new()) {
+                        return Err(TypeError::new(
+                            TypeErrorCode::InvalidTypeAlias,
+                            d.span,
+                            format!("type alias `{name}` refers to itself"),
+                        ));
+                    }
+                }
+                Decl::Type(d) => {
+                    for field in d.ctors.iter().flat_map(|ctor| &ctor.fields) {
+                        check_type_refs(field, sigs, &modules, symtab)?;
+                    }
+                }
+                Decl::Value(d) => check_type_refs(&d.ty, sigs, &modules, symtab)?,
+                Decl::Function(d) => {
+                    for ty in d.sig.params.iter().chain(std::iter::once(&*d.sig.ret)) {
+                        check_type_refs(ty, sigs, &modules, symtab)?;
+                    }
+                }
+                Decl::Import(_) | Decl::Export(_) => {}
             }
         }
     }
 
+    // Signatures are stored with aliases expanded, once every module's
+    // aliases are known.
+    let expanded = modules
+        .iter()
+        .map(|(name, sigs)| {
+            let values = sigs
+                .values
+                .iter()
+                .map(|(value, ty)| (value.clone(), expand_aliases(ty, name, &modules)))
+                .collect::<BTreeMap<_, _>>();
+            (name.clone(), values)
+        })
+        .collect::<Vec<_>>();
+    for (name, values) in expanded {
+        modules.get_mut(&name).expect("module must exist").values = values;
+    }
+
     Ok(modules)
 }
 
+// Source for export declaration `d` without its `idx`th name; empty when no
+// names remain.
+fn export_without(d: &crate::ast::ExportDecl, idx: usize, symtab: Option<&[String]>) -> String {
+    let names = d
+        .names
+        .iter()
+        .zip(&d.opaque)
+        .enumerate()
+        .filter(|(i, _)| *i != idx)
+        .map(|(_, (name, is_opaque))| {
+            let tilde = if *is_opaque { "~" } else { "" };
+            format!("{tilde}{}", id_text(name, symtab))
+        })
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!("E[{}];", names.join(","))
+    }
+}
+
+// Whether expanding alias `name` of module `module` reaches itself again.
+fn alias_is_cyclic(
+    modules: &BTreeMap<String, ModuleSigs>,
+    module: &str,
+    name: &str,
+    visiting: &mut Vec<(String, String)>,
+) -> bool {
+    let key = (module.to_string(), name.to_string());
+    if visiting.contains(&key) {
+        return true;
+    }
+    let Some(alias) = modules.get(module).and_then(|sigs| sigs.aliases.get(name)) else {
+        return false;
+    };
+    visiting.push(key);
+    let mut cyclic = false;
+    visit_named_types(&alias.ty, &mut |named| {
+        let (owner, base) = split_type_name(named, module, modules);
+        cyclic = cyclic || alias_is_cyclic(modules, owner, base, visiting);
+    });
+    visiting.pop();
+    cyclic
+}
+
+fn visit_named_types(ty: &Type, f: &mut dyn FnMut(&str)) {
+    if let Type::Named(name, _) = ty {
+        f(name);
+    }
+    map_type_children(ty, &mut |child| {
+        visit_named_types(child, f);
+        child.clone()
+    });
+}
+
+// The declaring module and bare name of a named type as seen from `module`:
+// other user modules' types carry a `module.` prefix.
+fn split_type_name<'n>(
+    name: &'n str,
+    module: &'n str,
+    modules: &BTreeMap<String, ModuleSigs>,
+) -> (&'n str, &'n str) {
+    match name.rsplit_once('.') {
+        Some((owner, base)) if modules.contains_key(owner) => (owner, base),
+        _ => (module, name),
+    }
+}
+
+// Replaces alias references in `ty`, which is written from `module`'s point
+// of view, by their bodies. Aliases are checked to be acyclic beforehand.
+fn expand_aliases(ty: &Type, module: &str, modules: &BTreeMap<String, ModuleSigs>) -> Type {
+    let Type::Named(name, args) = ty else {
+        return map_type_children(ty, &mut |child| expand_aliases(child, module, modules));
+    };
+    let args = args
+        .iter()
+        .map(|arg| expand_aliases(arg, module, modules))
+        .collect::<Vec<_>>();
+    let (owner, base) = split_type_name(name, module, modules);
+    let Some(owner_sigs) = modules.get(owner) else {
+        return Type::Named(name.clone(), args);
+    };
+    let Some(alias) = owner_sigs
+        .aliases
+        .get(base)
+        .filter(|alias| alias.params.len() == args.len())
+    else {
+        return Type::Named(name.clone(), args);
+    };
+    let body = import_type(&alias.ty, owner, owner_sigs, module);
+    let params = alias
+        .params
+        .iter()
+        .cloned()
+        .zip(args)
+        .collect::<HashMap<_, _>>();
+    expand_aliases(&substitute_type_vars(&body, &params), module, modules)
+}
+
+fn substitute_type_vars(ty: &Type, params: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::TypeVar(name) => params.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        _ => map_type_children(ty, &mut |child| substitute_type_vars(child, params)),
+    }
+}
+
+// Validates every `alias.Type` reference in `ty`: the alias must be imported
+// and the target module must declare and export the type.
+fn check_type_refs(
+    ty: &TypeExpr,
+    sigs: &ModuleSigs,
+    modules: &BTreeMap<String, ModuleSigs>,
+    symtab: Option<&[String]>,
+) -> Result<(), TypeError> {
+    match ty {
+        TypeExpr::Prim(_, _) => Ok(()),
+        TypeExpr::Named {
+            module,
+            name,
+            args,
+            span,
+        } => {
+            let mut owner = sigs;
+            let name_text = id_text(name, symtab);
+            if let Some(alias) = module {
+                let alias = id_text(alias, symtab);
+                let name = &name_text;
+                let Some(target) = sigs.imports.get(&alias) else {
+                    return Err(TypeError::new(
+                        TypeErrorCode::UnknownModule,
+                        *span,
+                        format!("unknown import alias `{alias}` in type `{alias}.{name}`"),
+                    ));
+                };
+                let target_sigs = modules.get(target).expect("imports are validated");
+                if !target_sigs.types.contains(name) {
+                    return Err(TypeError::new(
+                        TypeErrorCode::UnknownName,
+                        *span,
+                        format!("module `{target}` has no type `{name}`"),
+                    ));
+                }
+                if !target_sigs.exports.contains(name) {
+                    return Err(TypeError::new(
+                        TypeErrorCode::InvalidExport,
+                        *span,
+                        format!("type `{name}` is not exported by module `{target}`"),
+                    ));
+                }
+                owner = target_sigs;
+            }
+            if let Some(alias) = owner.aliases.get(&name_text)
+                && alias.params.len() != args.len()
+            {
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidTypeAlias,
+                    *span,
+                    format!(
+                        "type alias `{name_text}` expects {} type args, got {}",
+                        alias.params.len(),
+                        args.len()
+                    ),
+                ));
+            }
+            args.iter()
+                .try_for_each(|arg| check_type_refs(arg, sigs, modules, symtab))
+        }
+        TypeExpr::Optional { inner, .. }
+        | TypeExpr::Array { inner, .. }
+        | TypeExpr::Group { inner, .. } => check_type_refs(inner, sigs, modules, symtab),
+        TypeExpr::Map { key, value, .. } => {
+            check_type_refs(key, sigs, modules, symtab)?;
+            check_type_refs(value, sigs, modules, symtab)
+        }
+        TypeExpr::Tuple { items, .. } => items
+            .iter()
+            .try_for_each(|item| check_type_refs(item, sigs, modules, symtab)),
+        TypeExpr::Function { sig, .. } => {
+            for param in &sig.params {
+                check_type_refs(param, sigs, modules, symtab)?;
+            }
+            check_type_refs(&sig.ret, sigs, modules, symtab)
+        }
+        TypeExpr::ResultSugar { ok, err, .. } => {
+            check_type_refs(ok, sigs, modules, symtab)?;
+            check_type_refs(err, sigs, modules, symtab)
+        }
+    }
+}
+
+// The core modules export the builtin table, split by the module that owns
+// each name; anything not listed under a host module belongs to the prelude.
 fn builtin_module_sigs() -> BTreeMap<String, ModuleSigs> {
+    let owned: [(&str, &[&str], &[&str]); 5] = [
+        ("core.io", &["print", "println", "readln"], &[]),
+        (
+            "core.fs",
+            &[
+                "read",
+                "write",
+                "append",
+                "list_dir",
+                "exists",
+                "mkdir",
+                "remove",
+                "read_lines",
+            ],
+            &[],
+        ),
+        ("core.json", &["parse", "stringify"], &["Json"]),
+        ("core.proc", &["run", "run_capture"], &["Output"]),
+        ("core.http", &["get", "request"], &["Response"]),
+    ];
+    let prelude = builtin_values()
+        .keys()
+        .map(String::as_str)
+        .filter(|name| !owned.iter().any(|(_, names, _)| names.contains(name)))
+        .collect::<Vec<_>>();
     let mut modules = BTreeMap::new();
-    for module_name in [
-        "core.prelude",
-        "core.io",
-        "core.fs",
-        "core.json",
-        "core.proc",
-        "core.http",
-    ] {
-        modules.insert(
-            module_name.to_string(),
-            ModuleSigs {
-                values: BTreeMap::new(),
-                ctors: BTreeMap::new(),
-                exports: BTreeSet::new(),
-                imports: BTreeMap::new(),
-            },
-        );
+    for (module_name, names, types) in owned {
+        modules.insert(module_name.to_string(), builtin_sigs(names, types));
     }
+    modules.insert("core.prelude".to_string(), builtin_sigs(&prelude, &[]));
     modules
 }
 
+fn builtin_sigs(names: &[&str], types: &[&str]) -> ModuleSigs {
+    let values = names
+        .iter()
+        .map(|name| (name.to_string(), builtin_values()[*name].clone()))
+        .collect::<BTreeMap<_, _>>();
+    let types = types
+        .iter()
+        .map(|ty| ty.to_string())
+        .collect::<BTreeSet<_>>();
+    ModuleSigs {
+        exports: values.keys().chain(&types).cloned().collect(),
+        values,
+        ctors: BTreeMap::new(),
+        types,
+        aliases: BTreeMap::new(),
+        opaque: BTreeSet::new(),
+        imports: BTreeMap::new(),
+    }
+}
+
+// Checks every declaration, returning all errors in source order.
 fn check_one_module(
     program: &Program,
     module_name: &str,
     modules: &BTreeMap<String, ModuleSigs>,
-) -> Result<(), TypeError> {
+) -> Vec<TypeError> {
     let module = modules.get(module_name).expect("module sig should exist");
+    let symtab = program.module.symtab.as_deref();
+    let errors = RefCell::new(Vec::new());
     for decl in &program.module.decls {
         match decl {
-            Decl::Import(_) | Decl::Export(_) | Decl::Type(_) => {}
+            Decl::Import(_) | Decl::Export(_) | Decl::Type(_) | Decl::Alias(_) => {}
             Decl::Value(v) => {
+                let infer = RefCell::new(Infer::default());
                 let mut ctx = CheckCtx {
                     module_name,
                     module,
-                    symtab: program.module.symtab.as_deref(),
+                    modules,
+                    symtab,
+                    infer: &infer,
+                    errors: &errors,
+                    type_vars: HashMap::new(),
                     locals: HashMap::new(),
                     return_type: None,
                     allow_return_magic: false,
+                    exit_locals: HashMap::new(),
                 };
-                let got = check_expr(&mut ctx, &v.expr)?;
-                let expected = ast_type_to_type(&v.ty, program.module.symtab.as_deref())?;
-                expect_type(&expected, &got.ty, v.expr.span())?;
+                let got = check_expr(&mut ctx, &v.expr);
+                let expected = &module.values[&id_text(&v.name, symtab)];
+                report(
+                    &ctx,
+                    expect_type(&ctx, expected, &got.ty, v.expr.span())
+                        .map_err(|err| annotated_here(err, &v.ty)),
+                );
             }
             Decl::Function(f) => {
-                if id_text(&f.name, program.module.symtab.as_deref()) == "main" {
-                    validate_main_signature(f)?;
+                let fn_name = id_text(&f.name, symtab);
+                let type_vars = rigid_type_vars(&f.type_params, symtab);
+                let Type::Function {
+                    params,
+                    ret,
+                    effects,
+                } = module.values[&fn_name].clone()
+                else {
+                    unreachable!("function signatures lower to function types");
+                };
+                if fn_name == "main"
+                    && let Err(err) = validate_main_signature(f, &ret)
+                {
+                    errors.borrow_mut().push(err);
                 }
+                let infer = RefCell::new(Infer::default());
                 let mut ctx = CheckCtx {
                     module_name,
                     module,
-                    symtab: program.module.symtab.as_deref(),
+                    modules,
+                    symtab,
+                    infer: &infer,
+                    errors: &errors,
+                    type_vars,
                     locals: HashMap::new(),
-                    return_type: Some(ast_type_to_type(
-                        &f.sig.ret,
-                        program.module.symtab.as_deref(),
-                    )?),
+                    return_type: Some((*ret).clone()),
                     allow_return_magic: false,
+                    exit_locals: HashMap::new(),
                 };
-                for (idx, param_ty) in f.sig.params.iter().enumerate() {
-                    let param_name = format!("arg{idx}");
-                    ctx.locals.insert(
-                        param_name,
-                        ast_type_to_type(param_ty, program.module.symtab.as_deref())?,
-                    );
-                }
-                let got = check_expr(&mut ctx, &f.expr)?;
-                let sig = function_type_to_type(&f.sig)?;
-                if let Type::Function { ret, effects, .. } = sig {
-                    expect_type(&ret, &got.ty, f.expr.span())?;
-                    if !effects_is_superset(&effects, &got.effects) {
-                        return Err(TypeError {
-                            code: TypeErrorCode::EffectViolation,
-                            span: f.expr.span(),
-                            message: format!(
-                                "function `{}` declared effects {} but body needs {}",
-                                id_text(&f.name, program.module.symtab.as_deref()),
-                                effect_set_to_string(&effects),
-                                effect_set_to_string(&got.effects)
+                for (idx, param_ty) in params.into_iter().enumerate() {
+                    let param_name = f.param_name(idx, symtab);
+                    if ctx.locals.insert(param_name.clone(), param_ty).is_some() {
+                        errors.borrow_mut().push(TypeError::new(
+                            TypeErrorCode::DuplicateSymbol,
+                            f.param_names[idx].span,
+                            format!(
+                                "duplicate parameter `{param_name}` in function `{}`",
+                                id_text(&f.name, symtab)
                             ),
-                        });
+                        ));
                     }
                 }
+                ctx.exit_locals = ctx.locals.clone();
+                let got = check_expr(&mut ctx, &f.expr);
+                report(
+                    &ctx,
+                    expect_type(&ctx, &ret, &got.ty, f.expr.span())
+                        .map_err(|err| annotated_here(err, &f.sig.ret)),
+                );
+                if !effects_within(&ctx, &effects, &got.effects) {
+                    let needed = resolve_effects(&ctx, &got.effects);
+                    let err = TypeError::new(
+                        TypeErrorCode::EffectViolation,
+                        f.expr.span(),
+                        format!(
+                            "function `{}` declared effects {} but body needs {}",
+                            id_text(&f.name, symtab),
+                            effect_set_to_string(&effects),
+                            effect_set_to_string(&needed)
+                        ),
+                    );
+                    errors.borrow_mut().push(with_effects_fix(
+                        err,
+                        &f.sig.effects,
+                        &needed,
+                        f.sig.effects_span,
+                    ));
+                }
             }
         }
     }
-    Ok(())
+    let mut errors = errors.into_inner();
+    errors.sort_by_key(|err| err.span.start);
+    errors
 }
 
-fn validate_main_signature(f: &crate::ast::FunctionDecl) -> Result<(), TypeError> {
+fn validate_main_signature(f: &crate::ast::FunctionDecl, ret: &Type) -> Result<(), TypeError> {
     if !f.sig.params.is_empty() {
-        return Err(TypeError {
-            code: TypeErrorCode::InvalidMainSignature,
-            span: f.sig.span,
-            message: "`main` must have zero parameters".to_string(),
-        });
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidMainSignature,
+            f.sig.span,
+            "`main` must have zero parameters".to_string(),
+        ));
     }
-    let ret = ast_type_to_type(&f.sig.ret, None)?;
-    if ret != Type::I32 {
-        return Err(TypeError {
-            code: TypeErrorCode::InvalidMainSignature,
-            span: f.sig.span,
-            message: "`main` must return i32".to_string(),
-        });
+    if *ret != Type::I32 {
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidMainSignature,
+            f.sig.span,
+            "`main` must return i32".to_string(),
+        ));
     }
     Ok(())
 }
 
-fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeError> {
+// Checks `expr`, recording any error and typing the failed expression as
+// `Type::Error` so checking continues past it.
+fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> ExprCheck {
+    let saved = matches!(
+        expr,
+        Expr::Let { .. } | Expr::LetPattern { .. } | Expr::LetRec { .. }
+    )
+    .then(|| ctx.locals.clone());
+    match check_expr_inner(ctx, expr) {
+        Ok(checked) => checked,
+        Err(err) => {
+            if let Some(saved) = saved {
+                ctx.locals = saved;
+            }
+            report(ctx, Err(err));
+            error_check()
+        }
+    }
+}
+
+fn annotated_here(err: TypeError, ann: &TypeExpr) -> TypeError {
+    err.with_label(
+        ann.span(),
+        "expected because of this annotation".to_string(),
+    )
+}
+
+fn error_check() -> ExprCheck {
+    ExprCheck {
+        ty: Type::Error,
+        effects: EffectSet::default(),
+    }
+}
+
+fn report(ctx: &CheckCtx<'_>, result: Result<(), TypeError>) {
+    if let Err(err) = result {
+        ctx.errors.borrow_mut().push(err);
+    }
+}
+
+fn check_expr_inner(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeError> {
     match expr {
         Expr::Unit(_) => Ok(ExprCheck {
             ty: Type::Unit,
@@ -447,85 +987,115 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
                 effects: EffectSet::default(),
             })
         }
-        Expr::NameApp { name, args, span } => {
+        Expr::NameApp {
+            module,
+            name,
+            args,
+            span,
+        } => {
             let name_text = id_text(name, ctx.symtab);
+            if let Some(alias) = module {
+                let target = import_target(ctx, alias)?;
+                if let Some(ctor) = visible_ctor(ctx, target, &name_text, name.span)? {
+                    return check_ctor_app(ctx, ctor, &name_text, args, *span);
+                }
+                let callee_ty = resolve_qualified_type(ctx, target, name, name.span)?;
+                return call_type(ctx, callee_ty, name.span, args, *span);
+            }
             if name_text == "Ok" || name_text == "Er" {
                 if args.len() != 1 {
-                    return Err(TypeError {
-                        code: TypeErrorCode::ArityMismatch,
-                        span: *span,
-                        message: format!(
+                    return Err(TypeError::new(
+                        TypeErrorCode::ArityMismatch,
+                        *span,
+                        format!(
                             "constructor `{}` expects 1 args, got {}",
                             name_text,
                             args.len()
                         ),
-                    });
+                    ));
                 }
-                let payload = check_expr(ctx, &args[0])?;
+                let payload = check_expr(ctx, &args[0]);
                 let ty = if name_text == "Ok" {
-                    Type::Result(
-                        Box::new(payload.ty.clone()),
-                        Box::new(Type::TypeVar("__res_err".to_string())),
-                    )
+                    Type::Result(Box::new(payload.ty.clone()), Box::new(fresh_var(ctx)))
                 } else {
-                    Type::Result(
-                        Box::new(Type::TypeVar("__res_ok".to_string())),
-                        Box::new(payload.ty.clone()),
-                    )
+                    Type::Result(Box::new(fresh_var(ctx)), Box::new(payload.ty.clone()))
                 };
                 return Ok(ExprCheck {
                     ty,
                     effects: payload.effects,
                 });
             }
-            if let Some(ctor) = ctx.module.ctors.get(&name_text) {
-                let (fields, result_ty) = instantiate_ctor_sig(ctor);
-                if fields.len() != args.len() {
-                    return Err(TypeError {
-                        code: TypeErrorCode::ArityMismatch,
-                        span: *span,
-                        message: format!(
+            if let Some(arity) = optional_ctor_arity(ctx, &name_text) {
+                if args.len() != arity {
+                    return Err(TypeError::new(
+                        TypeErrorCode::ArityMismatch,
+                        *span,
+                        format!(
                             "constructor `{}` expects {} args, got {}",
                             name_text,
-                            fields.len(),
+                            arity,
                             args.len()
                         ),
-                    });
-                }
-                let mut effects = EffectSet::default();
-                for (arg, expected) in args.iter().zip(fields.iter()) {
-                    let got = check_expr(ctx, arg)?;
-                    effects = union_effects(&effects, &got.effects);
-                    expect_type(expected, &got.ty, arg.span())?;
+                    ));
                 }
+                let Some(arg) = args.first() else {
+                    return Ok(ExprCheck {
+                        ty: Type::Optional(Box::new(fresh_var(ctx))),
+                        effects: EffectSet::default(),
+                    });
+                };
+                let payload = check_expr(ctx, arg);
                 return Ok(ExprCheck {
-                    ty: result_ty,
-                    effects,
+                    ty: Type::Optional(Box::new(payload.ty)),
+                    effects: payload.effects,
                 });
             }
-            let callee_ty = resolve_name_type(ctx, &name_text, name.span)?;
-            call_type(ctx, callee_ty, args, *span)
+            if let Some(ctor) = ctx.module.ctors.get(&name_text) {
+                return check_ctor_app(ctx, ctor, &name_text, args, *span);
+            }
+            let callee_ty = resolve_name_type(ctx, &name_text, name.span).unwrap_or_else(|err| {
+                report(ctx, Err(err));
+                Type::Error
+            });
+            call_type(ctx, callee_ty, name.span, args, *span)
         }
         Expr::Call { callee, args, span } => {
             if let Expr::Name(name) = &**callee {
                 let op = id_text(name, ctx.symtab);
-                if op == "==" || op == "!=" {
+                if matches!(op.as_str(), "==" | "!=" | "<" | "<=" | ">" | ">=") {
                     if args.len() != 2 {
-                        return Err(TypeError {
-                            code: TypeErrorCode::ArityMismatch,
-                            span: *span,
-                            message: format!("call expects 2 args, got {}", args.len()),
-                        });
+                        return Err(TypeError::new(
+                            TypeErrorCode::ArityMismatch,
+                            *span,
+                            format!("call expects 2 args, got {}", args.len()),
+                        ));
                     }
-                    let left = check_expr(ctx, &args[0])?;
-                    let right = check_expr(ctx, &args[1])?;
-                    expect_type(&left.ty, &right.ty, args[1].span())?;
-                    if matches!(left.ty, Type::Function { .. }) {
-                        return Err(TypeError {
-                            code: TypeErrorCode::TypeMismatch,
-                            span: *span,
-                            message: "equality does not support function values".to_string(),
-                        });
+                    let left = check_expr(ctx, &args[0]);
+                    let right = check_expr(ctx, &args[1]);
+                    expect_type(ctx, &left.ty, &right.ty, args[1].span()).map_err(|err| {
+                        err.with_label(
+                            args[0].span(),
+                            format!(
+                                "the left operand has type {}",
+                                show_type(&zonk(ctx, &left.ty))
+                            ),
+                        )
+                    })?;
+                    let operand = zonk(ctx, &left.ty);
+                    if contains_function(ctx, &operand, &mut BTreeSet::new()) {
+                        let what = if op == "==" || op == "!=" {
+                            "equality"
+                        } else {
+                            "comparison"
+                        };
+                        return Err(TypeError::new(
+                            TypeErrorCode::TypeMismatch,
+                            *span,
+                            format!(
+                                "{what} does not support function values (operands have type {})",
+                                show_type(&operand)
+                            ),
+                        ));
                     }
                     return Ok(ExprCheck {
                         ty: Type::Bool,
@@ -533,8 +1103,8 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
                     });
                 }
             }
-            let callee_checked = check_expr(ctx, callee)?;
-            let call = call_type(ctx, callee_checked.ty, args, *span)?;
+            let callee_checked = check_expr(ctx, callee);
+            let call = call_type(ctx, callee_checked.ty, callee.span(), args, *span)?;
             Ok(ExprCheck {
                 ty: call.ty,
                 effects: union_effects(&callee_checked.effects, &call.effects),
@@ -547,17 +1117,21 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
             body,
             ..
         } => {
-            let value_checked = check_expr(ctx, value)?;
+            let value_checked = check_expr(ctx, value);
             let bind_ty = if let Some(ann) = ty {
-                let ann_ty = ast_type_to_type(ann, ctx.symtab)?;
-                expect_type(&ann_ty, &value_checked.ty, value.span())?;
+                let ann_ty = ctx_type(ctx, ann)?;
+                report(
+                    ctx,
+                    expect_type(ctx, &ann_ty, &value_checked.ty, value.span())
+                        .map_err(|err| annotated_here(err, ann)),
+                );
                 ann_ty
             } else {
                 value_checked.ty.clone()
             };
             let local_name = id_text(name, ctx.symtab);
             let prev = ctx.locals.insert(local_name.clone(), bind_ty);
-            let body_checked = check_expr(ctx, body)?;
+            let body_checked = check_expr(ctx, body);
             if let Some(old) = prev {
                 ctx.locals.insert(local_name.clone(), old);
             } else {
@@ -568,19 +1142,121 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
                 effects: union_effects(&value_checked.effects, &body_checked.effects),
             })
         }
+        Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            body,
+            ..
+        } => {
+            let value_checked = check_expr(ctx, value);
+            let bind_ty = if let Some(ann) = ty {
+                let ann_ty = ctx_type(ctx, ann)?;
+                report(
+                    ctx,
+                    expect_type(ctx, &ann_ty, &value_checked.ty, value.span())
+                        .map_err(|err| annotated_here(err, ann)),
+                );
+                ann_ty
+            } else {
+                value_checked.ty.clone()
+            };
+            if !pattern_is_irrefutable(ctx, pattern) {
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidPattern,
+                    pattern.span(),
+                    "let destructuring pattern must be irrefutable".to_string(),
+                ));
+            }
+            let saved = ctx.locals.clone();
+            check_pattern_or_bind(ctx, pattern, &bind_ty);
+            let body_checked = check_expr(ctx, body);
+            ctx.locals = saved;
+            Ok(ExprCheck {
+                ty: body_checked.ty,
+                effects: union_effects(&value_checked.effects, &body_checked.effects),
+            })
+        }
+        Expr::LetRec { bindings, body, .. } => {
+            // Every name is bound to its lambda's annotated type before any
+            // body is checked, so the lambdas can call each other.
+            let saved = ctx.locals.clone();
+            let mut names = BTreeSet::new();
+            let mut bound = Vec::new();
+            for binding in bindings {
+                let name = id_text(&binding.name, ctx.symtab);
+                if !names.insert(name.clone()) {
+                    return Err(TypeError::new(
+                        TypeErrorCode::DuplicateSymbol,
+                        binding.name.span,
+                        format!("duplicate recursive binding `{name}`"),
+                    ));
+                }
+                let Expr::Lambda {
+                    params,
+                    ret,
+                    effects,
+                    ..
+                } = &binding.value
+                else {
+                    return Err(TypeError::new(
+                        TypeErrorCode::TypeMismatch,
+                        binding.value.span(),
+                        format!("recursive binding `{name}` must be a lambda"),
+                    ));
+                };
+                let ty = Type::Function {
+                    params: params
+                        .iter()
+                        .map(|p| ctx_type(ctx, &p.ty))
+                        .collect::<Result<_, _>>()?,
+                    ret: Box::new(ctx_type(ctx, ret)?),
+                    effects: effects.clone(),
+                };
+                bound.push(ty.clone());
+                ctx.locals.insert(name, ty);
+            }
+            for (binding, ty) in bindings.iter().zip(&bound) {
+                let checked = check_expr(ctx, &binding.value);
+                report(ctx, expect_type(ctx, ty, &checked.ty, binding.value.span()));
+            }
+            let body_checked = check_expr(ctx, body);
+            ctx.locals = saved;
+            Ok(body_checked)
+        }
         Expr::If {
             cond,
             then_branch,
             else_branch,
             ..
         } => {
-            let cond_checked = check_expr(ctx, cond)?;
-            expect_type(&Type::Bool, &cond_checked.ty, cond.span())?;
-            let then_checked = check_expr(ctx, then_branch)?;
-            let else_checked = check_expr(ctx, else_branch)?;
-            expect_type(&then_checked.ty, &else_checked.ty, else_branch.span())?;
+            let cond_checked = check_expr(ctx, cond);
+            report(
+                ctx,
+                expect_type(ctx, &Type::Bool, &cond_checked.ty, cond.span()),
+            );
+            let then_checked = check_expr(ctx, then_branch);
+            let else_checked = check_expr(ctx, else_branch);
+            let ty = match join_types(ctx, &then_checked.ty, &else_checked.ty) {
+                Some(ty) => ty,
+                None => {
+                    let result =
+                        expect_type(ctx, &then_checked.ty, &else_checked.ty, else_branch.span())
+                            .map_err(|err| {
+                                err.with_label(
+                                    then_branch.span(),
+                                    format!(
+                                        "the then branch has type {}",
+                                        show_type(&zonk(ctx, &then_checked.ty))
+                                    ),
+                                )
+                            });
+                    report(ctx, result);
+                    then_checked.ty
+                }
+            };
             Ok(ExprCheck {
-                ty: then_checked.ty,
+                ty,
                 effects: union_effects(
                     &cond_checked.effects,
                     &union_effects(&then_checked.effects, &else_checked.effects),
@@ -590,21 +1266,24 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
         Expr::Block { prefix, tail, .. } => {
             let mut effects = EffectSet::default();
             for e in prefix {
-                let checked = check_expr(ctx, e)?;
+                let checked = check_expr(ctx, e);
                 effects = union_effects(&effects, &checked.effects);
             }
-            let tail_checked = check_expr(ctx, tail)?;
+            let tail_checked = check_expr(ctx, tail);
             Ok(ExprCheck {
                 ty: tail_checked.ty,
                 effects: union_effects(&effects, &tail_checked.effects),
             })
         }
         Expr::Assert { cond, msg, .. } => {
-            let cond_checked = check_expr(ctx, cond)?;
-            expect_type(&Type::Bool, &cond_checked.ty, cond.span())?;
+            let cond_checked = check_expr(ctx, cond);
+            report(
+                ctx,
+                expect_type(ctx, &Type::Bool, &cond_checked.ty, cond.span()),
+            );
             let mut effects = cond_checked.effects;
             if let Some(msg) = msg {
-                let msg_checked = check_expr(ctx, msg)?;
+                let msg_checked = check_expr(ctx, msg);
                 effects = union_effects(&effects, &msg_checked.effects);
             }
             Ok(ExprCheck {
@@ -615,8 +1294,8 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
         Expr::Require { expr, .. } => {
             let mut nested = ctx.clone();
             nested.allow_return_magic = false;
-            let checked = check_expr(&mut nested, expr)?;
-            expect_type(&Type::Bool, &checked.ty, expr.span())?;
+            let checked = check_expr(&mut nested, expr);
+            report(ctx, expect_type(ctx, &Type::Bool, &checked.ty, expr.span()));
             Ok(ExprCheck {
                 ty: Type::Unit,
                 effects: checked.effects,
@@ -625,8 +1304,9 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
         Expr::Ensure { expr, .. } => {
             let mut nested = ctx.clone();
             nested.allow_return_magic = true;
-            let checked = check_expr(&mut nested, expr)?;
-            expect_type(&Type::Bool, &checked.ty, expr.span())?;
+            nested.locals = ctx.exit_locals.clone();
+            let checked = check_expr(&mut nested, expr);
+            report(ctx, expect_type(ctx, &Type::Bool, &checked.ty, expr.span()));
             Ok(ExprCheck {
                 ty: Type::Unit,
                 effects: checked.effects,
@@ -636,34 +1316,42 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
             params,
             ret,
             effects,
+            effects_span,
             body,
             ..
         } => {
-            validate_effect_set(effects, expr.span())?;
+            validate_effect_set(effects, *effects_span)?;
             let mut nested = ctx.clone();
             let mut param_types = Vec::new();
             for p in params {
-                let ty = ast_type_to_type(&p.ty, ctx.symtab)?;
+                let ty = ctx_type(ctx, &p.ty)?;
                 nested
                     .locals
                     .insert(id_text(&p.name, ctx.symtab), ty.clone());
                 param_types.push(ty);
             }
-            nested.return_type = Some(ast_type_to_type(ret, ctx.symtab)?);
+            let ret_ty = ctx_type(ctx, ret)?;
+            nested.return_type = Some(ret_ty.clone());
             nested.allow_return_magic = false;
-            let body_checked = check_expr(&mut nested, body)?;
-            let ret_ty = ast_type_to_type(ret, ctx.symtab)?;
-            expect_type(&ret_ty, &body_checked.ty, body.span())?;
-            if !effects_is_superset(effects, &body_checked.effects) {
-                return Err(TypeError {
-                    code: TypeErrorCode::EffectViolation,
-                    span: body.span(),
-                    message: format!(
+            nested.exit_locals = nested.locals.clone();
+            let body_checked = check_expr(&mut nested, body);
+            report(
+                ctx,
+                expect_type(ctx, &ret_ty, &body_checked.ty, body.span())
+                    .map_err(|err| annotated_here(err, ret)),
+            );
+            if !effects_within(ctx, effects, &body_checked.effects) {
+                let needed = resolve_effects(ctx, &body_checked.effects);
+                let err = TypeError::new(
+                    TypeErrorCode::EffectViolation,
+                    body.span(),
+                    format!(
                         "lambda declared effects {} but body needs {}",
                         effect_set_to_string(effects),
-                        effect_set_to_string(&body_checked.effects)
+                        effect_set_to_string(&needed)
                     ),
-                });
+                );
+                return Err(with_effects_fix(err, effects, &needed, *effects_span));
             }
             Ok(ExprCheck {
                 ty: Type::Function {
@@ -679,319 +1367,1767 @@ fn check_expr(ctx: &mut CheckCtx<'_>, expr: &Expr) -> Result<ExprCheck, TypeErro
             arms,
             span,
         } => {
-            let scrut = check_expr(ctx, scrutinee)?;
-            let mut arm_ty: Option<Type> = None;
+            let scrut = check_expr(ctx, scrutinee);
+            let mut arm_ty: Option<(Type, Span)> = None;
             let mut effects = scrut.effects;
-            let mut seen_bool_true = false;
-            let mut seen_bool_false = false;
-            let mut seen_wild = false;
-            let mut seen_ctors = BTreeSet::new();
-            let adt_ctors = adt_constructor_names(ctx, &scrut.ty);
+            let mut patterns_ok = true;
 
             for arm in arms {
                 let mut local_ctx = ctx.clone();
-                let cover = check_pattern(&mut local_ctx, &arm.pattern, &scrut.ty)?;
-                match cover {
-                    PatternCover::BoolTrue => seen_bool_true = true,
-                    PatternCover::BoolFalse => seen_bool_false = true,
-                    PatternCover::Ctor(name) => {
-                        seen_ctors.insert(name);
-                    }
-                    PatternCover::Wildcard => seen_wild = true,
-                    PatternCover::Other => {}
+                patterns_ok &= check_pattern_or_bind(&mut local_ctx, &arm.pattern, &scrut.ty);
+                if let Some(guard) = &arm.guard {
+                    let guard_checked = check_expr(&mut local_ctx, guard);
+                    report(
+                        ctx,
+                        expect_type(ctx, &Type::Bool, &guard_checked.ty, guard.span()),
+                    );
+                    effects = union_effects(&effects, &guard_checked.effects);
                 }
-                let arm_checked = check_expr(&mut local_ctx, &arm.expr)?;
+                let arm_checked = check_expr(&mut local_ctx, &arm.expr);
                 effects = union_effects(&effects, &arm_checked.effects);
-                if let Some(expected) = &arm_ty {
-                    expect_type(expected, &arm_checked.ty, arm.expr.span())?;
+                if let Some((expected, first)) = &mut arm_ty {
+                    match join_types(ctx, expected, &arm_checked.ty) {
+                        Some(joined) => *expected = joined,
+                        None => {
+                            let result =
+                                expect_type(ctx, expected, &arm_checked.ty, arm.expr.span())
+                                    .map_err(|err| {
+                                        err.with_label(
+                                            *first,
+                                            format!(
+                                                "the first arm has type {}",
+                                                show_type(&zonk(ctx, expected))
+                                            ),
+                                        )
+                                    });
+                            report(ctx, result);
+                        }
+                    }
                 } else {
-                    arm_ty = Some(arm_checked.ty);
+                    arm_ty = Some((arm_checked.ty, arm.expr.span()));
                 }
             }
-
-            if !seen_wild {
-                if scrut.ty == Type::Bool && !(seen_bool_true && seen_bool_false) {
-                    return Err(TypeError {
-                        code: TypeErrorCode::NonExhaustiveMatch,
-                        span: *span,
-                        message: "non-exhaustive boolean match".to_string(),
-                    });
-                }
-                if let Some(all) = adt_ctors {
-                    if !all.is_empty() && seen_ctors != all {
-                        return Err(TypeError {
-                            code: TypeErrorCode::NonExhaustiveMatch,
-                            span: *span,
-                            message: "non-exhaustive ADT match".to_string(),
-                        });
-                    }
-                }
+            // Coverage assumes every pattern fits the scrutinee's type.
+            if patterns_ok {
+                let result_ty = arm_ty.as_ref().map(|(ty, _)| ty);
+                check_match_coverage(ctx, &scrut.ty, arms, *span, result_ty)?;
             }
 
             Ok(ExprCheck {
-                ty: arm_ty.unwrap_or(Type::Unit),
+                ty: arm_ty.map_or(Type::Unit, |(ty, _)| ty),
                 effects,
             })
         }
-        Expr::Paren { inner, .. } => check_expr(ctx, inner),
-    }
-}
-
-enum PatternCover {
-    BoolTrue,
-    BoolFalse,
-    Ctor(String),
-    Wildcard,
-    Other,
-}
-
-fn check_pattern(
-    ctx: &mut CheckCtx<'_>,
-    pat: &Pattern,
-    expected: &Type,
-) -> Result<PatternCover, TypeError> {
-    match pat {
-        Pattern::Wildcard(_) => Ok(PatternCover::Wildcard),
-        Pattern::Literal(Literal::Bool(v, span)) => {
-            expect_type(&Type::Bool, expected, *span)?;
-            Ok(if *v {
-                PatternCover::BoolTrue
-            } else {
-                PatternCover::BoolFalse
+        Expr::Paren { inner, .. } => Ok(check_expr(ctx, inner)),
+        Expr::Try { inner, span } => {
+            let checked = check_expr(ctx, inner);
+            let ok_ty = fresh_var(ctx);
+            let err_ty = fresh_var(ctx);
+            let result = Type::Result(Box::new(ok_ty.clone()), Box::new(err_ty.clone()));
+            expect_type(ctx, &result, &checked.ty, inner.span())?;
+            let Some(ret) = ctx.return_type.clone() else {
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidTry,
+                    *span,
+                    "`?` is only valid inside a function body".to_string(),
+                ));
+            };
+            match resolve(ctx, &ret) {
+                Type::Result(_, ret_err) => expect_type(ctx, &ret_err, &err_ty, *span)?,
+                other => {
+                    return Err(TypeError::new(
+                        TypeErrorCode::InvalidTry,
+                        *span,
+                        format!(
+                            "`?` requires the enclosing function to return a result, but it returns {}",
+                            show_type(&zonk(ctx, &other))
+                        ),
+                    ));
+                }
+            }
+            Ok(ExprCheck {
+                ty: ok_ty,
+                effects: checked.effects,
             })
         }
-        Pattern::Literal(Literal::Int(v, span)) => {
-            let lit_ty = if *v >= i32::MIN as i64 && *v <= i32::MAX as i64 {
+        Expr::Record { name, fields, span } => {
+            let record_name = id_text(name, ctx.symtab);
+            let sig = match ctx.module.ctors.get(&record_name) {
+                Some(sig) if !sig.field_names.is_empty() => sig,
+                _ => {
+                    return Err(TypeError::new(
+                        TypeErrorCode::UnknownName,
+                        name.span,
+                        format!("unknown record `{record_name}`"),
+                    ));
+                }
+            };
+            let (field_tys, record_ty) = instantiate_ctor_sig(ctx, sig);
+            let mut seen = vec![false; field_tys.len()];
+            let mut effects = EffectSet::default();
+            for init in fields {
+                let idx = record_field_index(ctx, sig, &record_name, &init.name)?;
+                if std::mem::replace(&mut seen[idx], true) {
+                    return Err(TypeError::new(
+                        TypeErrorCode::InvalidField,
+                        init.name.span,
+                        format!("field `{}` is set more than once", sig.field_names[idx]),
+                    ));
+                }
+                let got = check_expr(ctx, &init.value);
+                effects = union_effects(&effects, &got.effects);
+                report(
+                    ctx,
+                    expect_type(ctx, &field_tys[idx], &got.ty, init.value.span()),
+                );
+            }
+            if let Some(idx) = seen.iter().position(|set| !set) {
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidField,
+                    *span,
+                    format!(
+                        "record `{record_name}` is missing field `{}`",
+                        sig.field_names[idx]
+                    ),
+                ));
+            }
+            Ok(ExprCheck {
+                ty: record_ty,
+                effects,
+            })
+        }
+        Expr::Field { base, field, span } => {
+            if let Some(target) = qualified_module(ctx, base) {
+                let ty = resolve_qualified_type(ctx, target, field, *span)?;
+                return Ok(ExprCheck {
+                    ty,
+                    effects: EffectSet::default(),
+                });
+            }
+            let checked = check_expr(ctx, base);
+            if resolve(ctx, &checked.ty) == Type::Error {
+                return Ok(error_check());
+            }
+            let (record_name, sig) = record_sig_of(ctx, &checked.ty, *span)?;
+            let idx = record_field_index(ctx, sig, &record_name, field)?;
+            let (field_tys, record_ty) = instantiate_ctor_sig(ctx, sig);
+            expect_type(ctx, &record_ty, &checked.ty, base.span())?;
+            Ok(ExprCheck {
+                ty: field_tys[idx].clone(),
+                effects: checked.effects,
+            })
+        }
+        Expr::Update { base, fields, span } => {
+            let checked = check_expr(ctx, base);
+            if resolve(ctx, &checked.ty) == Type::Error {
+                for init in fields {
+                    check_expr(ctx, &init.value);
+                }
+                return Ok(error_check());
+            }
+            let (record_name, sig) = record_sig_of(ctx, &checked.ty, *span)?;
+            let (field_tys, record_ty) = instantiate_ctor_sig(ctx, sig);
+            expect_type(ctx, &record_ty, &checked.ty, base.span())?;
+            let mut seen = vec![false; field_tys.len()];
+            let mut effects = checked.effects;
+            for init in fields {
+                let idx = record_field_index(ctx, sig, &record_name, &init.name)?;
+                if std::mem::replace(&mut seen[idx], true) {
+                    return Err(TypeError::new(
+                        TypeErrorCode::InvalidField,
+                        init.name.span,
+                        format!("field `{}` is set more than once", sig.field_names[idx]),
+                    ));
+                }
+                let got = check_expr(ctx, &init.value);
+                effects = union_effects(&effects, &got.effects);
+                report(
+                    ctx,
+                    expect_type(ctx, &field_tys[idx], &got.ty, init.value.span()),
+                );
+            }
+            Ok(ExprCheck {
+                ty: checked.ty,
+                effects,
+            })
+        }
+        Expr::Tuple { items, .. } => {
+            let mut tys = Vec::new();
+            let mut effects = EffectSet::default();
+            for item in items {
+                let checked = check_expr(ctx, item);
+                effects = union_effects(&effects, &checked.effects);
+                tys.push(checked.ty);
+            }
+            Ok(ExprCheck {
+                ty: Type::Tuple(tys),
+                effects,
+            })
+        }
+    }
+}
+
+fn check_ctor_app(
+    ctx: &mut CheckCtx<'_>,
+    ctor: &CtorSig,
+    name_text: &str,
+    args: &[Expr],
+    span: Span,
+) -> Result<ExprCheck, TypeError> {
+    let (fields, result_ty) = instantiate_ctor_sig(ctx, ctor);
+    if fields.len() != args.len() {
+        return Err(TypeError::new(
+            TypeErrorCode::ArityMismatch,
+            span,
+            format!(
+                "constructor `{}` expects {} args, got {}",
+                name_text,
+                fields.len(),
+                args.len()
+            ),
+        ));
+    }
+    let mut effects = EffectSet::default();
+    for (arg, expected) in args.iter().zip(fields.iter()) {
+        let got = check_expr(ctx, arg);
+        effects = union_effects(&effects, &got.effects);
+        expect_type(ctx, expected, &got.ty, arg.span())?;
+    }
+    Ok(ExprCheck {
+        ty: result_ty,
+        effects,
+    })
+}
+
+// The record constructor behind a value of type `ty`; records have exactly one
+// constructor, which carries the field names.
+fn record_sig_of<'m>(
+    ctx: &CheckCtx<'m>,
+    ty: &Type,
+    span: Span,
+) -> Result<(String, &'m CtorSig), TypeError> {
+    let ty = zonk(ctx, ty);
+    if let Type::Named(name, _) = &ty {
+        let (owner, base) = type_owner(ctx, name);
+        if let Some((ctor, sig)) = owner
+            .ctors
+            .iter()
+            .find(|(_, sig)| sig.parent == base && !sig.field_names.is_empty())
+        {
+            if sig.module != ctx.module_name {
+                check_ctor_visible(owner, sig, ctor, span)?;
+            }
+            return Ok((ctor.clone(), sig));
+        }
+    }
+    Err(TypeError::new(
+        TypeErrorCode::InvalidField,
+        span,
+        format!(
+            "field access needs a value of a known record type, found {}",
+            show_type(&ty)
+        ),
+    ))
+}
+
+fn record_field_index(
+    ctx: &CheckCtx<'_>,
+    sig: &CtorSig,
+    record_name: &str,
+    field: &crate::ast::Ident,
+) -> Result<usize, TypeError> {
+    let field_name = id_text(field, ctx.symtab);
+    sig.field_names
+        .iter()
+        .position(|name| *name == field_name)
+        .ok_or_else(|| {
+            TypeError::new(
+                TypeErrorCode::InvalidField,
+                field.span,
+                format!("record `{record_name}` has no field `{field_name}`"),
+            )
+        })
+}
+
+fn check_pattern(ctx: &mut CheckCtx<'_>, pat: &Pattern, expected: &Type) -> Result<(), TypeError> {
+    let expected = &resolve(ctx, expected);
+    if *expected == Type::Error {
+        bind_pattern_as_error(ctx, pat);
+        return Ok(());
+    }
+    match pat {
+        Pattern::Wildcard(_) => Ok(()),
+        Pattern::Literal(Literal::Bool(_, span)) => {
+            expect_type(ctx, &Type::Bool, expected, *span)?;
+            Ok(())
+        }
+        Pattern::Literal(Literal::Int(v, span)) => {
+            let lit_ty = if *v >= i32::MIN as i64 && *v <= i32::MAX as i64 {
                 Type::I32
             } else {
                 Type::I64
             };
-            expect_type(&lit_ty, expected, *span)?;
-            Ok(PatternCover::Other)
+            expect_type(ctx, &lit_ty, expected, *span)?;
+            Ok(())
         }
         Pattern::Literal(Literal::String(_, span)) => {
-            expect_type(&Type::String, expected, *span)?;
-            Ok(PatternCover::Other)
+            expect_type(ctx, &Type::String, expected, *span)?;
+            Ok(())
         }
         Pattern::Name(name) => {
             let name_text = id_text(name, ctx.symtab);
             if let Some(ctor) = ctx.module.ctors.get(&name_text) {
                 if ctor.fields.is_empty() {
-                    let (_, ctor_ty) = instantiate_ctor_sig(ctor);
-                    expect_type(&ctor_ty, expected, name.span)?;
-                    return Ok(PatternCover::Ctor(name_text));
+                    let (_, ctor_ty) = instantiate_ctor_sig(ctx, ctor);
+                    expect_type(ctx, &ctor_ty, expected, name.span)?;
+                    return Ok(());
                 }
             }
+            if optional_ctor_arity(ctx, &name_text) == Some(0) {
+                let optional = Type::Optional(Box::new(fresh_var(ctx)));
+                expect_type(ctx, &optional, expected, name.span)?;
+                return Ok(());
+            }
             ctx.locals.insert(name_text, expected.clone());
-            Ok(PatternCover::Other)
+            Ok(())
         }
-        Pattern::Ctor { name, args, span } => {
+        Pattern::Ctor {
+            module,
+            name,
+            args,
+            span,
+        } => {
             let ctor_name = id_text(name, ctx.symtab);
+            if let Some(alias) = module {
+                let target = import_target(ctx, alias)?;
+                let ctor = visible_ctor(ctx, target, &ctor_name, name.span)?.ok_or_else(|| {
+                    TypeError::new(
+                        TypeErrorCode::InvalidPattern,
+                        name.span,
+                        format!("module `{target}` has no constructor `{ctor_name}`"),
+                    )
+                })?;
+                return check_ctor_pattern(ctx, ctor, &ctor_name, args, expected, *span);
+            }
+            if let Some(arity) = optional_ctor_arity(ctx, &ctor_name) {
+                let inner = fresh_var(ctx);
+                let optional = Type::Optional(Box::new(inner.clone()));
+                expect_type(ctx, &optional, expected, *span)?;
+                if args.len() != arity {
+                    return Err(TypeError::new(
+                        TypeErrorCode::ArityMismatch,
+                        *span,
+                        format!(
+                            "constructor `{}` pattern expects {} args, got {}",
+                            ctor_name,
+                            arity,
+                            args.len()
+                        ),
+                    ));
+                }
+                for arg in args {
+                    check_pattern(ctx, arg, &inner)?;
+                }
+                return Ok(());
+            }
+            let expected =
+                &if matches!(expected, Type::Var(_)) && (ctor_name == "Ok" || ctor_name == "Er") {
+                    let res = Type::Result(Box::new(fresh_var(ctx)), Box::new(fresh_var(ctx)));
+                    expect_type(ctx, &res, expected, *span)?;
+                    res
+                } else {
+                    expected.clone()
+                };
             if let Type::Result(ok_ty, err_ty) = expected {
                 let field_ty: &Type = match ctor_name.as_str() {
                     "Ok" => ok_ty,
                     "Er" => err_ty,
                     _ => {
-                        return Err(TypeError {
-                            code: TypeErrorCode::InvalidPattern,
-                            span: name.span,
-                            message: format!("unknown constructor `{}`", ctor_name),
-                        });
+                        return Err(TypeError::new(
+                            TypeErrorCode::InvalidPattern,
+                            name.span,
+                            format!("unknown constructor `{}`", ctor_name),
+                        ));
                     }
                 };
                 if args.len() != 1 {
-                    return Err(TypeError {
-                        code: TypeErrorCode::ArityMismatch,
-                        span: *span,
-                        message: format!(
+                    return Err(TypeError::new(
+                        TypeErrorCode::ArityMismatch,
+                        *span,
+                        format!(
                             "constructor `{}` pattern expects 1 args, got {}",
                             ctor_name,
                             args.len()
                         ),
-                    });
+                    ));
                 }
                 check_pattern(ctx, &args[0], field_ty)?;
-                return Ok(PatternCover::Ctor(ctor_name));
+                return Ok(());
             }
-            let ctor = ctx.module.ctors.get(&ctor_name).ok_or_else(|| TypeError {
-                code: TypeErrorCode::InvalidPattern,
-                span: name.span,
-                message: format!("unknown constructor `{}`", ctor_name),
+            let ctor = ctx.module.ctors.get(&ctor_name).ok_or_else(|| {
+                TypeError::new(
+                    TypeErrorCode::InvalidPattern,
+                    name.span,
+                    format!("unknown constructor `{}`", ctor_name),
+                )
             })?;
-            let (fields, ctor_ty) = instantiate_ctor_sig(ctor);
-            expect_type(&ctor_ty, expected, *span)?;
-            if fields.len() != args.len() {
-                return Err(TypeError {
-                    code: TypeErrorCode::ArityMismatch,
-                    span: *span,
-                    message: format!(
-                        "constructor `{}` pattern expects {} args, got {}",
-                        ctor_name,
-                        fields.len(),
-                        args.len()
-                    ),
-                });
-            }
-            for (arg, field_ty) in args.iter().zip(fields.iter()) {
-                check_pattern(ctx, arg, field_ty)?;
-            }
-            Ok(PatternCover::Ctor(ctor_name))
+            check_ctor_pattern(ctx, ctor, &ctor_name, args, expected, *span)
         }
         Pattern::Tuple { items, span } => {
+            let expected = &if matches!(expected, Type::Var(_)) {
+                let tuple = Type::Tuple(items.iter().map(|_| fresh_var(ctx)).collect());
+                expect_type(ctx, &tuple, expected, *span)?;
+                tuple
+            } else {
+                expected.clone()
+            };
             let Type::Tuple(expected_items) = expected else {
-                return Err(TypeError {
-                    code: TypeErrorCode::InvalidPattern,
-                    span: *span,
-                    message: "tuple pattern requires tuple scrutinee".to_string(),
-                });
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidPattern,
+                    *span,
+                    "tuple pattern requires tuple scrutinee".to_string(),
+                ));
             };
             if items.len() != expected_items.len() {
-                return Err(TypeError {
-                    code: TypeErrorCode::ArityMismatch,
-                    span: *span,
-                    message: format!(
+                return Err(TypeError::new(
+                    TypeErrorCode::ArityMismatch,
+                    *span,
+                    format!(
                         "tuple pattern expects {} items, got {}",
                         expected_items.len(),
                         items.len()
                     ),
-                });
+                ));
             }
             for (item, expected_item) in items.iter().zip(expected_items.iter()) {
                 check_pattern(ctx, item, expected_item)?;
             }
-            Ok(PatternCover::Other)
+            Ok(())
         }
         Pattern::Paren { inner, .. } => check_pattern(ctx, inner, expected),
+        Pattern::Or { alts, span } => {
+            // Every alternative must bind the same names at the same types, so
+            // the arm body sees one consistent set of locals.
+            let outer = ctx.locals.clone();
+            let mut first: Option<(BTreeSet<String>, HashMap<String, Type>)> = None;
+            for alt in alts {
+                ctx.locals = outer.clone();
+                check_pattern(ctx, alt, expected)?;
+                let mut names = BTreeSet::new();
+                pattern_binders(ctx, alt, &mut names);
+                match &first {
+                    None => {
+                        let bound = names
+                            .iter()
+                            .map(|name| (name.clone(), ctx.locals[name].clone()))
+                            .collect();
+                        first = Some((names, bound));
+                    }
+                    Some((first_names, first_bound)) => {
+                        if &names != first_names {
+                            return Err(TypeError::new(
+                                TypeErrorCode::InvalidPattern,
+                                *span,
+                                "or-pattern alternatives must bind the same names".to_string(),
+                            ));
+                        }
+                        for name in &names {
+                            let alt_ty = ctx.locals[name].clone();
+                            expect_type(ctx, &first_bound[name], &alt_ty, alt.span())?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_ctor_pattern(
+    ctx: &mut CheckCtx<'_>,
+    ctor: &CtorSig,
+    ctor_name: &str,
+    args: &[Pattern],
+    expected: &Type,
+    span: Span,
+) -> Result<(), TypeError> {
+    let (fields, ctor_ty) = instantiate_ctor_sig(ctx, ctor);
+    expect_type(ctx, &ctor_ty, expected, span)?;
+    if fields.len() != args.len() {
+        return Err(TypeError::new(
+            TypeErrorCode::ArityMismatch,
+            span,
+            format!(
+                "constructor `{}` pattern expects {} args, got {}",
+                ctor_name,
+                fields.len(),
+                args.len()
+            ),
+        ));
+    }
+    for (arg, field_ty) in args.iter().zip(fields.iter()) {
+        check_pattern(ctx, arg, field_ty)?;
+    }
+    Ok(())
+}
+
+// Checks `pat`, recording an error instead of failing. On error its names are
+// still bound (as `Type::Error`) so the arm or body can be checked.
+// Returns whether the pattern checked.
+fn check_pattern_or_bind(ctx: &mut CheckCtx<'_>, pat: &Pattern, expected: &Type) -> bool {
+    match check_pattern(ctx, pat, expected) {
+        Ok(()) => true,
+        Err(err) => {
+            report(ctx, Err(err));
+            bind_pattern_as_error(ctx, pat);
+            false
+        }
+    }
+}
+
+fn bind_pattern_as_error(ctx: &mut CheckCtx<'_>, pat: &Pattern) {
+    let mut names = BTreeSet::new();
+    pattern_binders(ctx, pat, &mut names);
+    for name in names {
+        ctx.locals.insert(name, Type::Error);
+    }
+}
+
+fn pattern_binders(ctx: &CheckCtx<'_>, pat: &Pattern, out: &mut BTreeSet<String>) {
+    match pat {
+        Pattern::Wildcard(_) | Pattern::Literal(_) => {}
+        Pattern::Name(name) => {
+            let name_text = id_text(name, ctx.symtab);
+            if !ctx.module.ctors.contains_key(&name_text)
+                && optional_ctor_arity(ctx, &name_text).is_none()
+            {
+                out.insert(name_text);
+            }
+        }
+        Pattern::Ctor { args: items, .. } | Pattern::Tuple { items, .. } => {
+            for item in items {
+                pattern_binders(ctx, item, out);
+            }
+        }
+        Pattern::Paren { inner, .. } => pattern_binders(ctx, inner, out),
+        Pattern::Or { alts, .. } => pattern_binders(ctx, &alts[0], out),
+    }
+}
+
+// Exhaustiveness and reachability follow the usefulness algorithm: an arm is
+// reachable iff its pattern is useful with respect to the arms above it, and a
+// match is exhaustive iff `_` is not useful with respect to all arms. Patterns
+// are first deconstructed into constructor applications over typed columns.
+#[derive(Debug, Clone, PartialEq)]
+enum CtorKey {
+    Variant(String),
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Tuple(usize),
+}
+
+#[derive(Debug, Clone)]
+enum DeconPat {
+    Wild,
+    Ctor(CtorKey, Vec<DeconPat>),
+    Or(Vec<DeconPat>),
+}
+
+fn check_match_coverage(
+    ctx: &CheckCtx<'_>,
+    scrut_ty: &Type,
+    arms: &[crate::ast::MatchArm],
+    span: Span,
+    result_ty: Option<&Type>,
+) -> Result<(), TypeError> {
+    let scrut_ty = zonk(ctx, scrut_ty);
+    if contains_error(&scrut_ty) {
+        return Ok(());
+    }
+    let mut rows: Vec<Vec<DeconPat>> = Vec::new();
+    for arm in arms {
+        let row = vec![deconstruct_pattern(ctx, &arm.pattern)];
+        if useful(ctx, &rows, &row, std::slice::from_ref(&scrut_ty)).is_none() {
+            return Err(TypeError::new(
+                TypeErrorCode::UnreachableArm,
+                arm.pattern.span(),
+                format!(
+                    "unreachable match arm: pattern `{}` is already covered by earlier arms",
+                    show_decon_pat(&row[0])
+                ),
+            ));
+        }
+        // A guarded arm may fall through, so it never covers anything.
+        if arm.guard.is_none() {
+            rows.push(row);
+        }
+    }
+    if let Some(witness) = useful(
+        ctx,
+        &rows,
+        &[DeconPat::Wild],
+        std::slice::from_ref(&scrut_ty),
+    ) {
+        let err = TypeError::new(
+            TypeErrorCode::NonExhaustiveMatch,
+            span,
+            format!(
+                "non-exhaustive match: pattern `{}` is not covered",
+                show_decon_pat(&witness[0])
+            ),
+        );
+        let Some((at, missing)) = missing_arms(ctx, rows, &scrut_ty, arms, span, result_ty) else {
+            return Err(err);
+        };
+        let message = "add the missing match arms (each fails an assert until filled in)";
+        return Err(err.with_fix(at, missing, message.to_string()));
+    }
+    Ok(())
+}
+
+// Source for an arm per uncovered pattern, each failing at run time, and the
+// empty span before the match's closing bracket to insert it at. Only offered
+// when the arms' type is known and can be written in this module.
+fn missing_arms(
+    ctx: &CheckCtx<'_>,
+    mut rows: Vec<Vec<DeconPat>>,
+    scrut_ty: &Type,
+    arms: &[crate::ast::MatchArm],
+    span: Span,
+    result_ty: Option<&Type>,
+) -> Option<(Span, String)> {
+    let value = match zonk(ctx, result_ty?) {
+        Type::Unit => "()".to_string(),
+        Type::I32 => "0".to_string(),
+        Type::Bool => "f".to_string(),
+        Type::String => "\"\"".to_string(),
+        // Other types get a call to a local function that never returns; the
+        // assert fails before it runs.
+        ty => format!("w(u=l(x:i32):{}=c(u,x),c(u,0))", source_type(ctx, &ty)?),
+    };
+    let body = format!("{{a(f,\"unhandled match arm\");{value}}}");
+    // Bracket matches write each arm as `{pat expr}`.
+    let bracket = arms
+        .first()
+        .is_some_and(|arm| arm.span.start != arm.pattern.span().start);
+    let mut out = String::new();
+    for _ in 0..MAX_MISSING_ARMS {
+        let Some(witness) = useful(
+            ctx,
+            &rows,
+            &[DeconPat::Wild],
+            std::slice::from_ref(scrut_ty),
+        ) else {
+            let at = Span {
+                start: span.end - 1,
+                end: span.end - 1,
+            };
+            return Some((at, out));
+        };
+        let pat = show_decon_pat(&witness[0]);
+        if bracket {
+            out.push_str(&format!("{{{pat} {body}}}"));
+        } else {
+            out.push_str(&format!("{pat}=>{body};"));
+        }
+        rows.push(witness);
+    }
+    None
+}
+
+const MAX_MISSING_ARMS: usize = 64;
+
+// `ty` as it is written in the current module, or `None` when it mentions an
+// inference variable or an imported type with no alias here.
+fn source_type(ctx: &CheckCtx<'_>, ty: &Type) -> Option<String> {
+    let list = |items: &[Type]| {
+        items
+            .iter()
+            .map(|item| source_type(ctx, item))
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(","))
+    };
+    Some(match ty {
+        Type::Named(name, args) => {
+            let (owner, base) = type_owner(ctx, name);
+            let written = if std::ptr::eq(owner, ctx.module) {
+                base.to_string()
+            } else {
+                let (alias, _) = ctx.module.imports.iter().find(|(_, target)| {
+                    ctx.modules
+                        .get(*target)
+                        .is_some_and(|m| std::ptr::eq(m, owner))
+                })?;
+                format!("{alias}.{base}")
+            };
+            if args.is_empty() {
+                written
+            } else {
+                format!("{written}[{}]", list(args)?)
+            }
+        }
+        Type::Optional(inner) => format!("?{}", source_type(ctx, inner)?),
+        Type::Array(inner) => format!("{}[]", source_type(ctx, inner)?),
+        Type::Map(k, v) => format!("{{{}:{}}}", source_type(ctx, k)?, source_type(ctx, v)?),
+        Type::Tuple(items) => format!("({})", list(items)?),
+        Type::Function {
+            params,
+            ret,
+            effects,
+        } => {
+            if effects
+                .vars
+                .iter()
+                .any(|var| effect_var_index(var).is_some())
+            {
+                return None;
+            }
+            format!(
+                "({})->{}{}",
+                list(params)?,
+                source_type(ctx, ret)?,
+                effect_set_to_string(effects)
+            )
+        }
+        Type::Result(ok, err) => {
+            format!("({}!{})", source_type(ctx, ok)?, source_type(ctx, err)?)
+        }
+        Type::Var(_) | Type::Error => return None,
+        _ => show_type(ty),
+    })
+}
+
+fn deconstruct_pattern(ctx: &CheckCtx<'_>, pat: &Pattern) -> DeconPat {
+    match pat {
+        Pattern::Wildcard(_) => DeconPat::Wild,
+        Pattern::Paren { inner, .. } => deconstruct_pattern(ctx, inner),
+        Pattern::Name(name) => {
+            let name_text = id_text(name, ctx.symtab);
+            match ctx.module.ctors.get(&name_text) {
+                Some(ctor) if ctor.fields.is_empty() => {
+                    DeconPat::Ctor(CtorKey::Variant(name_text), Vec::new())
+                }
+                None if optional_ctor_arity(ctx, &name_text) == Some(0) => {
+                    DeconPat::Ctor(CtorKey::Variant(name_text), Vec::new())
+                }
+                _ => DeconPat::Wild,
+            }
+        }
+        Pattern::Ctor { name, args, .. } => DeconPat::Ctor(
+            CtorKey::Variant(id_text(name, ctx.symtab)),
+            args.iter().map(|a| deconstruct_pattern(ctx, a)).collect(),
+        ),
+        Pattern::Literal(Literal::Bool(v, _)) => DeconPat::Ctor(CtorKey::Bool(*v), Vec::new()),
+        Pattern::Literal(Literal::Int(v, _)) => DeconPat::Ctor(CtorKey::Int(*v), Vec::new()),
+        Pattern::Literal(Literal::String(v, _)) => {
+            DeconPat::Ctor(CtorKey::Str(v.clone()), Vec::new())
+        }
+        Pattern::Tuple { items, .. } => DeconPat::Ctor(
+            CtorKey::Tuple(items.len()),
+            items.iter().map(|i| deconstruct_pattern(ctx, i)).collect(),
+        ),
+        Pattern::Or { alts, .. } => {
+            DeconPat::Or(alts.iter().map(|a| deconstruct_pattern(ctx, a)).collect())
+        }
+    }
+}
+
+// All constructors of `ty`, or `None` when the type has too many values to
+// enumerate (integers, strings) or is not known here.
+fn type_ctors(ctx: &CheckCtx<'_>, ty: &Type) -> Option<Vec<CtorKey>> {
+    match ty {
+        Type::Bool => Some(vec![CtorKey::Bool(true), CtorKey::Bool(false)]),
+        Type::Result(_, _) => Some(vec![
+            CtorKey::Variant("Ok".to_string()),
+            CtorKey::Variant("Er".to_string()),
+        ]),
+        Type::Optional(_) => Some(vec![
+            CtorKey::Variant("None".to_string()),
+            CtorKey::Variant("Some".to_string()),
+        ]),
+        Type::Named(name, _) => {
+            let (owner, base) = type_owner(ctx, name);
+            let ctors = owner
+                .ctors
+                .iter()
+                .filter(|(_, sig)| sig.parent == base)
+                .map(|(ctor, _)| CtorKey::Variant(ctor.clone()))
+                .collect::<Vec<_>>();
+            if ctors.is_empty() { None } else { Some(ctors) }
+        }
+        Type::Tuple(items) => Some(vec![CtorKey::Tuple(items.len())]),
+        _ => None,
+    }
+}
+
+fn ctor_field_types(ctx: &CheckCtx<'_>, ty: &Type, key: &CtorKey, arity: usize) -> Vec<Type> {
+    let fields = match (key, ty) {
+        (CtorKey::Variant(name), Type::Result(ok, err)) => match name.as_str() {
+            "Ok" => vec![(**ok).clone()],
+            _ => vec![(**err).clone()],
+        },
+        (CtorKey::Variant(name), Type::Optional(inner)) => match name.as_str() {
+            "Some" => vec![(**inner).clone()],
+            _ => Vec::new(),
+        },
+        (CtorKey::Variant(name), Type::Named(ty_name, args)) => {
+            match type_owner(ctx, ty_name).0.ctors.get(name) {
+                Some(sig) => {
+                    let vars = sig
+                        .type_params
+                        .iter()
+                        .cloned()
+                        .zip(args.iter().cloned())
+                        .collect::<HashMap<_, _>>();
+                    ctor_field_types_with(ctx, sig, &vars)
+                        .iter()
+                        .map(|field| zonk(ctx, field))
+                        .collect()
+                }
+                None => Vec::new(),
+            }
+        }
+        (CtorKey::Tuple(_), Type::Tuple(items)) => items.clone(),
+        _ => Vec::new(),
+    };
+    if fields.len() == arity {
+        fields
+    } else {
+        vec![Type::Unit; arity]
+    }
+}
+
+fn ctor_arity(ctx: &CheckCtx<'_>, ty: &Type, key: &CtorKey) -> usize {
+    match key {
+        CtorKey::Tuple(n) => *n,
+        CtorKey::Variant(name) => match ty {
+            Type::Result(_, _) => 1,
+            Type::Optional(_) => usize::from(name == "Some"),
+            Type::Named(ty_name, _) => type_owner(ctx, ty_name)
+                .0
+                .ctors
+                .get(name)
+                .map_or(0, |sig| sig.fields.len()),
+            _ => ctx.module.ctors.get(name).map_or(0, |sig| sig.fields.len()),
+        },
+        _ => 0,
+    }
+}
+
+// Splits rows whose first column is an or-pattern into one row per
+// alternative, so the remaining algorithm only sees wildcards and ctors.
+fn expand_or_rows(rows: &[Vec<DeconPat>]) -> Vec<Vec<DeconPat>> {
+    let mut out = Vec::new();
+    for row in rows {
+        match &row[0] {
+            DeconPat::Or(alts) => {
+                let alt_rows = alts
+                    .iter()
+                    .map(|alt| {
+                        let mut alt_row = vec![alt.clone()];
+                        alt_row.extend_from_slice(&row[1..]);
+                        alt_row
+                    })
+                    .collect::<Vec<_>>();
+                out.extend(expand_or_rows(&alt_rows));
+            }
+            _ => out.push(row.clone()),
+        }
+    }
+    out
+}
+
+fn specialize_row(row: &[DeconPat], key: &CtorKey, arity: usize) -> Option<Vec<DeconPat>> {
+    let mut out = match &row[0] {
+        DeconPat::Ctor(head, args) if head == key => args.clone(),
+        DeconPat::Ctor(..) => return None,
+        DeconPat::Wild => vec![DeconPat::Wild; arity],
+        DeconPat::Or(_) => unreachable!("or-patterns are expanded before specialization"),
+    };
+    out.extend_from_slice(&row[1..]);
+    Some(out)
+}
+
+// Returns a witness vector of values matched by `q` but by no row, if any.
+fn useful(
+    ctx: &CheckCtx<'_>,
+    rows: &[Vec<DeconPat>],
+    q: &[DeconPat],
+    tys: &[Type],
+) -> Option<Vec<DeconPat>> {
+    if q.is_empty() {
+        return if rows.is_empty() {
+            Some(Vec::new())
+        } else {
+            None
+        };
+    }
+    if let DeconPat::Or(alts) = &q[0] {
+        return alts.iter().find_map(|alt| {
+            let mut alt_q = vec![alt.clone()];
+            alt_q.extend_from_slice(&q[1..]);
+            useful(ctx, rows, &alt_q, tys)
+        });
+    }
+    let rows = &expand_or_rows(rows);
+    let ty = &tys[0];
+    let specialize = |key: &CtorKey| -> Option<Vec<DeconPat>> {
+        let arity = ctor_arity(ctx, ty, key);
+        let sub_rows = rows
+            .iter()
+            .filter_map(|row| specialize_row(row, key, arity))
+            .collect::<Vec<_>>();
+        let sub_q = specialize_row(q, key, arity)?;
+        let mut sub_tys = ctor_field_types(ctx, ty, key, arity);
+        sub_tys.extend_from_slice(&tys[1..]);
+        let mut witness = useful(ctx, &sub_rows, &sub_q, &sub_tys)?;
+        let rest = witness.split_off(arity);
+        let mut out = vec![DeconPat::Ctor(key.clone(), witness)];
+        out.extend(rest);
+        Some(out)
+    };
+    if let DeconPat::Ctor(key, _) = &q[0] {
+        return specialize(key);
+    }
+
+    let mut seen: Vec<CtorKey> = Vec::new();
+    for row in rows {
+        if let DeconPat::Ctor(key, _) = &row[0]
+            && !seen.contains(key)
+        {
+            seen.push(key.clone());
+        }
+    }
+    let all = type_ctors(ctx, ty);
+    if let Some(all) = &all
+        && !seen.is_empty()
+        && all.iter().all(|key| seen.contains(key))
+    {
+        return all.iter().find_map(specialize);
+    }
+
+    let defaults = rows
+        .iter()
+        .filter(|row| matches!(row[0], DeconPat::Wild))
+        .map(|row| row[1..].to_vec())
+        .collect::<Vec<_>>();
+    let mut witness = useful(ctx, &defaults, &q[1..], &tys[1..])?;
+    let missing = if seen.is_empty() {
+        DeconPat::Wild
+    } else if let Some(all) = all {
+        let key = all
+            .into_iter()
+            .find(|key| !seen.contains(key))
+            .expect("incomplete constructor set has a missing constructor");
+        let arity = ctor_arity(ctx, ty, &key);
+        DeconPat::Ctor(key, vec![DeconPat::Wild; arity])
+    } else if seen.iter().all(|key| matches!(key, CtorKey::Int(_))) {
+        let n = (0..)
+            .find(|n| !seen.contains(&CtorKey::Int(*n)))
+            .expect("finitely many int patterns");
+        DeconPat::Ctor(CtorKey::Int(n), Vec::new())
+    } else {
+        DeconPat::Wild
+    };
+    witness.insert(0, missing);
+    Some(witness)
+}
+
+fn show_decon_pat(pat: &DeconPat) -> String {
+    let list = |items: &[DeconPat]| {
+        items
+            .iter()
+            .map(show_decon_pat)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    match pat {
+        DeconPat::Wild => "_".to_string(),
+        DeconPat::Ctor(CtorKey::Variant(name), args) if args.is_empty() => name.clone(),
+        DeconPat::Ctor(CtorKey::Variant(name), args) => format!("{name}({})", list(args)),
+        DeconPat::Ctor(CtorKey::Bool(v), _) => if *v { "t" } else { "f" }.to_string(),
+        DeconPat::Ctor(CtorKey::Int(v), _) => v.to_string(),
+        DeconPat::Ctor(CtorKey::Str(v), _) => format!("{v:?}"),
+        DeconPat::Ctor(CtorKey::Tuple(_), args) => format!("({})", list(args)),
+        DeconPat::Or(alts) => alts
+            .iter()
+            .map(show_decon_pat)
+            .collect::<Vec<_>>()
+            .join("|"),
+    }
+}
+
+// `Some`/`None` build `?T` values unless the module declares its own
+// constructor with that name.
+fn optional_ctor_arity(ctx: &CheckCtx<'_>, name: &str) -> Option<usize> {
+    if ctx.module.ctors.contains_key(name) {
+        return None;
+    }
+    match name {
+        "Some" => Some(1),
+        "None" => Some(0),
+        _ => None,
+    }
+}
+
+fn pattern_is_irrefutable(ctx: &CheckCtx<'_>, pat: &Pattern) -> bool {
+    match pat {
+        Pattern::Wildcard(_) => true,
+        Pattern::Name(name) => {
+            let name_text = id_text(name, ctx.symtab);
+            !ctx.module.ctors.contains_key(&name_text)
+                && optional_ctor_arity(ctx, &name_text).is_none()
+        }
+        Pattern::Tuple { items, .. } => items.iter().all(|item| pattern_is_irrefutable(ctx, item)),
+        Pattern::Paren { inner, .. } => pattern_is_irrefutable(ctx, inner),
+        Pattern::Literal(_) | Pattern::Ctor { .. } | Pattern::Or { .. } => false,
     }
 }
 
 fn call_type(
     ctx: &mut CheckCtx<'_>,
     callee_ty: Type,
+    callee: Span,
     args: &[Expr],
     span: Span,
 ) -> Result<ExprCheck, TypeError> {
+    let callee_ty = resolve(ctx, &callee_ty);
+    if callee_ty == Type::Error {
+        let mut effects = EffectSet::default();
+        for arg in args {
+            effects = union_effects(&effects, &check_expr(ctx, arg).effects);
+        }
+        return Ok(ExprCheck {
+            ty: Type::Error,
+            effects,
+        });
+    }
     let Type::Function {
         params,
         ret,
         effects: call_effects,
     } = callee_ty
     else {
-        return Err(TypeError {
-            code: TypeErrorCode::NotCallable,
+        return Err(TypeError::new(
+            TypeErrorCode::NotCallable,
             span,
-            message: "attempted to call a non-function value".to_string(),
-        });
+            "attempted to call a non-function value".to_string(),
+        ));
     };
     if params.len() != args.len() {
+        return Err(TypeError::new(
+            TypeErrorCode::ArityMismatch,
+            span,
+            format!("call expects {} args, got {}", params.len(), args.len()),
+        ));
+    }
+    let mut effects = call_effects;
+    for (idx, (arg, expected)) in args.iter().zip(params.iter()).enumerate() {
+        let got = check_expr(ctx, arg);
+        effects = union_effects(&effects, &got.effects);
+        let result = expect_type(ctx, expected, &got.ty, arg.span()).map_err(|err| {
+            err.with_label(
+                callee,
+                format!(
+                    "argument {} of this function has type {}",
+                    idx + 1,
+                    show_type(&zonk(ctx, expected))
+                ),
+            )
+        });
+        report(ctx, result);
+    }
+    Ok(ExprCheck { ty: *ret, effects })
+}
+
+fn resolve_name_type(ctx: &CheckCtx<'_>, name: &str, span: Span) -> Result<Type, TypeError> {
+    if name == "_r" {
+        if !ctx.allow_return_magic {
+            return Err(TypeError::new(
+                TypeErrorCode::InvalidReturnMagic,
+                span,
+                "`_r` is only valid inside ensure expressions".to_string(),
+            ));
+        }
+        if let Some(ty) = &ctx.return_type {
+            return Ok(ty.clone());
+        }
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidReturnMagic,
+            span,
+            "`_r` requires an enclosing function body".to_string(),
+        ));
+    }
+    if let Some(ty) = ctx.locals.get(name) {
+        return Ok(ty.clone());
+    }
+    if let Some(ty) = ctx.module.values.get(name) {
+        return Ok(instantiate(ctx, ty));
+    }
+    if let Some(ty) = builtin_values().get(name) {
+        return Ok(instantiate(ctx, ty));
+    }
+    let notes = suggest_name(ctx, name, span).into_iter().collect();
+    if ctx.allow_return_magic {
         return Err(TypeError {
-            code: TypeErrorCode::ArityMismatch,
+            code: TypeErrorCode::UnknownName,
             span,
-            message: format!("call expects {} args, got {}", params.len(), args.len()),
+            message: format!(
+                "unknown name `{name}` in ensure; ensures run at function exit and may only use parameters, `_r` and module names"
+            ),
+            notes,
         });
     }
-    let mut effects = call_effects;
-    for (arg, expected) in args.iter().zip(params.iter()) {
-        let got = check_expr(ctx, arg)?;
-        effects = union_effects(&effects, &got.effects);
-        expect_type(expected, &got.ty, arg.span())?;
+    Err(TypeError {
+        code: TypeErrorCode::UnknownName,
+        span,
+        message: format!("unknown name `{name}` in module `{}`", ctx.module_name),
+        notes,
+    })
+}
+
+// The closest in-scope local, module value, constructor or named builtin to
+// an unknown `name`, if one is within a third of its length in edits.
+fn suggest_name(ctx: &CheckCtx<'_>, name: &str, span: Span) -> Option<Note> {
+    if name.chars().count() < 2 {
+        return None;
+    }
+    let max = (name.chars().count() / 3).max(1);
+    let candidates = ctx
+        .locals
+        .keys()
+        .chain(ctx.module.values.keys())
+        .chain(ctx.module.ctors.keys())
+        .chain(builtin_values().keys());
+    let (_, best) = candidates
+        .filter(|candidate| {
+            candidate.as_str() != name && candidate.starts_with(|c: char| c.is_alphabetic())
+        })
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min()?;
+    Some(Note::Suggestion {
+        span,
+        replacement: best.clone(),
+        message: format!("did you mean `{best}`?"),
+    })
+}
+
+// Optimal string alignment distance: inserting, deleting or replacing a
+// character and swapping two adjacent ones each count as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        d[i][0] = i;
+        for j in 1..=b.len() {
+            let mut best = (d[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = best;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// `alias.name` is a module-qualified reference when `alias` is an import alias
+// not shadowed by a local or a module value.
+fn qualified_module<'m>(ctx: &CheckCtx<'m>, base: &Expr) -> Option<&'m str> {
+    let Expr::Name(id) = base else {
+        return None;
+    };
+    let alias = id_text(id, ctx.symtab);
+    if ctx.locals.contains_key(&alias) || ctx.module.values.contains_key(&alias) {
+        return None;
+    }
+    ctx.module.imports.get(&alias).map(String::as_str)
+}
+
+fn import_target<'m>(ctx: &CheckCtx<'m>, alias: &crate::ast::Ident) -> Result<&'m str, TypeError> {
+    let alias_text = id_text(alias, ctx.symtab);
+    ctx.module
+        .imports
+        .get(&alias_text)
+        .map(String::as_str)
+        .ok_or_else(|| {
+            TypeError::new(
+                TypeErrorCode::UnknownModule,
+                alias.span,
+                format!("unknown import alias `{alias_text}`"),
+            )
+        })
+}
+
+// A constructor of module `target` used from the current module. Its type must
+// be exported without `~`, or the constructor listed itself.
+fn visible_ctor<'m>(
+    ctx: &CheckCtx<'m>,
+    target: &str,
+    name: &str,
+    span: Span,
+) -> Result<Option<&'m CtorSig>, TypeError> {
+    let sigs = ctx.modules.get(target).expect("imports are validated");
+    let Some(sig) = sigs.ctors.get(name) else {
+        return Ok(None);
+    };
+    check_ctor_visible(sigs, sig, name, span)?;
+    Ok(Some(sig))
+}
+
+fn check_ctor_visible(
+    sigs: &ModuleSigs,
+    sig: &CtorSig,
+    name: &str,
+    span: Span,
+) -> Result<(), TypeError> {
+    if sigs.opaque.contains(&sig.parent) {
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidExport,
+            span,
+            format!(
+                "`{}` is opaque outside module `{}`; constructor `{name}` is not available",
+                sig.parent, sig.module
+            ),
+        ));
+    }
+    if !sigs.exports.contains(name) && !sigs.exports.contains(&sig.parent) {
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidExport,
+            span,
+            format!(
+                "constructor `{name}` is not exported by module `{}`",
+                sig.module
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn resolve_qualified_type(
+    ctx: &CheckCtx<'_>,
+    target: &str,
+    field: &crate::ast::Ident,
+    span: Span,
+) -> Result<Type, TypeError> {
+    let name = id_text(field, ctx.symtab);
+    let sigs = ctx.modules.get(target).expect("imports are validated");
+    let Some(ty) = sigs.values.get(&name) else {
+        return Err(TypeError::new(
+            TypeErrorCode::UnknownName,
+            span,
+            format!("module `{target}` has no value `{name}`"),
+        ));
+    };
+    if !sigs.exports.contains(&name) {
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidExport,
+            span,
+            format!("`{name}` is not exported by module `{target}`"),
+        ));
+    }
+    Ok(instantiate(
+        ctx,
+        &import_type(ty, target, sigs, ctx.module_name),
+    ))
+}
+
+fn function_type_to_type(
+    sig: &FunctionType,
+    vars: &HashMap<String, Type>,
+    symtab: Option<&[String]>,
+    imports: &BTreeMap<String, String>,
+) -> Type {
+    Type::Function {
+        params: sig
+            .params
+            .iter()
+            .map(|t| ast_type_to_type_with_vars(t, vars, symtab, imports))
+            .collect(),
+        ret: Box::new(ast_type_to_type_with_vars(&sig.ret, vars, symtab, imports)),
+        effects: sig.effects.clone(),
+    }
+}
+
+fn rigid_type_vars(
+    params: &[crate::ast::Ident],
+    symtab: Option<&[String]>,
+) -> HashMap<String, Type> {
+    params
+        .iter()
+        .map(|p| {
+            let name = id_text(p, symtab);
+            (name.clone(), Type::TypeVar(name))
+        })
+        .collect()
+}
+
+fn ctx_type(ctx: &CheckCtx<'_>, ty: &TypeExpr) -> Result<Type, TypeError> {
+    check_type_refs(ty, ctx.module, ctx.modules, ctx.symtab)?;
+    let converted = ast_type_to_type_with_vars(ty, &ctx.type_vars, ctx.symtab, &ctx.module.imports);
+    Ok(expand_aliases(&converted, ctx.module_name, ctx.modules))
+}
+
+fn instantiate_ctor_sig(ctx: &CheckCtx<'_>, sig: &CtorSig) -> (Vec<Type>, Type) {
+    let mut map = HashMap::new();
+    for tp in &sig.type_params {
+        map.insert(tp.clone(), fresh_var(ctx));
+    }
+    let fields = ctor_field_types_with(ctx, sig, &map);
+    let params = sig
+        .type_params
+        .iter()
+        .map(|tp| map.get(tp).cloned().expect("type var must exist"))
+        .collect::<Vec<_>>();
+    let parent = if sig.module == ctx.module_name {
+        sig.parent.clone()
+    } else {
+        qualified_type_name(&sig.module, &sig.parent)
+    };
+    (fields, Type::Named(parent, params))
+}
+
+// Field types of `sig` as seen from the current module, with the type
+// parameters replaced by `args`.
+fn ctor_field_types_with(
+    ctx: &CheckCtx<'_>,
+    sig: &CtorSig,
+    args: &HashMap<String, Type>,
+) -> Vec<Type> {
+    let owner = ctx.modules.get(&sig.module).expect("ctor module exists");
+    let vars = sig
+        .type_params
+        .iter()
+        .map(|tp| (tp.clone(), Type::TypeVar(tp.clone())))
+        .collect::<HashMap<_, _>>();
+    sig.fields
+        .iter()
+        .map(|field| {
+            let ty =
+                ast_type_to_type_with_vars(field, &vars, sig.symtab.as_deref(), &owner.imports);
+            let ty = import_type(&ty, &sig.module, owner, ctx.module_name);
+            let ty = expand_aliases(&ty, ctx.module_name, ctx.modules);
+            substitute_type_vars(&ty, args)
+        })
+        .collect()
+}
+
+// The module sigs declaring the named type `name`, and its bare name there.
+fn type_owner<'m, 'n>(ctx: &CheckCtx<'m>, name: &'n str) -> (&'m ModuleSigs, &'n str) {
+    match name.rsplit_once('.') {
+        Some((owner, base)) if ctx.modules.contains_key(owner) => (&ctx.modules[owner], base),
+        _ => (ctx.module, name),
+    }
+}
+
+fn fresh_var(ctx: &CheckCtx<'_>) -> Type {
+    let mut infer = ctx.infer.borrow_mut();
+    infer.subst.push(None);
+    Type::Var((infer.subst.len() - 1) as u32)
+}
+
+// Inference effect variables are named `'eN`; the quote keeps them apart from
+// the effect variables written in signatures.
+fn fresh_effect_var(ctx: &CheckCtx<'_>) -> String {
+    let mut infer = ctx.infer.borrow_mut();
+    infer.effects.push(None);
+    format!("'e{}", infer.effects.len() - 1)
+}
+
+fn effect_var_index(name: &str) -> Option<usize> {
+    name.strip_prefix("'e")?.parse().ok()
+}
+
+fn instantiate(ctx: &CheckCtx<'_>, scheme: &Type) -> Type {
+    fn go(
+        ctx: &CheckCtx<'_>,
+        ty: &Type,
+        map: &mut HashMap<String, Type>,
+        effect_map: &mut HashMap<String, String>,
+    ) -> Type {
+        match ty {
+            Type::TypeVar(name) => map
+                .entry(name.clone())
+                .or_insert_with(|| fresh_var(ctx))
+                .clone(),
+            Type::Function {
+                params,
+                ret,
+                effects,
+            } => Type::Function {
+                params: params.iter().map(|p| go(ctx, p, map, effect_map)).collect(),
+                ret: Box::new(go(ctx, ret, map, effect_map)),
+                effects: EffectSet {
+                    atoms: effects.atoms.clone(),
+                    vars: effects
+                        .vars
+                        .iter()
+                        .map(|v| match effect_var_index(v) {
+                            Some(_) => v.clone(),
+                            None => effect_map
+                                .entry(v.clone())
+                                .or_insert_with(|| fresh_effect_var(ctx))
+                                .clone(),
+                        })
+                        .collect(),
+                },
+            },
+            other => map_type_children(other, &mut |child| go(ctx, child, map, effect_map)),
+        }
+    }
+    go(ctx, scheme, &mut HashMap::new(), &mut HashMap::new())
+}
+
+fn map_type_children(ty: &Type, f: &mut dyn FnMut(&Type) -> Type) -> Type {
+    match ty {
+        Type::Named(name, args) => Type::Named(name.clone(), args.iter().map(&mut *f).collect()),
+        Type::Optional(inner) => Type::Optional(Box::new(f(inner))),
+        Type::Array(inner) => Type::Array(Box::new(f(inner))),
+        Type::Map(k, v) => Type::Map(Box::new(f(k)), Box::new(f(v))),
+        Type::Tuple(items) => Type::Tuple(items.iter().map(&mut *f).collect()),
+        Type::Function {
+            params,
+            ret,
+            effects,
+        } => Type::Function {
+            params: params.iter().map(&mut *f).collect(),
+            ret: Box::new(f(ret)),
+            effects: effects.clone(),
+        },
+        Type::Result(ok, err) => Type::Result(Box::new(f(ok)), Box::new(f(err))),
+        other => other.clone(),
+    }
+}
+
+fn resolve(ctx: &CheckCtx<'_>, ty: &Type) -> Type {
+    let infer = ctx.infer.borrow();
+    let mut current = ty.clone();
+    while let Type::Var(id) = current {
+        match &infer.subst[id as usize] {
+            Some(bound) => current = bound.clone(),
+            None => break,
+        }
+    }
+    current
+}
+
+fn zonk(ctx: &CheckCtx<'_>, ty: &Type) -> Type {
+    match resolve(ctx, ty) {
+        Type::Var(id) => Type::Var(id),
+        Type::Function {
+            params,
+            ret,
+            effects,
+        } => Type::Function {
+            params: params.iter().map(|p| zonk(ctx, p)).collect(),
+            ret: Box::new(zonk(ctx, &ret)),
+            effects: resolve_effects(ctx, &effects),
+        },
+        other => map_type_children(&other, &mut |child| zonk(ctx, child)),
+    }
+}
+
+fn occurs(ctx: &CheckCtx<'_>, id: u32, ty: &Type) -> bool {
+    match resolve(ctx, ty) {
+        Type::Var(other) => other == id,
+        Type::Named(_, args) | Type::Tuple(args) => args.iter().any(|a| occurs(ctx, id, a)),
+        Type::Optional(inner) | Type::Array(inner) => occurs(ctx, id, &inner),
+        Type::Map(a, b) | Type::Result(a, b) => occurs(ctx, id, &a) || occurs(ctx, id, &b),
+        Type::Function { params, ret, .. } => {
+            params.iter().any(|p| occurs(ctx, id, p)) || occurs(ctx, id, &ret)
+        }
+        _ => false,
+    }
+}
+
+fn unify(ctx: &CheckCtx<'_>, a: &Type, b: &Type) -> Result<(), UnifyError> {
+    unify_at(ctx, a, b, false)
+}
+
+// The type of an `i`/`m` whose branches have types `a` and `b`: function types
+// join their effect sets, anything else must subsume the other. Inference
+// state is left untouched when there is no join.
+fn join_types(ctx: &CheckCtx<'_>, a: &Type, b: &Type) -> Option<Type> {
+    let snapshot = ctx.infer.borrow().clone();
+    let restore = || *ctx.infer.borrow_mut() = snapshot.clone();
+    if let (
+        Type::Function {
+            params: pa,
+            ret: ra,
+            effects: ea,
+        },
+        Type::Function {
+            params: pb,
+            ret: rb,
+            effects: eb,
+        },
+    ) = (zonk(ctx, a), zonk(ctx, b))
+    {
+        if pa.len() == pb.len()
+            && unify_all(ctx, &pa, &pb).is_ok()
+            && let Some(ret) = join_types(ctx, &ra, &rb)
+        {
+            return Some(Type::Function {
+                params: pa,
+                ret: Box::new(ret),
+                effects: union_effects(&resolve_effects(ctx, &ea), &resolve_effects(ctx, &eb)),
+            });
+        }
+        restore();
+    }
+    for (wide, narrow) in [(a, b), (b, a)] {
+        if subsume(ctx, wide, narrow).is_ok() {
+            return Some(wide.clone());
+        }
+        restore();
     }
-    Ok(ExprCheck { ty: *ret, effects })
+    None
 }
 
-fn resolve_name_type(ctx: &CheckCtx<'_>, name: &str, span: Span) -> Result<Type, TypeError> {
-    if name == "_r" {
-        if !ctx.allow_return_magic {
-            return Err(TypeError {
-                code: TypeErrorCode::InvalidReturnMagic,
-                span,
-                message: "`_r` is only valid inside ensure expressions".to_string(),
-            });
+// Like `unify`, but a function in `b` may have fewer effects than the one in
+// `a` (also in its result), so less-effectful functions can be passed where
+// more effects are allowed. Parameters stay invariant.
+fn subsume(ctx: &CheckCtx<'_>, a: &Type, b: &Type) -> Result<(), UnifyError> {
+    unify_at(ctx, a, b, true)
+}
+
+fn unify_at(ctx: &CheckCtx<'_>, a: &Type, b: &Type, covariant: bool) -> Result<(), UnifyError> {
+    let a = resolve(ctx, a);
+    let b = resolve(ctx, b);
+    match (&a, &b) {
+        (Type::Var(x), Type::Var(y)) if x == y => Ok(()),
+        (Type::Var(id), other) | (other, Type::Var(id)) => {
+            if occurs(ctx, *id, other) {
+                return Err(UnifyError::Occurs(*id, other.clone()));
+            }
+            ctx.infer.borrow_mut().subst[*id as usize] = Some(other.clone());
+            Ok(())
         }
-        if let Some(ty) = &ctx.return_type {
-            return Ok(ty.clone());
+        (Type::Error, _) | (_, Type::Error) => Ok(()),
+        (Type::Named(x, xs), Type::Named(y, ys)) if x == y && xs.len() == ys.len() => {
+            unify_all(ctx, xs, ys)
         }
-        return Err(TypeError {
-            code: TypeErrorCode::InvalidReturnMagic,
-            span,
-            message: "`_r` requires an enclosing function body".to_string(),
-        });
+        (Type::Optional(x), Type::Optional(y)) | (Type::Array(x), Type::Array(y)) => {
+            unify(ctx, x, y)
+        }
+        (Type::Map(xk, xv), Type::Map(yk, yv)) | (Type::Result(xk, xv), Type::Result(yk, yv)) => {
+            unify(ctx, xk, yk)?;
+            unify(ctx, xv, yv)
+        }
+        (Type::Tuple(xs), Type::Tuple(ys)) if xs.len() == ys.len() => unify_all(ctx, xs, ys),
+        (
+            Type::Function {
+                params: xp,
+                ret: xr,
+                effects: xe,
+            },
+            Type::Function {
+                params: yp,
+                ret: yr,
+                effects: ye,
+            },
+        ) if xp.len() == yp.len() => {
+            unify_all(ctx, xp, yp)?;
+            unify_at(ctx, xr, yr, covariant)?;
+            unify_effects(ctx, xe, ye, covariant)
+        }
+        _ if a == b => Ok(()),
+        _ => Err(UnifyError::Mismatch),
     }
-    if let Some(ty) = ctx.locals.get(name) {
-        return Ok(ty.clone());
+}
+
+// Makes `got` a subset of `expected` (equal to it unless `covariant`) by
+// binding unbound inference variables on either side.
+fn unify_effects(
+    ctx: &CheckCtx<'_>,
+    expected: &EffectSet,
+    got: &EffectSet,
+    covariant: bool,
+) -> Result<(), UnifyError> {
+    let open_vars = |of: &EffectSet, other: &EffectSet| {
+        of.vars
+            .iter()
+            .filter(|v| effect_var_index(v).is_some() && !other.vars.contains(v))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let e = resolve_effects(ctx, expected);
+    let g = resolve_effects(ctx, got);
+    let e_extra = concrete_effects(&effect_difference(&e, &g));
+    for (i, var) in open_vars(&g, &e).iter().enumerate() {
+        let bound = if i == 0 {
+            e_extra.clone()
+        } else {
+            EffectSet::default()
+        };
+        bind_effect_var(ctx, var, bound);
     }
-    if let Some(ty) = ctx.module.values.get(name) {
-        return Ok(ty.clone());
+    let g = resolve_effects(ctx, got);
+    let g_extra = concrete_effects(&effect_difference(&g, &e));
+    let e_open = open_vars(&e, &g);
+    if e_open.is_empty() && g_extra != EffectSet::default() {
+        return Err(UnifyError::Mismatch);
     }
-    if let Some(ty) = builtin_values().get(name) {
-        return Ok(ty.clone());
+    // `got` only gives a lower bound for these: each keeps a fresh open tail
+    // so that a later, more effectful use can still widen it.
+    for (i, var) in e_open.iter().enumerate() {
+        let mut bound = if i == 0 {
+            g_extra.clone()
+        } else {
+            EffectSet::default()
+        };
+        bound.vars.push(fresh_effect_var(ctx));
+        bind_effect_var(ctx, var, bound);
     }
-    Err(TypeError {
-        code: TypeErrorCode::UnknownName,
-        span,
-        message: format!("unknown name `{name}` in module `{}`", ctx.module_name),
-    })
+    let e = resolve_effects(ctx, expected);
+    let g = resolve_effects(ctx, got);
+    if effect_difference(&g, &e) != EffectSet::default()
+        || (!covariant && effect_difference(&e, &g) != EffectSet::default())
+    {
+        return Err(UnifyError::Mismatch);
+    }
+    Ok(())
 }
 
-fn function_type_to_type(sig: &FunctionType) -> Result<Type, TypeError> {
-    Ok(Type::Function {
-        params: sig
-            .params
+fn bind_effect_var(ctx: &CheckCtx<'_>, var: &str, effects: EffectSet) {
+    let idx = effect_var_index(var).expect("only inference effect vars are bound");
+    ctx.infer.borrow_mut().effects[idx] = Some(effects);
+}
+
+// `effects` with every bound inference variable replaced by its binding.
+fn resolve_effects(ctx: &CheckCtx<'_>, effects: &EffectSet) -> EffectSet {
+    let mut out = EffectSet {
+        atoms: effects.atoms.clone(),
+        vars: Vec::new(),
+    };
+    for var in &effects.vars {
+        let bound = effect_var_index(var).and_then(|idx| ctx.infer.borrow().effects[idx].clone());
+        let part = match bound {
+            Some(set) => resolve_effects(ctx, &set),
+            None => EffectSet {
+                atoms: Vec::new(),
+                vars: vec![var.clone()],
+            },
+        };
+        out = union_effects(&out, &part);
+    }
+    out
+}
+
+fn effect_difference(a: &EffectSet, b: &EffectSet) -> EffectSet {
+    EffectSet {
+        atoms: a
+            .atoms
             .iter()
-            .map(|t| ast_type_to_type(t, None))
-            .collect::<Result<_, _>>()?,
-        ret: Box::new(ast_type_to_type(&sig.ret, None)?),
-        effects: sig.effects.clone(),
-    })
+            .filter(|atom| !b.atoms.contains(atom))
+            .copied()
+            .collect(),
+        vars: a
+            .vars
+            .iter()
+            .filter(|var| !b.vars.contains(var))
+            .cloned()
+            .collect(),
+    }
 }
 
-fn instantiate_ctor_sig(sig: &CtorSig) -> (Vec<Type>, Type) {
-    let mut map = HashMap::new();
-    for tp in &sig.type_params {
-        map.insert(tp.clone(), Type::TypeVar(tp.clone()));
+fn concrete_effects(effects: &EffectSet) -> EffectSet {
+    EffectSet {
+        atoms: effects.atoms.clone(),
+        vars: effects
+            .vars
+            .iter()
+            .filter(|v| effect_var_index(v).is_none())
+            .cloned()
+            .collect(),
     }
-    let fields = sig
-        .fields
-        .iter()
-        .map(|field| ast_type_to_type_with_vars(field, &map, sig.symtab.as_deref()))
-        .collect::<Vec<_>>();
-    let params = sig
-        .type_params
-        .iter()
-        .map(|tp| map.get(tp).cloned().expect("type var must exist"))
-        .collect::<Vec<_>>();
-    (fields, Type::Named(sig.parent.clone(), params))
 }
 
-fn adt_constructor_names(ctx: &CheckCtx<'_>, ty: &Type) -> Option<BTreeSet<String>> {
+// Whether a body needing `needed` fits in the `declared` effect set; unbound
+// inference variables in `needed` are fixed so that it does.
+fn effects_within(ctx: &CheckCtx<'_>, declared: &EffectSet, needed: &EffectSet) -> bool {
+    unify_effects(ctx, declared, needed, true).is_ok()
+}
+
+fn unify_all(ctx: &CheckCtx<'_>, xs: &[Type], ys: &[Type]) -> Result<(), UnifyError> {
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        unify(ctx, x, y)?;
+    }
+    Ok(())
+}
+
+fn contains_error(ty: &Type) -> bool {
     match ty {
-        Type::Result(_, _) => {
-            let mut out = BTreeSet::new();
-            out.insert("Ok".to_string());
-            out.insert("Er".to_string());
-            Some(out)
+        Type::Error => true,
+        Type::Named(_, args) | Type::Tuple(args) => args.iter().any(contains_error),
+        Type::Optional(inner) | Type::Array(inner) => contains_error(inner),
+        Type::Map(k, v) | Type::Result(k, v) => contains_error(k) || contains_error(v),
+        Type::Function { params, ret, .. } => {
+            params.iter().any(contains_error) || contains_error(ret)
         }
-        Type::Named(name, _) => {
-            let mut out = BTreeSet::new();
-            for (ctor, sig) in &ctx.module.ctors {
-                if &sig.parent == name {
-                    out.insert(ctor.clone());
-                }
+        _ => false,
+    }
+}
+
+fn contains_function(ctx: &CheckCtx<'_>, ty: &Type, seen: &mut BTreeSet<String>) -> bool {
+    match resolve(ctx, ty) {
+        Type::Function { .. } => true,
+        Type::Named(name, args) => {
+            if args.iter().any(|arg| contains_function(ctx, arg, seen)) {
+                return true;
             }
-            if out.is_empty() { None } else { Some(out) }
+            if !seen.insert(name.clone()) {
+                return false;
+            }
+            let (owner, base) = type_owner(ctx, &name);
+            owner
+                .ctors
+                .values()
+                .filter(|sig| sig.parent == base)
+                .any(|sig| {
+                    let vars = sig
+                        .type_params
+                        .iter()
+                        .cloned()
+                        .zip(args.iter().cloned())
+                        .collect::<HashMap<_, _>>();
+                    ctor_field_types_with(ctx, sig, &vars)
+                        .iter()
+                        .any(|field_ty| contains_function(ctx, field_ty, seen))
+                })
         }
-        _ => None,
+        Type::Optional(inner) | Type::Array(inner) => contains_function(ctx, &inner, seen),
+        Type::Map(k, v) | Type::Result(k, v) => {
+            contains_function(ctx, &k, seen) || contains_function(ctx, &v, seen)
+        }
+        Type::Tuple(items) => items.iter().any(|item| contains_function(ctx, item, seen)),
+        _ => false,
     }
 }
 
-fn ast_type_to_type(ty: &TypeExpr, symtab: Option<&[String]>) -> Result<Type, TypeError> {
-    Ok(ast_type_to_type_with_vars(ty, &HashMap::new(), symtab))
+fn ast_type_to_type(
+    ty: &TypeExpr,
+    symtab: Option<&[String]>,
+    imports: &BTreeMap<String, String>,
+) -> Result<Type, TypeError> {
+    Ok(ast_type_to_type_with_vars(
+        ty,
+        &HashMap::new(),
+        symtab,
+        imports,
+    ))
 }
 
 fn ast_type_to_type_with_vars(
     ty: &TypeExpr,
     vars: &HashMap<String, Type>,
     symtab: Option<&[String]>,
+    imports: &BTreeMap<String, String>,
 ) -> Type {
     match ty {
         TypeExpr::Prim(prim, _) => match prim {
@@ -1005,9 +3141,17 @@ fn ast_type_to_type_with_vars(
             PrimType::F64 => Type::F64,
             PrimType::Unit => Type::Unit,
         },
-        TypeExpr::Named { name, args, .. } => {
-            let resolved_name = name.resolved_string(symtab);
-            if args.is_empty() {
+        TypeExpr::Named {
+            module, name, args, ..
+        } => {
+            let mut resolved_name = name.resolved_string(symtab);
+            if let Some(alias) = module {
+                let alias = alias.resolved_string(symtab);
+                resolved_name = match imports.get(&alias) {
+                    Some(target) => qualified_type_name(target, &resolved_name),
+                    None => format!("{alias}.{resolved_name}"),
+                };
+            } else if args.is_empty() {
                 if let Some(v) = vars.get(&resolved_name) {
                     return v.clone();
                 }
@@ -1015,100 +3159,96 @@ fn ast_type_to_type_with_vars(
             Type::Named(
                 resolved_name,
                 args.iter()
-                    .map(|a| ast_type_to_type_with_vars(a, vars, symtab))
+                    .map(|a| ast_type_to_type_with_vars(a, vars, symtab, imports))
                     .collect(),
             )
         }
-        TypeExpr::Optional { inner, .. } => {
-            Type::Optional(Box::new(ast_type_to_type_with_vars(inner, vars, symtab)))
-        }
-        TypeExpr::Array { inner, .. } => {
-            Type::Array(Box::new(ast_type_to_type_with_vars(inner, vars, symtab)))
-        }
+        TypeExpr::Optional { inner, .. } => Type::Optional(Box::new(ast_type_to_type_with_vars(
+            inner, vars, symtab, imports,
+        ))),
+        TypeExpr::Array { inner, .. } => Type::Array(Box::new(ast_type_to_type_with_vars(
+            inner, vars, symtab, imports,
+        ))),
         TypeExpr::Map { key, value, .. } => Type::Map(
-            Box::new(ast_type_to_type_with_vars(key, vars, symtab)),
-            Box::new(ast_type_to_type_with_vars(value, vars, symtab)),
+            Box::new(ast_type_to_type_with_vars(key, vars, symtab, imports)),
+            Box::new(ast_type_to_type_with_vars(value, vars, symtab, imports)),
         ),
         TypeExpr::Tuple { items, .. } => Type::Tuple(
             items
                 .iter()
-                .map(|i| ast_type_to_type_with_vars(i, vars, symtab))
+                .map(|i| ast_type_to_type_with_vars(i, vars, symtab, imports))
                 .collect(),
         ),
         TypeExpr::Function { sig, .. } => Type::Function {
             params: sig
                 .params
                 .iter()
-                .map(|p| ast_type_to_type_with_vars(p, vars, symtab))
+                .map(|p| ast_type_to_type_with_vars(p, vars, symtab, imports))
                 .collect(),
-            ret: Box::new(ast_type_to_type_with_vars(&sig.ret, vars, symtab)),
+            ret: Box::new(ast_type_to_type_with_vars(&sig.ret, vars, symtab, imports)),
             effects: sig.effects.clone(),
         },
         TypeExpr::ResultSugar { ok, err, .. } => Type::Result(
-            Box::new(ast_type_to_type_with_vars(ok, vars, symtab)),
-            Box::new(ast_type_to_type_with_vars(err, vars, symtab)),
+            Box::new(ast_type_to_type_with_vars(ok, vars, symtab, imports)),
+            Box::new(ast_type_to_type_with_vars(err, vars, symtab, imports)),
         ),
-        TypeExpr::Group { inner, .. } => ast_type_to_type_with_vars(inner, vars, symtab),
+        TypeExpr::Group { inner, .. } => ast_type_to_type_with_vars(inner, vars, symtab, imports),
     }
 }
 
-fn expect_type(expected: &Type, got: &Type, span: Span) -> Result<(), TypeError> {
-    if type_compatible(expected, got) {
-        return Ok(());
+// Types declared by another user module are named `module.Type`; the core
+// modules' types are global and keep their bare names.
+fn qualified_type_name(module: &str, name: &str) -> String {
+    if module.starts_with("core.") {
+        name.to_string()
+    } else {
+        format!("{module}.{name}")
     }
-    Err(TypeError {
-        code: TypeErrorCode::TypeMismatch,
-        span,
-        message: format!(
-            "type mismatch: expected {}, got {}",
-            show_type(expected),
-            show_type(got)
-        ),
-    })
 }
 
-fn type_compatible(expected: &Type, got: &Type) -> bool {
-    if expected == got {
-        return true;
-    }
-    if matches_result_hole(expected) || matches_result_hole(got) {
-        return true;
+// Rewrites a type from module `from`'s point of view into module `into`'s:
+// `from`'s own types gain their module prefix and `into`'s lose it.
+fn import_type(ty: &Type, from: &str, from_sigs: &ModuleSigs, into: &str) -> Type {
+    if from == into {
+        return ty.clone();
     }
-    match (expected, got) {
-        (Type::Named(a, aa), Type::Named(b, bb)) if a == b && aa.len() == bb.len() => {
-            aa.iter().zip(bb.iter()).all(|(x, y)| type_compatible(x, y))
-        }
-        (Type::Optional(a), Type::Optional(b)) => type_compatible(a, b),
-        (Type::Array(a), Type::Array(b)) => type_compatible(a, b),
-        (Type::Map(ak, av), Type::Map(bk, bv)) => {
-            type_compatible(ak, bk) && type_compatible(av, bv)
-        }
-        (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
-            a.iter().zip(b.iter()).all(|(x, y)| type_compatible(x, y))
-        }
-        (
-            Type::Function {
-                params: ap,
-                ret: ar,
-                effects: ae,
-            },
-            Type::Function {
-                params: bp,
-                ret: br,
-                effects: be,
-            },
-        ) if ap.len() == bp.len() && ae == be => {
-            ap.iter().zip(bp.iter()).all(|(x, y)| type_compatible(x, y)) && type_compatible(ar, br)
-        }
-        (Type::Result(aok, aer), Type::Result(bok, ber)) => {
-            type_compatible(aok, bok) && type_compatible(aer, ber)
-        }
-        _ => false,
+    if let Type::Named(name, args) = ty {
+        let args = args
+            .iter()
+            .map(|arg| import_type(arg, from, from_sigs, into))
+            .collect();
+        let name = match name.rsplit_once('.') {
+            Some((module, base)) if module == into => base.to_string(),
+            None if from_sigs.types.contains(name) => qualified_type_name(from, name),
+            _ => name.clone(),
+        };
+        return Type::Named(name, args);
     }
+    map_type_children(ty, &mut |child| import_type(child, from, from_sigs, into))
 }
 
-fn matches_result_hole(ty: &Type) -> bool {
-    matches!(ty, Type::TypeVar(name) if name == "__res_ok" || name == "__res_err")
+fn expect_type(
+    ctx: &CheckCtx<'_>,
+    expected: &Type,
+    got: &Type,
+    span: Span,
+) -> Result<(), TypeError> {
+    let message = match subsume(ctx, expected, got) {
+        Ok(()) => return Ok(()),
+        Err(UnifyError::Mismatch) => format!(
+            "type mismatch: expected {}, got {}",
+            show_type(&zonk(ctx, expected)),
+            show_type(&zonk(ctx, got))
+        ),
+        Err(UnifyError::Occurs(id, ty)) => format!(
+            "type mismatch: infinite type {} = {} (expected {}, got {})",
+            show_type(&Type::Var(id)),
+            show_type(&zonk(ctx, &ty)),
+            show_type(&zonk(ctx, expected)),
+            show_type(&zonk(ctx, got))
+        ),
+    };
+    Err(TypeError::new(TypeErrorCode::TypeMismatch, span, message))
 }
 
 fn show_type(ty: &Type) -> String {
@@ -1152,13 +3292,11 @@ fn show_type(ty: &Type) -> String {
         ),
         Type::Result(ok, err) => format!("{}!{}", show_type(ok), show_type(err)),
         Type::TypeVar(v) => v.clone(),
+        Type::Var(id) => format!("'t{id}"),
+        Type::Error => "<error>".to_string(),
     }
 }
 
-fn effects_is_superset(container: &EffectSet, needed: &EffectSet) -> bool {
-    needed.atoms.iter().all(|a| container.atoms.contains(a))
-}
-
 fn union_effects(a: &EffectSet, b: &EffectSet) -> EffectSet {
     let mut atoms = Vec::new();
     for atom in [
@@ -1174,11 +3312,41 @@ fn union_effects(a: &EffectSet, b: &EffectSet) -> EffectSet {
             atoms.push(atom);
         }
     }
-    EffectSet { atoms }
+    let vars = a
+        .vars
+        .iter()
+        .chain(&b.vars)
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    EffectSet { atoms, vars }
+}
+
+// Adds a fix declaring the missing effects at `span`, the written set
+// `declared`. Inference variables cannot be written down, so a body needing
+// one gets no fix.
+fn with_effects_fix(
+    err: TypeError,
+    declared: &EffectSet,
+    needed: &EffectSet,
+    span: Span,
+) -> TypeError {
+    let missing = effect_difference(needed, declared);
+    if missing
+        .vars
+        .iter()
+        .any(|var| effect_var_index(var).is_some())
+    {
+        return err;
+    }
+    let fixed = effect_set_to_string(&union_effects(declared, &missing));
+    let message = format!("declare the effects as `{fixed}`");
+    err.with_fix(span, fixed, message)
 }
 
 fn effect_set_to_string(effects: &EffectSet) -> String {
-    if effects.atoms.is_empty() {
+    if effects.atoms.is_empty() && effects.vars.is_empty() {
         String::new()
     } else {
         let names = effects
@@ -1193,38 +3361,145 @@ fn effect_set_to_string(effects: &EffectSet) -> String {
                 EffectAtom::Time => "time",
                 EffectAtom::St => "st",
             })
+            .chain(effects.vars.iter().map(String::as_str))
             .collect::<Vec<_>>()
             .join(",");
         format!("!{{{names}}}")
     }
 }
 
+// `span` is where the set is written; errors carry a fix rewriting it in
+// canonical order.
 fn validate_effect_set(effects: &EffectSet, span: Span) -> Result<(), TypeError> {
+    check_effect_set_order(effects, span).map_err(|err| {
+        let canonical = effect_set_to_string(&union_effects(effects, &EffectSet::default()));
+        let message = format!("write the effect set as `{canonical}`");
+        err.with_fix(span, canonical, message)
+    })
+}
+
+// A signature's effect variables are bound by its parameters' function
+// types. One that only appears in the result or the function's own effects
+// can never be instantiated, and is most likely a misspelled atom.
+fn check_effect_vars_bound(sig: &FunctionType) -> Result<(), TypeError> {
+    let mut bound = BTreeSet::new();
+    for param in &sig.params {
+        collect_effect_vars(param, &mut bound);
+    }
+    let unbound = sig
+        .effects
+        .vars
+        .iter()
+        .find(|var| !bound.contains(*var))
+        .map(|var| (var, sig.effects_span))
+        .or_else(|| unbound_effect_var(&sig.ret, &bound));
+    match unbound {
+        Some((var, span)) => Err(TypeError::new(
+            TypeErrorCode::InvalidEffectSet,
+            span,
+            format!(
+                "unknown effect atom `{var}` (an effect variable must appear in a parameter's function type)"
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn collect_effect_vars(ty: &TypeExpr, out: &mut BTreeSet<String>) {
+    match ty {
+        TypeExpr::Prim(..) => {}
+        TypeExpr::Named { args, .. } => args.iter().for_each(|arg| collect_effect_vars(arg, out)),
+        TypeExpr::Tuple { items, .. } => {
+            items.iter().for_each(|item| collect_effect_vars(item, out))
+        }
+        TypeExpr::Optional { inner, .. }
+        | TypeExpr::Array { inner, .. }
+        | TypeExpr::Group { inner, .. } => collect_effect_vars(inner, out),
+        TypeExpr::Map { key, value, .. } => {
+            collect_effect_vars(key, out);
+            collect_effect_vars(value, out);
+        }
+        TypeExpr::ResultSugar { ok, err, .. } => {
+            collect_effect_vars(ok, out);
+            collect_effect_vars(err, out);
+        }
+        TypeExpr::Function { sig, .. } => {
+            out.extend(sig.effects.vars.iter().cloned());
+            sig.params
+                .iter()
+                .for_each(|param| collect_effect_vars(param, out));
+            collect_effect_vars(&sig.ret, out);
+        }
+    }
+}
+
+fn unbound_effect_var<'a>(
+    ty: &'a TypeExpr,
+    bound: &BTreeSet<String>,
+) -> Option<(&'a String, Span)> {
+    match ty {
+        TypeExpr::Prim(..) => None,
+        TypeExpr::Named { args, .. } => args.iter().find_map(|arg| unbound_effect_var(arg, bound)),
+        TypeExpr::Tuple { items, .. } => items
+            .iter()
+            .find_map(|item| unbound_effect_var(item, bound)),
+        TypeExpr::Optional { inner, .. }
+        | TypeExpr::Array { inner, .. }
+        | TypeExpr::Group { inner, .. } => unbound_effect_var(inner, bound),
+        TypeExpr::Map { key, value, .. } => {
+            unbound_effect_var(key, bound).or_else(|| unbound_effect_var(value, bound))
+        }
+        TypeExpr::ResultSugar { ok, err, .. } => {
+            unbound_effect_var(ok, bound).or_else(|| unbound_effect_var(err, bound))
+        }
+        TypeExpr::Function { sig, .. } => sig
+            .effects
+            .vars
+            .iter()
+            .find(|var| !bound.contains(*var))
+            .map(|var| (var, sig.effects_span))
+            .or_else(|| {
+                sig.params
+                    .iter()
+                    .find_map(|param| unbound_effect_var(param, bound))
+            })
+            .or_else(|| unbound_effect_var(&sig.ret, bound)),
+    }
+}
+
+fn check_effect_set_order(effects: &EffectSet, span: Span) -> Result<(), TypeError> {
     let mut seen = BTreeSet::new();
     let mut last_rank: Option<u8> = None;
     for atom in &effects.atoms {
         let rank = effect_rank(*atom);
         if let Some(prev) = last_rank {
             if rank <= prev {
-                return Err(TypeError {
-                    code: TypeErrorCode::InvalidEffectSet,
+                return Err(TypeError::new(
+                    TypeErrorCode::InvalidEffectSet,
                     span,
-                    message: format!(
+                    format!(
                         "effect set must be unique and sorted canonically as {}",
                         "io,fs,net,proc,rand,time,st"
                     ),
-                });
+                ));
             }
         }
         if !seen.insert(*atom) {
-            return Err(TypeError {
-                code: TypeErrorCode::InvalidEffectSet,
+            return Err(TypeError::new(
+                TypeErrorCode::InvalidEffectSet,
                 span,
-                message: "effect set contains duplicates".to_string(),
-            });
+                "effect set contains duplicates".to_string(),
+            ));
         }
         last_rank = Some(rank);
     }
+    if effects.vars.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(TypeError::new(
+            TypeErrorCode::InvalidEffectSet,
+            span,
+            "effect variables must be unique and sorted, after the atoms".to_string(),
+        ));
+    }
     Ok(())
 }
 
@@ -1256,6 +3531,7 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::Unit),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Io],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1266,6 +3542,7 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::Unit),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Io],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1276,6 +3553,7 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::String),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Io],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1286,6 +3564,7 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::Result(Box::new(Type::String), Box::new(Type::String))),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1296,6 +3575,79 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::Result(Box::new(Type::Unit), Box::new(Type::String))),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "append".to_string(),
+            Type::Function {
+                params: vec![Type::String, Type::String],
+                ret: Box::new(Type::Result(Box::new(Type::Unit), Box::new(Type::String))),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "list_dir".to_string(),
+            Type::Function {
+                params: vec![Type::String],
+                ret: Box::new(Type::Result(
+                    Box::new(Type::Array(Box::new(Type::String))),
+                    Box::new(Type::String),
+                )),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "exists".to_string(),
+            Type::Function {
+                params: vec![Type::String],
+                ret: Box::new(Type::Result(Box::new(Type::Bool), Box::new(Type::String))),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "mkdir".to_string(),
+            Type::Function {
+                params: vec![Type::String],
+                ret: Box::new(Type::Result(Box::new(Type::Unit), Box::new(Type::String))),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "remove".to_string(),
+            Type::Function {
+                params: vec![Type::String],
+                ret: Box::new(Type::Result(Box::new(Type::Unit), Box::new(Type::String))),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "read_lines".to_string(),
+            Type::Function {
+                params: vec![Type::String],
+                ret: Box::new(Type::Result(
+                    Box::new(Type::Array(Box::new(Type::String))),
+                    Box::new(Type::String),
+                )),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Fs],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1325,6 +3677,27 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::Result(Box::new(Type::I32), Box::new(Type::String))),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Proc],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "run_capture".to_string(),
+            Type::Function {
+                params: vec![
+                    Type::String,
+                    Type::Array(Box::new(Type::String)),
+                    Type::Optional(Box::new(Type::String)),
+                    Type::I32,
+                    Type::String,
+                ],
+                ret: Box::new(Type::Result(
+                    Box::new(Type::Named("Output".to_string(), vec![])),
+                    Box::new(Type::String),
+                )),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Proc],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1335,6 +3708,21 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
                 ret: Box::new(Type::Result(Box::new(Type::String), Box::new(Type::String))),
                 effects: EffectSet {
                     atoms: vec![EffectAtom::Net],
+                    vars: Vec::new(),
+                },
+            },
+        );
+        map.insert(
+            "request".to_string(),
+            Type::Function {
+                params: vec![Type::String, Type::String, Type::String, Type::String],
+                ret: Box::new(Type::Result(
+                    Box::new(Type::Named("Response".to_string(), vec![])),
+                    Box::new(Type::String),
+                )),
+                effects: EffectSet {
+                    atoms: vec![EffectAtom::Net],
+                    vars: Vec::new(),
                 },
             },
         );
@@ -1376,12 +3764,47 @@ fn builtin_values() -> &'static BTreeMap<String, Type> {
             map.insert(
                 name.to_string(),
                 Type::Function {
-                    params: vec![Type::I32, Type::I32],
+                    params: vec![
+                        Type::TypeVar("A".to_string()),
+                        Type::TypeVar("A".to_string()),
+                    ],
                     ret: Box::new(Type::Bool),
                     effects: EffectSet::default(),
                 },
             );
         }
+        map.insert(
+            "unwrap_or".to_string(),
+            Type::Function {
+                params: vec![
+                    Type::Optional(Box::new(Type::TypeVar("A".to_string()))),
+                    Type::TypeVar("A".to_string()),
+                ],
+                ret: Box::new(Type::TypeVar("A".to_string())),
+                effects: EffectSet::default(),
+            },
+        );
+        map.insert(
+            "map".to_string(),
+            Type::Function {
+                params: vec![
+                    Type::Optional(Box::new(Type::TypeVar("A".to_string()))),
+                    Type::Function {
+                        params: vec![Type::TypeVar("A".to_string())],
+                        ret: Box::new(Type::TypeVar("B".to_string())),
+                        effects: EffectSet {
+                            atoms: Vec::new(),
+                            vars: vec!["e".to_string()],
+                        },
+                    },
+                ],
+                ret: Box::new(Type::Optional(Box::new(Type::TypeVar("B".to_string())))),
+                effects: EffectSet {
+                    atoms: Vec::new(),
+                    vars: vec!["e".to_string()],
+                },
+            },
+        );
         for name in ["and", "or"] {
             map.insert(
                 name.to_string(),
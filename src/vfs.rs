@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::vm::{HttpRequest, HttpResponse, ProcOutput, ProcRequest, VmError, VmHost};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VirtualFs {
+    files: BTreeMap<String, String>,
+    dirs: BTreeSet<String>,
+}
+
+impl VirtualFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_dir(root: &Path) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!("vfs root is not a directory: {}", root.display()));
+        }
+        let mut vfs = VirtualFs::new();
+        let mut stack = vec![root.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            let entries =
+                fs::read_dir(&dir).map_err(|e| format!("failed reading {}: {e}", dir.display()))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| format!("failed reading {}: {e}", dir.display()))?;
+                let path = entry.path();
+                if path.is_dir() {
+                    stack.push(path);
+                    continue;
+                }
+                let rel = path
+                    .strip_prefix(root)
+                    .map_err(|_| format!("failed relativizing {}", path.display()))?;
+                let key = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let data = fs::read_to_string(&path)
+                    .map_err(|e| format!("failed reading {}: {e}", path.display()))?;
+                vfs.insert(&key, data)?;
+            }
+        }
+        Ok(vfs)
+    }
+
+    pub fn from_json_manifest(src: &str) -> Result<Self, String> {
+        let value = serde_json::from_str::<serde_json::Value>(src)
+            .map_err(|e| format!("invalid vfs manifest: {e}"))?;
+        let serde_json::Value::Object(entries) = value else {
+            return Err("invalid vfs manifest: expected a JSON object of path -> contents".into());
+        };
+        let mut vfs = VirtualFs::new();
+        for (path, data) in entries {
+            let serde_json::Value::String(data) = data else {
+                return Err(format!(
+                    "invalid vfs manifest: contents of `{path}` must be a string"
+                ));
+            };
+            vfs.insert(&path, data)?;
+        }
+        Ok(vfs)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if path.is_dir() {
+            return Self::from_dir(path);
+        }
+        let src = fs::read_to_string(path)
+            .map_err(|e| format!("failed reading {}: {e}", path.display()))?;
+        Self::from_json_manifest(&src)
+    }
+
+    pub fn insert(&mut self, path: &str, data: String) -> Result<(), String> {
+        let key = normalize_path(path)?;
+        if self.is_dir(&key) {
+            return Err(format!("`{key}` is a directory"));
+        }
+        self.check_parent_dirs(&key)?;
+        self.files.insert(key, data);
+        Ok(())
+    }
+
+    pub fn append(&mut self, path: &str, data: &str) -> Result<(), String> {
+        let key = normalize_path(path)?;
+        if self.is_dir(&key) {
+            return Err(format!("`{key}` is a directory"));
+        }
+        self.check_parent_dirs(&key)?;
+        self.files.entry(key).or_default().push_str(data);
+        Ok(())
+    }
+
+    pub fn create_dir(&mut self, path: &str) -> Result<(), String> {
+        let key = normalize_path(path)?;
+        self.check_no_files(&key)?;
+        self.dirs.insert(key);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, path: &str) -> Result<(), String> {
+        let key = normalize_path(path)?;
+        self.files
+            .remove(&key)
+            .map(|_| ())
+            .ok_or_else(|| format!("no such file `{key}`"))
+    }
+
+    pub fn exists(&self, path: &str) -> Result<bool, String> {
+        let key = normalize_dir(path)?;
+        Ok(key.is_empty() || self.files.contains_key(&key) || self.is_dir(&key))
+    }
+
+    pub fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        let key = normalize_dir(path)?;
+        if !key.is_empty() && !self.is_dir(&key) {
+            return Err(format!("no such directory `{key}`"));
+        }
+        let prefix = if key.is_empty() {
+            String::new()
+        } else {
+            format!("{key}/")
+        };
+        let names = self
+            .files
+            .keys()
+            .chain(self.dirs.iter())
+            .filter_map(|entry| entry.strip_prefix(prefix.as_str()))
+            .filter_map(|rest| rest.split('/').next())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect::<BTreeSet<_>>();
+        Ok(names.into_iter().collect())
+    }
+
+    // Fails when `key` or a directory on the way to it is a file.
+    fn check_no_files(&self, key: &str) -> Result<(), String> {
+        let mut prefix = String::new();
+        for part in key.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            if self.files.contains_key(&prefix) {
+                return Err(format!("`{prefix}` is a file"));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_parent_dirs(&self, key: &str) -> Result<(), String> {
+        match key.rsplit_once('/') {
+            Some((parent, _)) => self.check_no_files(parent),
+            None => Ok(()),
+        }
+    }
+
+    fn is_dir(&self, key: &str) -> bool {
+        let prefix = format!("{key}/");
+        self.dirs.contains(key)
+            || self
+                .files
+                .keys()
+                .chain(self.dirs.iter())
+                .any(|entry| entry.starts_with(&prefix))
+    }
+
+    pub fn get(&self, path: &str) -> Option<&str> {
+        let key = normalize_path(path).ok()?;
+        self.files.get(&key).map(String::as_str)
+    }
+
+    pub fn snapshot(&self) -> &BTreeMap<String, String> {
+        &self.files
+    }
+
+    pub fn into_snapshot(self) -> BTreeMap<String, String> {
+        self.files
+    }
+
+    pub fn to_json_manifest(&self) -> String {
+        let map = self
+            .files
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::to_string_pretty(&serde_json::Value::Object(map))
+            .expect("string map should serialize")
+    }
+}
+
+fn normalize_dir(path: &str) -> Result<String, String> {
+    if path
+        .split(['/', '\\'])
+        .all(|part| part.is_empty() || part == ".")
+    {
+        return Ok(String::new());
+    }
+    normalize_path(path)
+}
+
+pub fn normalize_path(path: &str) -> Result<String, String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(format!("path `{path}` escapes the vfs root"));
+                }
+            }
+            other => parts.push(other),
+        }
+    }
+    if parts.is_empty() {
+        return Err(format!("path `{path}` does not name a file"));
+    }
+    Ok(parts.join("/"))
+}
+
+pub struct VfsHost<H> {
+    pub inner: H,
+    pub vfs: VirtualFs,
+}
+
+impl<H: VmHost> VfsHost<H> {
+    pub fn new(inner: H, vfs: VirtualFs) -> Self {
+        Self { inner, vfs }
+    }
+}
+
+impl<H: VmHost> VmHost for VfsHost<H> {
+    fn io_print(&mut self, text: &str) -> Result<(), VmError> {
+        self.inner.io_print(text)
+    }
+
+    fn io_println(&mut self, text: &str) -> Result<(), VmError> {
+        self.inner.io_println(text)
+    }
+
+    fn io_readln(&mut self) -> Result<String, VmError> {
+        self.inner.io_readln()
+    }
+
+    fn fs_read_to_string(&mut self, path: &str) -> Result<String, String> {
+        let key = normalize_path(path).map_err(|e| format!("read failed: {e}"))?;
+        self.vfs
+            .files
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| format!("read failed: no such file `{key}`"))
+    }
+
+    fn fs_write_string(&mut self, path: &str, data: &str) -> Result<(), String> {
+        self.vfs
+            .insert(path, data.to_string())
+            .map_err(|e| format!("write failed: {e}"))
+    }
+
+    fn fs_append_string(&mut self, path: &str, data: &str) -> Result<(), String> {
+        self.vfs
+            .append(path, data)
+            .map_err(|e| format!("append failed: {e}"))
+    }
+
+    fn fs_list_dir(&mut self, path: &str) -> Result<Vec<String>, String> {
+        self.vfs
+            .list_dir(path)
+            .map_err(|e| format!("list_dir failed: {e}"))
+    }
+
+    fn fs_exists(&mut self, path: &str) -> Result<bool, String> {
+        self.vfs
+            .exists(path)
+            .map_err(|e| format!("exists failed: {e}"))
+    }
+
+    fn fs_create_dir(&mut self, path: &str) -> Result<(), String> {
+        self.vfs
+            .create_dir(path)
+            .map_err(|e| format!("mkdir failed: {e}"))
+    }
+
+    fn fs_remove_file(&mut self, path: &str) -> Result<(), String> {
+        self.vfs
+            .remove(path)
+            .map_err(|e| format!("remove failed: {e}"))
+    }
+
+    fn proc_run(&mut self, cmd: &str, args: &[String]) -> Result<i32, String> {
+        self.inner.proc_run(cmd, args)
+    }
+
+    fn proc_run_capture(&mut self, req: &ProcRequest) -> Result<ProcOutput, String> {
+        self.inner.proc_run_capture(req)
+    }
+
+    fn http_get(&mut self, url: &str) -> Result<String, String> {
+        self.inner.http_get(url)
+    }
+
+    fn http_request(&mut self, req: &HttpRequest) -> Result<HttpResponse, String> {
+        self.inner.http_request(req)
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::io::Read;
 
@@ -13,6 +14,7 @@ enum Value {
     Array(Vec<Value>),
     Map(BTreeMap<String, Value>),
     Adt { tag: String, fields: Vec<Value> },
+    Tuple(Vec<Value>),
     Closure { fn_id: u32, captures: Vec<Value> },
     Unit,
 }
@@ -22,6 +24,9 @@ struct Frame {
     fn_id: usize,
     ip: usize,
     locals: Vec<Value>,
+    // Operand stack height on entry; RET drops anything above it, so an early
+    // return from inside a call's arguments leaves the caller's stack intact.
+    stack_base: usize,
 }
 
 #[derive(Debug)]
@@ -39,14 +44,52 @@ impl std::error::Error for VmError {}
 
 pub const DEFAULT_FUEL: u64 = 10_000_000;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcRequest {
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub stdin: Option<String>,
+    pub timeout_ms: Option<u64>,
+    pub env: Vec<(String, Option<String>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcOutput {
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: i32,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
 pub trait VmHost {
     fn io_print(&mut self, text: &str) -> Result<(), VmError>;
     fn io_println(&mut self, text: &str) -> Result<(), VmError>;
     fn io_readln(&mut self) -> Result<String, VmError>;
     fn fs_read_to_string(&mut self, path: &str) -> Result<String, String>;
     fn fs_write_string(&mut self, path: &str, data: &str) -> Result<(), String>;
+    fn fs_append_string(&mut self, path: &str, data: &str) -> Result<(), String>;
+    fn fs_list_dir(&mut self, path: &str) -> Result<Vec<String>, String>;
+    fn fs_exists(&mut self, path: &str) -> Result<bool, String>;
+    fn fs_create_dir(&mut self, path: &str) -> Result<(), String>;
+    fn fs_remove_file(&mut self, path: &str) -> Result<(), String>;
     fn proc_run(&mut self, cmd: &str, args: &[String]) -> Result<i32, String>;
+    fn proc_run_capture(&mut self, req: &ProcRequest) -> Result<ProcOutput, String>;
     fn http_get(&mut self, url: &str) -> Result<String, String>;
+    fn http_request(&mut self, req: &HttpRequest) -> Result<HttpResponse, String>;
 }
 
 #[derive(Default)]
@@ -87,6 +130,38 @@ impl VmHost for RealHost {
         std::fs::write(path, data).map_err(|e| format!("write failed: {e}"))
     }
 
+    fn fs_append_string(&mut self, path: &str, data: &str) -> Result<(), String> {
+        use std::io::Write;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(data.as_bytes()))
+            .map_err(|e| format!("append failed: {e}"))
+    }
+
+    fn fs_list_dir(&mut self, path: &str) -> Result<Vec<String>, String> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(path).map_err(|e| format!("list_dir failed: {e}"))? {
+            let entry = entry.map_err(|e| format!("list_dir failed: {e}"))?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn fs_exists(&mut self, path: &str) -> Result<bool, String> {
+        std::fs::exists(path).map_err(|e| format!("exists failed: {e}"))
+    }
+
+    fn fs_create_dir(&mut self, path: &str) -> Result<(), String> {
+        std::fs::create_dir_all(path).map_err(|e| format!("mkdir failed: {e}"))
+    }
+
+    fn fs_remove_file(&mut self, path: &str) -> Result<(), String> {
+        std::fs::remove_file(path).map_err(|e| format!("remove failed: {e}"))
+    }
+
     fn proc_run(&mut self, cmd: &str, args: &[String]) -> Result<i32, String> {
         let mut child = std::process::Command::new(cmd);
         for arg in args {
@@ -98,6 +173,90 @@ impl VmHost for RealHost {
             .map_err(|e| format!("run failed: {e}"))
     }
 
+    fn proc_run_capture(&mut self, req: &ProcRequest) -> Result<ProcOutput, String> {
+        use std::process::Stdio;
+        use std::time::{Duration, Instant};
+
+        let mut command = std::process::Command::new(&req.cmd);
+        command
+            .args(&req.args)
+            .stdin(if req.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for (key, value) in &req.env {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("run_capture failed: {e}"))?;
+
+        let stdin_writer = match (child.stdin.take(), req.stdin.clone()) {
+            (Some(mut pipe), Some(input)) => Some(std::thread::spawn(move || {
+                use std::io::Write;
+                let _ = pipe.write_all(input.as_bytes());
+            })),
+            _ => None,
+        };
+        let mut stdout_pipe = child.stdout.take().expect("stdout should be piped");
+        let stdout_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stdout_pipe.read_to_end(&mut buf);
+            buf
+        });
+        let mut stderr_pipe = child.stderr.take().expect("stderr should be piped");
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stderr_pipe.read_to_end(&mut buf);
+            buf
+        });
+
+        let status = match req.timeout_ms {
+            None => child
+                .wait()
+                .map_err(|e| format!("run_capture failed: {e}"))?,
+            Some(ms) => {
+                let deadline = Instant::now() + Duration::from_millis(ms);
+                loop {
+                    match child.try_wait() {
+                        Ok(Some(status)) => break status,
+                        Ok(None) => {}
+                        Err(e) => return Err(format!("run_capture failed: {e}")),
+                    }
+                    if Instant::now() >= deadline {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        // A grandchild can keep the pipes open past the kill, so
+                        // joining could block beyond the deadline. Detach instead;
+                        // each thread exits once its pipe reaches EOF.
+                        drop(stdin_writer);
+                        drop(stdout_reader);
+                        drop(stderr_reader);
+                        return Err(format!("run_capture timed out after {ms}ms"));
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            }
+        };
+
+        if let Some(writer) = stdin_writer {
+            let _ = writer.join();
+        }
+        let stdout = stdout_reader.join().unwrap_or_default();
+        let stderr = stderr_reader.join().unwrap_or_default();
+        Ok(ProcOutput {
+            code: status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        })
+    }
+
     fn http_get(&mut self, url: &str) -> Result<String, String> {
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -121,6 +280,55 @@ impl VmHost for RealHost {
             Err("http get disabled on wasm host".to_string())
         }
     }
+
+    fn http_request(&mut self, req: &HttpRequest) -> Result<HttpResponse, String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut builder = ureq::http::Request::builder()
+                .method(req.method.as_str())
+                .uri(req.url.as_str());
+            for (name, value) in &req.headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            let request = builder
+                .body(req.body.clone())
+                .map_err(|e| format!("request failed: {e}"))?;
+            let agent: ureq::Agent = ureq::Agent::config_builder()
+                .http_status_as_error(false)
+                .build()
+                .into();
+            let mut response = agent
+                .run(request)
+                .map_err(|e| format!("request failed: {e}"))?;
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.as_str().to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect();
+            let mut body = String::new();
+            response
+                .body_mut()
+                .as_reader()
+                .read_to_string(&mut body)
+                .map_err(|e| format!("request body read failed: {e}"))?;
+            Ok(HttpResponse {
+                status: i32::from(response.status().as_u16()),
+                headers,
+                body,
+            })
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = req;
+            Err("http request disabled on wasm host".to_string())
+        }
+    }
 }
 
 impl VmHost for FuzzHost {
@@ -146,13 +354,41 @@ impl VmHost for FuzzHost {
         Err("fuzz host: fs write disabled".to_string())
     }
 
+    fn fs_append_string(&mut self, _path: &str, _data: &str) -> Result<(), String> {
+        Err("fuzz host: fs append disabled".to_string())
+    }
+
+    fn fs_list_dir(&mut self, _path: &str) -> Result<Vec<String>, String> {
+        Err("fuzz host: fs list_dir disabled".to_string())
+    }
+
+    fn fs_exists(&mut self, _path: &str) -> Result<bool, String> {
+        Err("fuzz host: fs exists disabled".to_string())
+    }
+
+    fn fs_create_dir(&mut self, _path: &str) -> Result<(), String> {
+        Err("fuzz host: fs mkdir disabled".to_string())
+    }
+
+    fn fs_remove_file(&mut self, _path: &str) -> Result<(), String> {
+        Err("fuzz host: fs remove disabled".to_string())
+    }
+
     fn proc_run(&mut self, _cmd: &str, _args: &[String]) -> Result<i32, String> {
         Err("fuzz host: proc run disabled".to_string())
     }
 
+    fn proc_run_capture(&mut self, _req: &ProcRequest) -> Result<ProcOutput, String> {
+        Err("fuzz host: proc run_capture disabled".to_string())
+    }
+
     fn http_get(&mut self, _url: &str) -> Result<String, String> {
         Err("fuzz host: http get disabled".to_string())
     }
+
+    fn http_request(&mut self, _req: &HttpRequest) -> Result<HttpResponse, String> {
+        Err("fuzz host: http request disabled".to_string())
+    }
 }
 
 pub fn run_bytecode(bytecode: &[u8], args: &[String]) -> Result<(), VmError> {
@@ -175,6 +411,14 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
         message: e.to_string(),
     })?;
     let strings = decoded.strings;
+    // A tag's rank is its position within its family; `<` only ever compares
+    // tags of one family.
+    let ctor_ranks = decoded
+        .ctor_families
+        .iter()
+        .flat_map(|family| family.iter().enumerate())
+        .map(|(rank, &tag)| (strings[tag as usize].as_str(), rank))
+        .collect::<HashMap<_, _>>();
     let functions = decoded.functions;
     let entry_fn = decoded.entry_fn;
     let entry_idx = entry_fn as usize;
@@ -194,6 +438,7 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
         fn_id: entry_idx,
         ip: 0,
         locals: Vec::new(),
+        stack_base: 0,
     }];
 
     while !frames.is_empty() {
@@ -293,7 +538,10 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
                     });
                 }
                 let args = stack.split_off(stack.len() - argc);
-                let result = call_builtin(host, id, &args)?;
+                let result = match id {
+                    25..=30 => compare_builtin(id, &args, &ctor_ranks)?,
+                    _ => call_builtin(host, id, &args)?,
+                };
                 stack.push(result);
             }
             x if x == OpCode::CallFn as u8 => {
@@ -325,6 +573,7 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
                     fn_id,
                     ip: 0,
                     locals: args,
+                    stack_base: stack.len(),
                 });
             }
             x if x == OpCode::MkClosure as u8 => {
@@ -376,6 +625,7 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
                     fn_id: fn_id as usize,
                     ip: 0,
                     locals,
+                    stack_base: stack.len(),
                 });
             }
             x if x == OpCode::Trap as u8 => {
@@ -404,6 +654,16 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
                     fields,
                 });
             }
+            x if x == OpCode::MkTuple as u8 => {
+                let argc = read_u8(code, &mut frame.ip)? as usize;
+                if stack.len() < argc {
+                    return Err(VmError {
+                        message: "stack underflow in MK_TUPLE".to_string(),
+                    });
+                }
+                let items = stack.split_off(stack.len() - argc);
+                stack.push(Value::Tuple(items));
+            }
             x if x == OpCode::JumpIfTag as u8 => {
                 let tag_idx = read_u32(code, &mut frame.ip)? as usize;
                 let target = read_u32(code, &mut frame.ip)? as usize;
@@ -482,9 +742,9 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
                 let value = stack.pop().ok_or_else(|| VmError {
                     message: "stack underflow in GET_ADT_FIELD".to_string(),
                 })?;
-                let Value::Adt { fields, .. } = value else {
+                let (Value::Adt { fields, .. } | Value::Tuple(fields)) = value else {
                     return Err(VmError {
-                        message: "GET_ADT_FIELD expects an ADT value".to_string(),
+                        message: "GET_ADT_FIELD expects an ADT or tuple value".to_string(),
                     });
                 };
                 let field = fields.get(idx).ok_or_else(|| VmError {
@@ -496,6 +756,7 @@ pub fn run_bytecode_with_fuel_and_host<H: VmHost>(
                 let ret = stack.pop().ok_or_else(|| VmError {
                     message: "stack underflow in RET".to_string(),
                 })?;
+                stack.truncate(frame.stack_base);
                 frames.pop();
                 if frames.is_empty() {
                     let code = match ret {
@@ -652,6 +913,7 @@ fn call_builtin<H: VmHost>(host: &mut H, id: u8, args: &[Value]) -> Result<Value
                 Value::Float(v) => Ok(Value::String(v.to_string())),
                 Value::Bool(v) => Ok(Value::String(v.to_string())),
                 Value::Array(items) => Ok(Value::String(format!("<array:{}>", items.len()))),
+                Value::Tuple(items) => Ok(Value::String(format!("<tuple:{}>", items.len()))),
                 Value::Map(entries) => Ok(Value::String(format!("<map:{}>", entries.len()))),
                 Value::Unit => Ok(Value::String("()".to_string())),
             }
@@ -702,6 +964,177 @@ fn call_builtin<H: VmHost>(host: &mut H, id: u8, args: &[Value]) -> Result<Value
                 Err(e) => Ok(err_value(e)),
             }
         }
+        10 => {
+            if args.len() != 4 {
+                return Err(VmError {
+                    message: "request expects four arguments".to_string(),
+                });
+            }
+            let mut parts = Vec::with_capacity(4);
+            for arg in args {
+                let Value::String(s) = arg else {
+                    return Err(VmError {
+                        message: "request expects string arguments".to_string(),
+                    });
+                };
+                parts.push(s.as_str());
+            }
+            let method = parts[0].to_ascii_uppercase();
+            if method.is_empty() || !method.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Ok(err_value(format!(
+                    "request failed: invalid method `{}`",
+                    parts[0]
+                )));
+            }
+            let headers = match parse_header_block(parts[2]) {
+                Ok(headers) => headers,
+                Err(e) => return Ok(err_value(format!("request failed: {e}"))),
+            };
+            let req = HttpRequest {
+                method,
+                url: parts[1].to_string(),
+                headers,
+                body: parts[3].to_string(),
+            };
+            match host.http_request(&req) {
+                Ok(resp) => Ok(ok_value(Value::Adt {
+                    tag: "Response".to_string(),
+                    fields: vec![
+                        Value::Int(i64::from(resp.status)),
+                        Value::String(format_header_block(&resp.headers)),
+                        Value::String(resp.body),
+                    ],
+                })),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        11 => {
+            if args.len() != 5 {
+                return Err(VmError {
+                    message: "run_capture expects five arguments".to_string(),
+                });
+            }
+            let (Value::String(cmd), Value::Array(arg_values)) = (&args[0], &args[1]) else {
+                return Err(VmError {
+                    message: "run_capture expects (s, s[], ?s, i32, s) arguments".to_string(),
+                });
+            };
+            let stdin = match &args[2] {
+                Value::Adt { tag, fields } if tag == "None" && fields.is_empty() => None,
+                Value::Adt { tag, fields } if tag == "Some" => match fields.as_slice() {
+                    [Value::String(input)] => Some(input.clone()),
+                    _ => {
+                        return Err(VmError {
+                            message: "run_capture expects (s, s[], ?s, i32, s) arguments"
+                                .to_string(),
+                        });
+                    }
+                },
+                _ => {
+                    return Err(VmError {
+                        message: "run_capture expects (s, s[], ?s, i32, s) arguments".to_string(),
+                    });
+                }
+            };
+            let (Value::Int(timeout_ms), Value::String(env)) = (&args[3], &args[4]) else {
+                return Err(VmError {
+                    message: "run_capture expects (s, s[], ?s, i32, s) arguments".to_string(),
+                });
+            };
+            let mut proc_args = Vec::with_capacity(arg_values.len());
+            for arg in arg_values {
+                let Value::String(arg) = arg else {
+                    return Err(VmError {
+                        message: "run_capture expects second argument as string array".to_string(),
+                    });
+                };
+                proc_args.push(arg.clone());
+            }
+            if *timeout_ms < 0 {
+                return Ok(err_value(format!(
+                    "run_capture failed: negative timeout {timeout_ms}"
+                )));
+            }
+            let env = match parse_env_block(env) {
+                Ok(env) => env,
+                Err(e) => return Ok(err_value(format!("run_capture failed: {e}"))),
+            };
+            let req = ProcRequest {
+                cmd: cmd.clone(),
+                args: proc_args,
+                stdin,
+                timeout_ms: (*timeout_ms > 0).then_some(*timeout_ms as u64),
+                env,
+            };
+            match host.proc_run_capture(&req) {
+                Ok(out) => Ok(ok_value(Value::Adt {
+                    tag: "Output".to_string(),
+                    fields: vec![
+                        Value::Int(i64::from(out.code)),
+                        Value::String(out.stdout),
+                        Value::String(out.stderr),
+                    ],
+                })),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        12 => {
+            let path = string1(args, "list_dir")?;
+            match host.fs_list_dir(path) {
+                Ok(names) => Ok(ok_value(Value::Array(
+                    names.into_iter().map(Value::String).collect(),
+                ))),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        13 => {
+            let path = string1(args, "exists")?;
+            match host.fs_exists(path) {
+                Ok(found) => Ok(ok_value(Value::Bool(found))),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        14 => {
+            if args.len() != 2 {
+                return Err(VmError {
+                    message: "append expects two arguments".to_string(),
+                });
+            }
+            let (Value::String(path), Value::String(data)) = (&args[0], &args[1]) else {
+                return Err(VmError {
+                    message: "append expects string arguments".to_string(),
+                });
+            };
+            match host.fs_append_string(path, data) {
+                Ok(()) => Ok(ok_value(Value::Unit)),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        15 => {
+            let path = string1(args, "mkdir")?;
+            match host.fs_create_dir(path) {
+                Ok(()) => Ok(ok_value(Value::Unit)),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        16 => {
+            let path = string1(args, "remove")?;
+            match host.fs_remove_file(path) {
+                Ok(()) => Ok(ok_value(Value::Unit)),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
+        17 => {
+            let path = string1(args, "read_lines")?;
+            match host.fs_read_to_string(path) {
+                Ok(data) => Ok(ok_value(Value::Array(
+                    data.lines()
+                        .map(|line| Value::String(line.to_string()))
+                        .collect(),
+                ))),
+                Err(e) => Ok(err_value(e)),
+            }
+        }
         20 => {
             let (a, b) = int2(args, "+")?;
             a.checked_add(b)
@@ -742,38 +1175,6 @@ fn call_builtin<H: VmHost>(host: &mut H, id: u8, args: &[Value]) -> Result<Value
                 .map(Value::Int)
                 .ok_or_else(|| arithmetic_error("integer overflow"))
         }
-        25 => {
-            if args.len() != 2 {
-                return Err(VmError {
-                    message: "== expects two arguments".to_string(),
-                });
-            }
-            Ok(Value::Bool(args[0] == args[1]))
-        }
-        26 => {
-            if args.len() != 2 {
-                return Err(VmError {
-                    message: "!= expects two arguments".to_string(),
-                });
-            }
-            Ok(Value::Bool(args[0] != args[1]))
-        }
-        27 => {
-            let (a, b) = int2(args, "<")?;
-            Ok(Value::Bool(a < b))
-        }
-        28 => {
-            let (a, b) = int2(args, "<=")?;
-            Ok(Value::Bool(a <= b))
-        }
-        29 => {
-            let (a, b) = int2(args, ">")?;
-            Ok(Value::Bool(a > b))
-        }
-        30 => {
-            let (a, b) = int2(args, ">=")?;
-            Ok(Value::Bool(a >= b))
-        }
         31 => {
             let (a, b) = bool2(args, "and")?;
             Ok(Value::Bool(a && b))
@@ -847,6 +1248,114 @@ fn call_builtin<H: VmHost>(host: &mut H, id: u8, args: &[Value]) -> Result<Value
     }
 }
 
+fn compare_builtin(
+    id: u8,
+    args: &[Value],
+    ctor_ranks: &HashMap<&str, usize>,
+) -> Result<Value, VmError> {
+    let op = match id {
+        25 => "==",
+        26 => "!=",
+        27 => "<",
+        28 => "<=",
+        29 => ">",
+        _ => ">=",
+    };
+    if args.len() != 2 {
+        return Err(VmError {
+            message: format!("{op} expects two arguments"),
+        });
+    }
+    let ord = compare_values(&args[0], &args[1], ctor_ranks).map_err(|message| VmError {
+        message: format!("{op}: {message}"),
+    })?;
+    Ok(Value::Bool(match id {
+        25 => ord == Ordering::Equal,
+        26 => ord != Ordering::Equal,
+        27 => ord == Ordering::Less,
+        28 => ord != Ordering::Greater,
+        29 => ord == Ordering::Greater,
+        _ => ord != Ordering::Less,
+    }))
+}
+
+// Total order over comparable values: numbers numerically (floats by IEEE
+// totalOrder), false < true, strings bytewise, arrays/maps/tuples
+// lexicographically, ADTs by constructor declaration order then fields.
+fn compare_values(
+    a: &Value,
+    b: &Value,
+    ctor_ranks: &HashMap<&str, usize>,
+) -> Result<Ordering, String> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Ok(x.cmp(y)),
+        (Value::Float(x), Value::Float(y)) => Ok(x.total_cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Ok(x.cmp(y)),
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        (Value::Unit, Value::Unit) => Ok(Ordering::Equal),
+        (Value::Array(xs), Value::Array(ys)) | (Value::Tuple(xs), Value::Tuple(ys)) => {
+            compare_seq(xs, ys, ctor_ranks)
+        }
+        (Value::Map(xs), Value::Map(ys)) => {
+            for ((kx, vx), (ky, vy)) in xs.iter().zip(ys.iter()) {
+                let ord = kx.cmp(ky).then(compare_values(vx, vy, ctor_ranks)?);
+                if ord != Ordering::Equal {
+                    return Ok(ord);
+                }
+            }
+            Ok(xs.len().cmp(&ys.len()))
+        }
+        (
+            Value::Adt {
+                tag: tx,
+                fields: fx,
+            },
+            Value::Adt {
+                tag: ty,
+                fields: fy,
+            },
+        ) => {
+            if tx != ty {
+                let rank = |tag: &str| ctor_ranks.get(tag).copied().unwrap_or(usize::MAX);
+                return Ok(rank(tx).cmp(&rank(ty)).then_with(|| tx.cmp(ty)));
+            }
+            compare_seq(fx, fy, ctor_ranks)
+        }
+        (Value::Closure { .. }, _) | (_, Value::Closure { .. }) => {
+            Err("cannot compare function values".to_string())
+        }
+        _ => Err("cannot compare values of different kinds".to_string()),
+    }
+}
+
+fn compare_seq(
+    xs: &[Value],
+    ys: &[Value],
+    ctor_ranks: &HashMap<&str, usize>,
+) -> Result<Ordering, String> {
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        let ord = compare_values(x, y, ctor_ranks)?;
+        if ord != Ordering::Equal {
+            return Ok(ord);
+        }
+    }
+    Ok(xs.len().cmp(&ys.len()))
+}
+
+fn string1<'a>(args: &'a [Value], op: &str) -> Result<&'a str, VmError> {
+    if args.len() != 1 {
+        return Err(VmError {
+            message: format!("{op} expects one argument"),
+        });
+    }
+    let Value::String(s) = &args[0] else {
+        return Err(VmError {
+            message: format!("{op} expects a string path"),
+        });
+    };
+    Ok(s)
+}
+
 fn int2(args: &[Value], op: &str) -> Result<(i64, i64), VmError> {
     if args.len() != 2 {
         return Err(VmError {
@@ -899,6 +1408,52 @@ fn err_value(message: String) -> Value {
     }
 }
 
+pub fn parse_env_block(block: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut env = Vec::new();
+    for line in block.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (line, None),
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("invalid env line `{line}`"));
+        }
+        env.push((key.to_string(), value));
+    }
+    Ok(env)
+}
+
+pub fn parse_header_block(block: &str) -> Result<Vec<(String, String)>, String> {
+    let mut headers = Vec::new();
+    for line in block.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("invalid header line `{line}`"));
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("invalid header line `{line}`"));
+        }
+        headers.push((name.to_string(), value.trim().to_string()));
+    }
+    Ok(headers)
+}
+
+pub fn format_header_block(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\n"))
+        .collect()
+}
+
 fn json_to_value(v: serde_json::Value) -> Value {
     match v {
         serde_json::Value::Null => Value::Adt {
@@ -1026,7 +1581,11 @@ fn arithmetic_error(message: &str) -> VmError {
 
 #[cfg(test)]
 mod tests {
-    use super::{FuzzHost, Value, VmError, VmHost, call_builtin, json_to_value, value_to_json};
+    use super::{
+        FuzzHost, HttpRequest, HttpResponse, ProcOutput, ProcRequest, Value, VmError, VmHost,
+        call_builtin, compare_builtin, json_to_value, value_to_json,
+    };
+    use std::collections::HashMap;
 
     struct TestHost;
 
@@ -1046,12 +1605,41 @@ mod tests {
         fn fs_write_string(&mut self, _path: &str, _data: &str) -> Result<(), String> {
             Err("disabled".to_string())
         }
+        fn fs_append_string(&mut self, _path: &str, _data: &str) -> Result<(), String> {
+            Err("disabled".to_string())
+        }
+        fn fs_list_dir(&mut self, _path: &str) -> Result<Vec<String>, String> {
+            Ok(vec!["a".to_string(), "b".to_string()])
+        }
+        fn fs_exists(&mut self, path: &str) -> Result<bool, String> {
+            Ok(path == "a")
+        }
+        fn fs_create_dir(&mut self, _path: &str) -> Result<(), String> {
+            Err("disabled".to_string())
+        }
+        fn fs_remove_file(&mut self, _path: &str) -> Result<(), String> {
+            Err("disabled".to_string())
+        }
         fn proc_run(&mut self, _cmd: &str, _args: &[String]) -> Result<i32, String> {
             Ok(0)
         }
+        fn proc_run_capture(&mut self, req: &ProcRequest) -> Result<ProcOutput, String> {
+            Ok(ProcOutput {
+                code: 3,
+                stdout: req.stdin.clone().unwrap_or_default(),
+                stderr: format!("{} {:?} {:?}", req.cmd, req.timeout_ms, req.env),
+            })
+        }
         fn http_get(&mut self, _url: &str) -> Result<String, String> {
             Err("disabled".to_string())
         }
+        fn http_request(&mut self, req: &HttpRequest) -> Result<HttpResponse, String> {
+            Ok(HttpResponse {
+                status: 201,
+                headers: req.headers.clone(),
+                body: format!("{} {} {}", req.method, req.url, req.body),
+            })
+        }
     }
 
     #[test]
@@ -1088,6 +1676,92 @@ mod tests {
         assert!(matches!(value, Value::Adt { tag, .. } if tag == "Ok"));
     }
 
+    #[test]
+    fn proc_run_capture_builtin_builds_output_adt() {
+        let mut host = TestHost;
+        let value = call_builtin(
+            &mut host,
+            11,
+            &[
+                Value::String("cat".to_string()),
+                Value::Array(Vec::new()),
+                Value::Adt {
+                    tag: "Some".to_string(),
+                    fields: vec![Value::String("in".to_string())],
+                },
+                Value::Int(0),
+                Value::String("A=1\nB".to_string()),
+            ],
+        )
+        .expect("run_capture should succeed");
+        let expected_env = vec![
+            ("A".to_string(), Some("1".to_string())),
+            ("B".to_string(), None),
+        ];
+        assert_eq!(
+            value,
+            Value::Adt {
+                tag: "Ok".to_string(),
+                fields: vec![Value::Adt {
+                    tag: "Output".to_string(),
+                    fields: vec![
+                        Value::Int(3),
+                        Value::String("in".to_string()),
+                        Value::String(format!("cat None {expected_env:?}")),
+                    ],
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn http_request_builtin_builds_response_adt() {
+        let mut host = TestHost;
+        let value = call_builtin(
+            &mut host,
+            10,
+            &[
+                Value::String("post".to_string()),
+                Value::String("http://mu.test/items".to_string()),
+                Value::String("Accept: text/plain\n".to_string()),
+                Value::String("payload".to_string()),
+            ],
+        )
+        .expect("request should succeed");
+        let Value::Adt { tag, fields } = value else {
+            panic!("expected ADT value");
+        };
+        assert_eq!(tag, "Ok");
+        assert_eq!(
+            fields,
+            vec![Value::Adt {
+                tag: "Response".to_string(),
+                fields: vec![
+                    Value::Int(201),
+                    Value::String("Accept: text/plain\n".to_string()),
+                    Value::String("POST http://mu.test/items payload".to_string()),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn http_request_builtin_reports_bad_headers_as_er() {
+        let mut host = TestHost;
+        let value = call_builtin(
+            &mut host,
+            10,
+            &[
+                Value::String("GET".to_string()),
+                Value::String("http://mu.test/".to_string()),
+                Value::String("no colon here".to_string()),
+                Value::String(String::new()),
+            ],
+        )
+        .expect("bad headers should not trap");
+        assert!(matches!(value, Value::Adt { tag, .. } if tag == "Er"));
+    }
+
     #[test]
     fn json_num_payload_uses_float_value() {
         let value = json_to_value(serde_json::json!(1.25));
@@ -1107,4 +1781,35 @@ mod tests {
         let json = value_to_json(&value).expect("Num(Float) should convert to JSON");
         assert_eq!(json, serde_json::json!(2.5));
     }
+
+    #[test]
+    fn float_comparisons_use_total_order() {
+        let ranks = HashMap::new();
+        let cmp = |id: u8, a: Value, b: Value| {
+            compare_builtin(id, &[a, b], &ranks).expect("floats should compare")
+        };
+        let nan = || Value::Float(f64::NAN);
+        assert_eq!(cmp(25, nan(), nan()), Value::Bool(true));
+        assert_eq!(
+            cmp(29, nan(), Value::Float(f64::INFINITY)),
+            Value::Bool(true)
+        );
+        assert_eq!(cmp(27, Value::Float(1.0), nan()), Value::Bool(true));
+        assert_eq!(
+            cmp(27, Value::Float(-f64::NAN), Value::Float(f64::NEG_INFINITY)),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            cmp(27, Value::Float(-0.0), Value::Float(0.0)),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            cmp(
+                28,
+                Value::Array(vec![Value::Float(2.0), nan()]),
+                Value::Array(vec![Value::Float(2.0), nan()])
+            ),
+            Value::Bool(true)
+        );
+    }
 }
@@ -1,7 +1,8 @@
 use std::fs;
 
-use muc::bytecode::{self, DecodeErrorCode, DecodedBytecode};
+use muc::bytecode::{self, DecodeErrorCode, DecodedBytecode, Profile};
 use muc::parser::parse_str;
+use muc::vm::run_bytecode;
 
 fn decode_fixture(name: &str) -> Result<DecodedBytecode, muc::bytecode::DecodeError> {
     let bytes = fs::read(format!("tests/bytecode/{name}")).expect("fixture should exist");
@@ -29,6 +30,70 @@ fn encode_decode_roundtrip_is_deterministic() {
     assert_eq!(encoded, reencoded, "encode/decode should be deterministic");
 }
 
+#[test]
+fn build_profile_is_recorded_in_metadata() {
+    let program = parse_str("@bc.p{F main:()->i32={^c(>,1,0);0};}").expect("program should parse");
+    let debug = bytecode::decode(&bytecode::compile(&program).expect("program should compile"))
+        .expect("debug build should decode");
+    assert_eq!(debug.profile(), Profile::Debug);
+    assert_eq!(
+        debug.metadata.get("profile").map(String::as_str),
+        Some("debug")
+    );
+
+    let encoded =
+        bytecode::compile_with_profile(&program, Profile::Release).expect("program should compile");
+    assert!(encoded.starts_with(bytecode::MAGIC));
+    let release = bytecode::decode(&encoded).expect("release build should decode");
+    assert_eq!(release.profile(), Profile::Release);
+    assert_eq!(bytecode::encode(&release), encoded);
+
+    let legacy = decode_fixture("minimal_valid.mub").expect("MUB1 streams still decode");
+    assert!(legacy.metadata.is_empty());
+    assert_eq!(legacy.profile(), Profile::Debug);
+}
+
+#[test]
+fn constructor_order_table_drives_adt_ordering() {
+    let program = parse_str("@bc.o{T Z=Zed|Alpha;F main:()->i32=i(c(<,Zed(),Alpha()),0,1);}")
+        .expect("program should parse");
+    let encoded = bytecode::compile(&program).expect("program should compile");
+    let mut decoded = bytecode::decode(&encoded).expect("encoded bytecode should decode");
+    let names = |decoded: &DecodedBytecode, family: &[u32]| {
+        family
+            .iter()
+            .map(|&idx| decoded.strings[idx as usize].clone())
+            .collect::<Vec<_>>()
+    };
+    let families = decoded
+        .ctor_families
+        .iter()
+        .map(|family| names(&decoded, family))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        families,
+        vec![
+            vec!["Ok".to_string(), "Er".to_string()],
+            vec!["None".to_string(), "Some".to_string()],
+            vec!["Zed".to_string(), "Alpha".to_string()],
+        ]
+    );
+    run_bytecode(&encoded, &[]).expect("Zed is declared first so sorts first");
+
+    // The VM ranks tags by the table, not by where they sit in the strings.
+    decoded.ctor_families[2].reverse();
+    let err = run_bytecode(&bytecode::encode(&decoded), &[])
+        .expect_err("reversed family should flip the order");
+    assert!(
+        err.message.contains("exited with status 1"),
+        "{}",
+        err.message
+    );
+
+    let legacy = decode_fixture("minimal_valid.mub").expect("MUB1 streams still decode");
+    assert_eq!(legacy.ctor_families, vec![Vec::<u32>::new()]);
+}
+
 #[test]
 fn decode_rejects_corrupt_vectors_with_stable_codes() {
     let cases = [
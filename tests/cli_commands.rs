@@ -116,8 +116,8 @@ fn build_outputs_mub_magic() {
 
     let bytes = fs::read(&out).expect("build output should be readable");
     assert!(
-        bytes.starts_with(b"MUB1"),
-        "bytecode should start with MUB1"
+        bytes.starts_with(b"MUB2"),
+        "bytecode should start with MUB2"
     );
     let _ = fs::remove_file(out);
 }
@@ -201,6 +201,69 @@ fn run_command_executes_built_mub() {
     let _ = fs::remove_file(out);
 }
 
+#[test]
+fn release_flag_skips_contract_checks() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let dir = unique_temp_dir("release");
+    fs::create_dir_all(&dir).expect("temp dir should be created");
+    let src = dir.join("main.mu");
+    fs::write(
+        &src,
+        "@rel.main{F pos:(n:i32)->i32={^c(>,n,0);n};F main:()->i32=c(pos,0);}",
+    )
+    .expect("source should be written");
+    let src = src.to_str().expect("temp path should be valid utf8");
+
+    let debug = Command::new(exe)
+        .args(["run", src])
+        .output()
+        .expect("binary should run");
+    assert!(
+        !debug.status.success(),
+        "debug run should trap on the require"
+    );
+
+    let release = Command::new(exe)
+        .args(["run", src, "--release"])
+        .output()
+        .expect("binary should run");
+    assert!(
+        release.status.success(),
+        "release run should skip the require: {}",
+        String::from_utf8_lossy(&release.stderr)
+    );
+
+    let out = dir.join("main.mub");
+    let build = Command::new(exe)
+        .args(["build", "--release", src, "-o"])
+        .arg(&out)
+        .output()
+        .expect("binary should run");
+    assert!(build.status.success(), "release build should succeed");
+    let built = Command::new(exe)
+        .arg("run")
+        .arg(&out)
+        .output()
+        .expect("binary should run");
+    assert!(
+        built.status.success(),
+        "release .mub should skip the require"
+    );
+
+    let rerun = Command::new(exe)
+        .arg("run")
+        .arg(&out)
+        .arg("--release")
+        .output()
+        .expect("binary should run");
+    assert!(
+        !rerun.status.success(),
+        "--release does not apply to .mub files"
+    );
+
+    let _ = fs::remove_dir_all(dir);
+}
+
 #[test]
 fn run_command_executes_built_http_mub() {
     let exe = env!("CARGO_BIN_EXE_muc");
@@ -388,6 +451,40 @@ fn build_loads_nested_local_modules_for_import_validation() {
     let _ = fs::remove_dir(dir);
 }
 
+#[test]
+fn check_reports_all_type_errors_with_a_count() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let dir = unique_temp_dir("multi_error");
+    fs::create_dir_all(&dir).expect("temp dir should be created");
+    let main = dir.join("main.mu");
+    fs::write(
+        &main,
+        "@multi.app{\nF a:(n:i32)->i32=c(+,n,\"x\");\nF main:()->i32=c(nope,1);\n}\n",
+    )
+    .expect("source should be written");
+
+    let output = Command::new(exe)
+        .args([
+            "check",
+            main.to_str().expect("temp path should be valid utf8"),
+        ])
+        .output()
+        .expect("binary should run");
+
+    assert!(!output.status.success(), "check should fail");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("main.mu:2:"), "{stderr}");
+    assert!(stderr.contains("E3004"), "{stderr}");
+    assert!(stderr.contains("main.mu:3:"), "{stderr}");
+    assert!(stderr.contains("E3001"), "{stderr}");
+    assert!(
+        stderr.contains("check failed with 2 type errors"),
+        "{stderr}"
+    );
+
+    let _ = fs::remove_dir_all(dir);
+}
+
 #[test]
 fn check_file_loads_local_modules_for_import_validation() {
     let exe = env!("CARGO_BIN_EXE_muc");
@@ -449,3 +546,174 @@ fn check_file_loads_nested_local_modules_for_import_validation() {
     let _ = fs::remove_dir(nested);
     let _ = fs::remove_dir(dir);
 }
+
+#[test]
+fn run_with_vfs_reads_seeded_files_and_writes_manifest() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let dir = unique_temp_dir("run_vfs");
+    let seed = dir.join("seed");
+    fs::create_dir_all(&seed).expect("seed dir should be created");
+    fs::write(seed.join("in.txt"), "hi").expect("seed file should be written");
+    let main = dir.join("main.mu");
+    let manifest = dir.join("out.json");
+    fs::write(
+        &main,
+        "@main.vfs{F main:()->i32!{fs}=m(c(read,\"in.txt\")){Ok(s)=>{c(write,\"out.txt\",s);0};Er(_)=>1;};}",
+    )
+    .expect("main source should be written");
+
+    let vfs_arg = format!("--vfs={}", seed.display());
+    let out_arg = format!("--vfs-out={}", manifest.display());
+    let output = Command::new(exe)
+        .args([
+            "run",
+            main.to_str().expect("temp path should be valid utf8"),
+            vfs_arg.as_str(),
+            out_arg.as_str(),
+        ])
+        .output()
+        .expect("binary should run");
+
+    assert!(
+        output.status.success(),
+        "run --vfs should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        !seed.join("out.txt").exists(),
+        "vfs writes must not touch the real filesystem"
+    );
+    let written = fs::read_to_string(&manifest).expect("vfs manifest should be written");
+    let json: serde_json::Value = serde_json::from_str(&written).expect("manifest should be json");
+    assert_eq!(json["in.txt"], "hi");
+    assert_eq!(json["out.txt"], "hi");
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn run_rejects_vfs_out_without_vfs() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let output = Command::new(exe)
+        .args(["run", "examples/hello.mu", "--vfs-out=out.json"])
+        .output()
+        .expect("binary should run");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`--vfs-out` requires `--vfs`"));
+}
+
+#[test]
+fn run_rejects_unknown_arguments_before_separator() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let output = Command::new(exe)
+        .args(["run", "examples/hello.mu", "--vsf=seed"])
+        .output()
+        .expect("binary should run");
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("unknown argument for run: `--vsf=seed`")
+    );
+}
+
+#[test]
+fn run_with_http_fixtures_replays_responses_offline() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let dir = unique_temp_dir("run_http_fixtures");
+    fs::create_dir_all(&dir).expect("temp dir should be created");
+    let main = dir.join("main.mu");
+    let fixtures = dir.join("fixtures.json");
+    fs::write(
+        &fixtures,
+        r#"[{"method":"GET","url":"http://mu.invalid/hello","status":200,"body":"hello from fixture"}]"#,
+    )
+    .expect("fixtures should be written");
+    fs::write(
+        &main,
+        "@main.httpfx{F main:()->i32!{io,net}=m(c(get,\"http://mu.invalid/hello\")){Ok(body)=>{c(println,body);0};Er(e)=>{c(println,e);1};};}",
+    )
+    .expect("main source should be written");
+
+    let fixtures_arg = format!("--http-fixtures={}", fixtures.display());
+    let output = Command::new(exe)
+        .args([
+            "run",
+            main.to_str().expect("temp path should be valid utf8"),
+            fixtures_arg.as_str(),
+        ])
+        .output()
+        .expect("binary should run");
+
+    assert!(
+        output.status.success(),
+        "run --http-fixtures should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "hello from fixture\n"
+    );
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn fix_applies_edits_reformats_and_rechecks() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let dir = unique_temp_dir("fix");
+    fs::create_dir_all(&dir).expect("temp dir should be created");
+    let main = dir.join("main.mu");
+    fs::write(
+        &main,
+        "@fix.app{\nE[main,gone];\nT C=Red|Blue;\nF name:(c:C)->s=m(c){Red=>\"red\";};\nF shout:(x:s)->unit!{fs,io}=c(print,x);\nF main:()->i32={c(print,c(name,Red()));0};\n}\n",
+    )
+    .expect("source should be written");
+    let path = main.to_str().expect("temp path should be valid utf8");
+
+    let output = Command::new(exe)
+        .args(["fix", path])
+        .output()
+        .expect("binary should run");
+    assert!(
+        output.status.success(),
+        "fix should leave a checking program: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("main.mu (4 fixes)"), "{stdout}");
+
+    let fixed = fs::read_to_string(&main).expect("source should be readable");
+    assert!(fixed.contains("E[main];"), "{fixed}");
+    assert!(fixed.contains("Blue=>"), "{fixed}");
+    assert!(fixed.contains("!{io,fs}"), "{fixed}");
+    assert!(fixed.contains("F main:()->i32!{io}="), "{fixed}");
+    let fmt = Command::new(exe)
+        .args(["fmt", "--check", path])
+        .output()
+        .expect("binary should run");
+    assert!(fmt.status.success(), "fixed files are formatted");
+    let run = Command::new(exe)
+        .args(["run", path])
+        .output()
+        .expect("binary should run");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "red");
+
+    // Errors without a fix are reported after the fixes that could be made.
+    fs::write(&main, "@fix.app{F main:()->i32!{fs,io}=c(nope,1);}")
+        .expect("source should be written");
+    let output = Command::new(exe)
+        .args(["fix", path])
+        .output()
+        .expect("binary should run");
+    assert!(!output.status.success(), "nope stays unknown");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("E3001"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let fixed = fs::read_to_string(&main).expect("source should be readable");
+    assert!(fixed.contains("!{io,fs}"), "{fixed}");
+
+    let _ = fs::remove_dir_all(dir);
+}
@@ -35,6 +35,73 @@ fn parse_errors_include_file_line_col_and_code() {
     let _ = fs::remove_file(path);
 }
 
+#[test]
+fn every_syntax_error_is_reported_with_a_count() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let path = unique_temp_file("multi_parse");
+    fs::write(
+        &path,
+        "@m{\nV x:i32=;\nV y:i32=1;\nF main:()->i32=c(+,1;\n}\n",
+    )
+    .expect("should write fixture");
+
+    for cmd in [&["check"][..], &["fmt", "--check"], &["fmt"]] {
+        let output = Command::new(exe)
+            .args(cmd)
+            .arg(path.to_str().expect("utf8 path"))
+            .output()
+            .expect("binary should run");
+
+        assert!(!output.status.success(), "{cmd:?} should fail");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(":2:") && stderr.contains("E2005"),
+            "{cmd:?}: {stderr}"
+        );
+        assert!(stderr.contains(":4:"), "{cmd:?}: {stderr}");
+        assert!(
+            stderr.contains("parse failed with 2 syntax errors"),
+            "{cmd:?}: {stderr}"
+        );
+    }
+
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn rich_format_shows_source_snippets_labels_and_suggestions() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let path = unique_temp_file("rich");
+    fs::write(
+        &path,
+        "@m.rich{\nF twice:(n:i32)->i32=c(+,n,n);\nF main:()->i32=v(count=2,c(twice,cout));\n}\n",
+    )
+    .expect("should write fixture");
+
+    let output = Command::new(exe)
+        .args(["check", path.to_str().expect("utf8 path"), "--format=rich"])
+        .output()
+        .expect("binary should run");
+
+    assert!(!output.status.success(), "check should fail");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("error[E3001]: unknown name `cout`"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("3 | F main:()->i32=v(count=2,c(twice,cout));"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("  |                                  ^^^^\n"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("= help: did you mean `count`?"), "{stderr}");
+
+    let _ = fs::remove_file(path);
+}
+
 #[test]
 fn type_errors_include_file_line_col_and_code() {
     let exe = env!("CARGO_BIN_EXE_muc");
@@ -80,3 +147,95 @@ fn runtime_errors_include_stable_code() {
 
     let _ = fs::remove_file(path);
 }
+
+fn json_stderr(output: &std::process::Output) -> serde_json::Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    serde_json::from_str(&stderr).unwrap_or_else(|e| panic!("stderr is not JSON ({e}): {stderr}"))
+}
+
+#[test]
+fn json_format_reports_diagnostics_with_locations_and_fixes() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let path = unique_temp_file("json_check");
+    let src = "@m.json{\nF main:()->i32=v(count=2,c(+,cout,1));\n}\n";
+    fs::write(&path, src).expect("should write fixture");
+    let file = path.to_str().expect("utf8 path");
+
+    let output = Command::new(exe)
+        .args(["check", file, "--format=json"])
+        .output()
+        .expect("binary should run");
+    assert!(!output.status.success(), "check should fail");
+    let report = json_stderr(&output);
+    assert_eq!(report["version"], 1);
+    assert_eq!(report["ok"], false);
+    let diags = report["diagnostics"].as_array().expect("diagnostics array");
+    assert_eq!(diags.len(), 1, "{report}");
+    let diag = &diags[0];
+    assert_eq!(diag["code"], "E3001");
+    assert_eq!(diag["severity"], "error");
+    assert_eq!(diag["file"], file);
+    assert_eq!(diag["line"], 2);
+    assert_eq!(diag["col"], 30);
+    let start = src.find("cout").expect("fixture mentions cout");
+    assert_eq!(diag["span"]["start"], start);
+    assert_eq!(diag["span"]["end"], start + 4);
+    assert_eq!(diag["fixes"][0]["message"], "did you mean `count`?");
+    assert_eq!(diag["fixes"][0]["edits"][0]["replacement"], "count");
+
+    // `fmt --check` describes the reformatting as an edit.
+    fs::write(&path, "@m.json{F main:()->i32=\n  0;}\n").expect("should write fixture");
+    let output = Command::new(exe)
+        .args(["fmt", "--check", file, "--format=json"])
+        .output()
+        .expect("binary should run");
+    assert!(!output.status.success(), "fmt --check should fail");
+    let report = json_stderr(&output);
+    let diag = &report["diagnostics"][0];
+    assert_eq!(diag["code"], "E5001");
+    let edit = &diag["fixes"][0]["edits"][0];
+    let (edit_start, edit_end) = (
+        edit["span"]["start"].as_u64().expect("start") as usize,
+        edit["span"]["end"].as_u64().expect("end") as usize,
+    );
+    let before = fs::read_to_string(&path).expect("should read fixture");
+    let fixed = format!(
+        "{}{}{}",
+        &before[..edit_start],
+        edit["replacement"].as_str().expect("replacement"),
+        &before[edit_end..]
+    );
+    fs::write(&path, fixed).expect("should write fixture");
+    let output = Command::new(exe)
+        .args(["fmt", "--check", file, "--format=json"])
+        .output()
+        .expect("binary should run");
+    assert!(output.status.success(), "the edit should format the file");
+    assert_eq!(json_stderr(&output)["ok"], true);
+
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn json_format_reports_runtime_traps_without_a_span() {
+    let exe = env!("CARGO_BIN_EXE_muc");
+    let path = unique_temp_file("json_trap");
+    fs::write(&path, "@m{F main:()->i32=c(/,1,0);}").expect("should write fixture");
+    let file = path.to_str().expect("utf8 path");
+
+    let output = Command::new(exe)
+        .args(["run", file, "--format=json"])
+        .output()
+        .expect("binary should run");
+
+    assert!(!output.status.success(), "run should fail");
+    let report = json_stderr(&output);
+    let diag = &report["diagnostics"][0];
+    assert_eq!(diag["code"], "E4003");
+    assert_eq!(diag["file"], file);
+    assert_eq!(diag["message"], "division by zero");
+    assert!(diag["span"].is_null(), "{report}");
+    assert!(diag["line"].is_null(), "{report}");
+
+    let _ = fs::remove_file(path);
+}
@@ -98,3 +98,147 @@ fn compressed_symtab_excludes_core_forms() {
         );
     }
 }
+
+#[test]
+fn named_function_params_roundtrip_through_both_modes() {
+    let input =
+        "@m.named{F add:( lhs:i32 , rhs:i32 )->i32=c(+,lhs,rhs);F main:()->i32=c(-,c(add,1,2),3);}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.named{F add:(lhs:i32,rhs:i32)->i32=c(+,lhs,rhs);F main:()->i32=c(-,c(add,1,2),3);}\n"
+    );
+
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(
+        compressed.contains("(lhs:i32,rhs:i32)"),
+        "short param names should stay inline: {compressed}"
+    );
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+
+    let input = "@m.named{F scale:(multiplier:i32,value:i32)->i32=c(*,multiplier,c(+,multiplier,value));F main:()->i32=c(-,c(scale,1,2),3);}";
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(
+        compressed.contains("$[multiplier") && compressed.contains("(#0:i32,"),
+        "long param names should go through the symtab: {compressed}"
+    );
+    let readable = parse_and_format(input).expect("input should parse");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn tuple_expressions_and_let_patterns_roundtrip_through_both_modes() {
+    let input = "@m.tup{F main:()->i32=v(( a , b ):(i32,i32)=( 1 , 2 ),m((a,b)){(x,_)=>x;});}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.tup{F main:()->i32=v((a,b):(i32,i32)=(1,2),m((a,b)){(x,_)=>x;});}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains("[v (a,b) (1,2) "), "{compressed}");
+    let again = parse_and_format_mode(&compressed, FmtMode::Compressed).expect("compressed parses");
+    assert_eq!(again, compressed);
+}
+
+#[test]
+fn guards_and_or_patterns_roundtrip_through_both_modes() {
+    let input = "@m.guards{T C=R|G|B;F g:(c:C,n:i32)->i32=m(c){R | G  if c(>,n,0)=>1;R|G=>2;B=>3;};F main:()->i32=c(g,B(),1);}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.guards{T C=R|G|B;F g:(c:C,n:i32)->i32=m(c){R|G if c(>,n,0)=>1;R|G=>2;B=>3;};F main:()->i32=c(g,B(),1);}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains("{R|G if (> n 0) 1}"), "{compressed}");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn try_operator_prints_postfix_in_both_modes() {
+    let input = "@m.try{F r:(p:s)->s!s!{fs}=Ok(c(str_cat,c(read,p) ?,\"!\"));}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.try{F r:(p:s)->s!s!{fs}=Ok(c(str_cat,c(read,p)?,\"!\"));}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains("(read p)?"), "{compressed}");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn records_roundtrip_and_compress_field_names() {
+    let input = "@m.rec{T Pos = { column:i32 , row:i32 };F right:(p:Pos)->Pos=p{ column = c(+,p.column,1) };F main:()->i32=c(right,Pos(column=1,row=2)).row;}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.rec{T Pos={column:i32,row:i32};F right:(p:Pos)->Pos=p{column=c(+,p.column,1)};F main:()->i32=c(right,Pos(column=1,row=2)).row;}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains(",column];"), "{compressed}");
+    assert!(
+        compressed.contains("p.#1") && compressed.contains("{#1:i32,"),
+        "{compressed}"
+    );
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn qualified_type_names_roundtrip() {
+    let input = "@m.q{:dep=dep.a;F keep:(r:dep . Room[i32])->dep.Room[i32]=r;}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.q{:dep=dep.a;F keep:(r:dep.Room[i32])->dep.Room[i32]=r;}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn aliases_opaque_exports_and_qualified_ctors_roundtrip() {
+    let input = "@m.al{:d=dep.a;E[~ Box, Step];A Step [X] = (X)->X;T Box=Box(i32);F f:(s:d.Shape)->i32=m(s){d.Circle(n)=>n;d.Dot=>0;};F g:()->d.Shape=d.Circle(1);}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.al{:d=dep.a;E[~Box,Step];A Step[X]=(X)->X;T Box=Box(i32);F f:(s:d.Shape)->i32=m(s){d.Circle(n)=>n;d.Dot=>0;};F g:()->d.Shape=d.Circle(1);}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains("d.#0(1)"), "{compressed}");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn effect_variables_print_after_atoms() {
+    let input = "@m.ev{F run[A]:(g:(A)->A!{e, io},x:A)->A!{e,io}=c(g,x);}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.ev{F run[A]:(g:(A)->A!{io,e},x:A)->A!{io,e}=c(g,x);}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains("!{I,e}"), "{compressed}");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
+
+#[test]
+fn recursive_lets_roundtrip_in_both_modes() {
+    let input = "@m.rec{F main:()->i32=w( ev = l(n:i32):b=c(od,n), od=l(n:i32):b=c(ev,n), i(c(ev,1),0,1));}";
+    let readable = parse_and_format(input).expect("input should parse");
+    assert_eq!(
+        readable,
+        "@m.rec{F main:()->i32=w(ev=l(n:i32):b=c(od,n),od=l(n:i32):b=c(ev,n),i(c(ev,1),0,1));}\n"
+    );
+    let compressed = parse_and_format_mode(input, FmtMode::Compressed).expect("input should parse");
+    assert!(compressed.contains("[w {"), "{compressed}");
+    let back = parse_and_format_mode(&compressed, FmtMode::Readable).expect("compressed parses");
+    assert_eq!(back, readable);
+}
@@ -0,0 +1,86 @@
+use muc::bytecode::compile;
+use muc::http_fixture::{HttpFixtureHost, HttpFixtures};
+use muc::parser::parse_str;
+use muc::typecheck::check_program;
+use muc::vm::{FuzzHost, HttpRequest, VmHost, run_bytecode_with_fuel_and_host};
+
+const FIXTURES: &str = r#"[
+  {"method":"GET","url":"http://mu.test/a","status":200,"headers":{"content-type":"text/plain"},"body":"first"},
+  {"method":"GET","url":"http://mu.test/a","status":200,"body":"second"},
+  {"method":"POST","url":"http://mu.test/items","status":201,"headers":[["location","/items/1"]],"body":"created"},
+  {"url":"http://mu.test/missing","status":404,"body":"nope"}
+]"#;
+
+fn get(url: &str) -> HttpRequest {
+    HttpRequest {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        headers: Vec::new(),
+        body: String::new(),
+    }
+}
+
+fn replay_host() -> HttpFixtureHost<FuzzHost> {
+    let fixtures = HttpFixtures::from_json(FIXTURES).expect("fixtures should load");
+    HttpFixtureHost::replay(FuzzHost, fixtures)
+}
+
+#[test]
+fn replay_serves_matching_exchanges_in_order_then_repeats_last() {
+    let mut host = replay_host();
+    let bodies: Vec<String> = (0..3)
+        .map(|_| {
+            host.http_request(&get("http://mu.test/a"))
+                .expect("fixture should match")
+                .body
+        })
+        .collect();
+    assert_eq!(bodies, ["first", "second", "second"]);
+}
+
+#[test]
+fn replay_reports_unmatched_requests() {
+    let mut host = replay_host();
+    let err = host
+        .http_request(&get("http://mu.test/other"))
+        .expect_err("unmatched request should fail");
+    assert!(
+        err.contains("no response recorded for GET http://mu.test/other"),
+        "unexpected error: {err}"
+    );
+}
+
+#[test]
+fn replay_get_keeps_body_only_semantics() {
+    let mut host = replay_host();
+    assert_eq!(host.http_get("http://mu.test/a").as_deref(), Ok("first"));
+    let err = host
+        .http_get("http://mu.test/missing")
+        .expect_err("non-2xx get should fail");
+    assert!(err.contains("http status 404"), "unexpected error: {err}");
+}
+
+#[test]
+fn fixtures_roundtrip_through_json() {
+    let fixtures = HttpFixtures::from_json(FIXTURES).expect("fixtures should load");
+    let reloaded =
+        HttpFixtures::from_json(&fixtures.to_json()).expect("serialized fixtures should reload");
+    assert_eq!(reloaded.entries(), fixtures.entries());
+}
+
+#[test]
+fn fixtures_reject_malformed_entries() {
+    let err = HttpFixtures::from_json(r#"[{"method":"GET"}]"#).expect_err("url is required");
+    assert!(err.contains("missing `url`"), "unexpected error: {err}");
+}
+
+#[test]
+fn request_builtin_returns_response_adt_from_fixtures() {
+    let src = "@x.httpreq{T Response=Response(i32,s,s);F main:()->i32!{net}=m(c(request,\"post\",\"http://mu.test/items\",\"accept: text/plain\",\"{}\")){Ok(r)=>m(r){Response(st,h,b)=>i(c(==,st,201),i(c(==,b,\"created\"),i(c(==,h,\"location: /items/1\\n\"),0,4),3),2);};Er(_)=>1;};}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("program should typecheck");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    let mut host = replay_host();
+    run_bytecode_with_fuel_and_host(&bc, &[], 100_000, &mut host)
+        .expect("request should be served from fixtures");
+}
@@ -1,4 +1,6 @@
-use muc::parser::{ParseErrorCode, parse_str};
+use muc::fmt::format_program;
+use muc::parser::{ParseErrorCode, parse_str, parse_str_recovering};
+use muc::typecheck::check_program;
 
 #[test]
 fn parse_missing_semicolon_reports_stable_code() {
@@ -50,3 +52,58 @@ fn parse_rejects_out_of_range_symref() {
     assert_eq!(err.code, ParseErrorCode::SymbolRefOutOfRange);
     assert_eq!(err.code.as_str(), "E2007");
 }
+
+#[test]
+fn parser_recovers_at_declaration_boundaries() {
+    let src = "@m.r{V a:i32=;F ok:(n:i32)->i32=c(+,n,1);F bad:(n:i32)->i32=c(+,n;V b:i32 1;F main:()->i32=c(ok,1);}";
+    let (program, errors) = parse_str_recovering(src);
+    let codes = errors.iter().map(|e| e.code.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        [
+            ParseErrorCode::ExpectedExpr,
+            ParseErrorCode::ExpectedToken,
+            ParseErrorCode::ExpectedToken,
+        ],
+        "{errors:?}"
+    );
+    assert_eq!(
+        parse_str(src).expect_err("still an error").span,
+        errors[0].span
+    );
+
+    let program = program.expect("header parsed, so a partial program is returned");
+    assert_eq!(program.module.decls.len(), 2);
+    check_program(&program).expect("intact declarations typecheck");
+    assert_eq!(
+        format_program(&program),
+        "@m.r{F ok:(n:i32)->i32=c(+,n,1);F main:()->i32=c(ok,1);}\n"
+    );
+}
+
+#[test]
+fn parser_recovers_from_missing_semicolons_and_stray_tokens() {
+    let (program, errors) = parse_str_recovering("@m{V x:i32=1 V y:i32=2;) ;V z:i32=3;}");
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    let program = program.expect("partial program");
+    assert_eq!(program.module.decls.len(), 2);
+
+    let (program, errors) = parse_str_recovering("@m{V x:i32=1;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(program.expect("partial program").module.decls.len(), 1);
+
+    let (program, errors) = parse_str_recovering("V x:i32=1;");
+    assert!(program.is_none());
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn if_is_reserved_and_cannot_name_a_binding() {
+    let err = parse_str("@m{V if:i32=1;}").expect_err("`if` should not be a name");
+    assert_eq!(err.code, ParseErrorCode::ExpectedIdent);
+    assert!(err.message.contains("reserved word"), "{}", err.message);
+
+    let err = parse_str("@m{F f:(x:i32)->i32=[m x {x if}];}")
+        .expect_err("a bracket arm whose body is `if` is a guard without a body");
+    assert_eq!(err.code, ParseErrorCode::ExpectedExpr);
+}
@@ -0,0 +1,71 @@
+#![cfg(unix)]
+
+use muc::vm::{FuzzHost, ProcRequest, RealHost, VmHost};
+
+fn sh(script: &str) -> ProcRequest {
+    ProcRequest {
+        cmd: "sh".to_string(),
+        args: vec!["-c".to_string(), script.to_string()],
+        stdin: None,
+        timeout_ms: None,
+        env: Vec::new(),
+    }
+}
+
+#[test]
+fn real_host_captures_stdout_stderr_and_exit_code() {
+    let out = RealHost
+        .proc_run_capture(&sh("echo out; echo err >&2; exit 3"))
+        .expect("sh should run");
+    assert_eq!(out.code, 3);
+    assert_eq!(out.stdout, "out\n");
+    assert_eq!(out.stderr, "err\n");
+}
+
+#[test]
+fn real_host_feeds_stdin_and_controls_env() {
+    let mut req = sh("cat; printf '%s|%s' \"$MU_SET\" \"${HOME:-unset}\"");
+    req.stdin = Some("piped\n".to_string());
+    req.env = vec![
+        ("MU_SET".to_string(), Some("yes".to_string())),
+        ("HOME".to_string(), None),
+    ];
+    let out = RealHost.proc_run_capture(&req).expect("sh should run");
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "piped\nyes|unset");
+}
+
+#[test]
+fn real_host_distinguishes_empty_stdin_from_no_stdin() {
+    let probe = "if [ -p /dev/stdin ]; then echo pipe; else echo closed; fi";
+    let out = RealHost
+        .proc_run_capture(&sh(probe))
+        .expect("sh should run");
+    assert_eq!(out.stdout, "closed\n");
+
+    let mut req = sh(probe);
+    req.stdin = Some(String::new());
+    let out = RealHost.proc_run_capture(&req).expect("sh should run");
+    assert_eq!(out.stdout, "pipe\n");
+}
+
+#[test]
+fn real_host_kills_processes_past_timeout() {
+    let mut req = sh("sleep 5");
+    req.timeout_ms = Some(50);
+    let err = RealHost
+        .proc_run_capture(&req)
+        .expect_err("sleep should time out");
+    assert!(
+        err.contains("timed out after 50ms"),
+        "unexpected error: {err}"
+    );
+}
+
+#[test]
+fn fuzz_host_stubs_run_capture() {
+    let err = FuzzHost
+        .proc_run_capture(&sh("echo hi"))
+        .expect_err("fuzz host should not spawn processes");
+    assert!(err.contains("fuzz host"), "unexpected error: {err}");
+}
@@ -1,5 +1,8 @@
+use muc::diagnostic::Note;
 use muc::parser::parse_str;
-use muc::typecheck::{TypeErrorCode, check_program, check_programs};
+use muc::typecheck::{
+    TypeErrorCode, check_program, check_program_with_modules, check_programs, check_programs_all,
+};
 
 #[test]
 fn pure_function_cannot_call_io() {
@@ -17,6 +20,13 @@ fn effectful_function_can_call_io() {
     check_program(&program).expect("effectful function should pass");
 }
 
+#[test]
+fn locals_and_module_functions_shadow_builtins() {
+    let src = "@m.shadow{F append:(s,s)->i32=7;F main:()->i32=v(get=l(n:i32):i32=n,c(+,c(append,\"a\",\"b\"),c(get,1)));}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("user `append` and local `get` should shadow the builtins");
+}
+
 #[test]
 fn non_exhaustive_bool_match_fails() {
     let src = "@m.ne{V x:i32=m(t){t=>1;};}";
@@ -150,6 +160,21 @@ fn proc_run_rejects_non_array_args_type() {
     assert_eq!(err.code, TypeErrorCode::TypeMismatch);
 }
 
+#[test]
+fn proc_run_capture_returns_declared_output_adt() {
+    let src = "@m.capture{T Output=Output(i32,s,s);F helper:(s[])->s!s!{proc}=m(c(run_capture,\"echo\",arg0,None(),1000,\"LANG=C\")){Ok(o)=>m(o){Output(_,out,_)=>Ok(out);};Er(e)=>Er(e);};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("run_capture should yield Res[Output,s]");
+}
+
+#[test]
+fn proc_run_capture_requires_proc_effect() {
+    let src = "@m.capturebad{F helper:(s[])->i32={c(run_capture,\"echo\",arg0,None(),0,\"\");0};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("missing proc effect should fail");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+}
+
 #[test]
 fn return_magic_is_allowed_inside_ensure() {
     let src = "@m.r1{F helper:()->b={_ _r;t};F main:()->i32=0;}";
@@ -318,3 +343,764 @@ fn mixed_ident_and_symref_typechecks() {
     let program = parse_str(src).expect("program should parse");
     check_program(&program).expect("mixed identifiers and symrefs should typecheck");
 }
+
+#[test]
+fn generic_functions_instantiate_fresh_type_vars_per_call() {
+    let src = "@m.gid{F id[A]:(A)->A=arg0;F main:()->i32={c(id,\"s\");c(id,0)};}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("id should instantiate at s and i32");
+}
+
+#[test]
+fn generic_type_params_are_rigid_inside_their_body() {
+    let src = "@m.grigid{F bad[A]:(A)->A=1;F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("A must not unify with i32");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+    assert!(
+        err.message.contains("expected A, got i32"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn generic_call_reports_unified_types_on_mismatch() {
+    let src = "@m.gpair{F first[A]:(A,A)->A=arg0;F main:()->i32=c(first,1,\"s\");}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("A cannot be both i32 and s");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+    assert!(
+        err.message.contains("expected i32, got s"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn generic_ctors_instantiate_per_use() {
+    let src =
+        "@m.gbox{T Box[A]=Box(A);V bs:Box[s]=Box(\"x\");F main:()->i32=m(Box(1)){Box(x)=>x;};}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("Box should instantiate at s and i32");
+}
+
+#[test]
+fn generic_ctor_mismatch_shows_unified_types() {
+    let src = "@m.gboxbad{T Box[A]=Box(A);V b:Box[s]=Box(1);F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("Box[i32] is not Box[s]");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+    assert!(
+        err.message.contains("expected Box[s], got Box[i32]"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn result_ctor_holes_do_not_leak_between_uses() {
+    let src = "@m.holes{F takes:(i32!s)->i32=0;F takeb:(i32!b)->i32=0;F main:()->i32=v(x=Ok(1),{c(takes,x);c(takeb,x)});}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("x cannot be both i32!s and i32!b");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+    assert!(
+        err.message.contains("expected i32!b, got i32!s"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn unifier_rejects_infinite_types() {
+    let src = "@m.occurs{F main:()->i32=v(x=Er(1),i(c(==,x,Ok(x)),0,1));}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("occurs check should fail");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+    assert!(err.message.contains("infinite type"), "{}", err.message);
+}
+
+#[test]
+fn named_params_are_bound_in_function_bodies() {
+    let src = "@m.named{F greet:(name:s,times:i32)->s=i(c(>,times,0),name,\"\");F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("named params should be in scope");
+}
+
+#[test]
+fn named_params_replace_positional_names() {
+    let src = "@m.namedpos{F id:(x:i32)->i32=arg0;F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("arg0 is not bound when params are named");
+    assert_eq!(err.code, TypeErrorCode::UnknownName);
+}
+
+#[test]
+fn duplicate_named_params_are_rejected() {
+    let src = "@m.nameddup{F dup:(x:i32,x:i32)->i32=x;F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("duplicate params should fail");
+    assert_eq!(err.code, TypeErrorCode::DuplicateSymbol);
+}
+
+#[test]
+fn ordering_is_polymorphic_over_comparable_types() {
+    let src = "@m.ord{T Suit=Clubs|Hearts(i32);F main:()->i32=i(c(and,c(<,\"a\",\"b\"),c(<=,Clubs(),Hearts(2))),0,1);}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("strings and ADTs should be ordered");
+}
+
+#[test]
+fn ordering_rejects_mixed_operand_types() {
+    let src = "@m.ordmix{F main:()->i32=i(c(<,1,\"a\"),0,1);}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("i32 and s are not comparable");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+}
+
+#[test]
+fn comparison_rejects_adts_holding_functions() {
+    let src =
+        "@m.ordfn{T Cb=Cb(()->i32);F zero:()->i32=0;F main:()->i32=i(c(<,Cb(zero),Cb(zero)),0,1);}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("function fields are not comparable");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+    assert!(
+        err.message
+            .contains("comparison does not support function values"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn comparison_allows_type_parameters() {
+    for src in [
+        "@m.mx{F mx[A]:(a:A,b:A)->A=i(c(>,a,b),a,b);F main:()->i32=c(mx,0,1);}",
+        "@m.ltg{T Box[A]=Box(A);F ltg[A]:(x:Box[A],y:Box[A])->b=c(<,x,y);F main:()->i32=i(c(ltg,Box(\"a\"),Box(\"b\")),0,1);}",
+    ] {
+        let program = parse_str(src).expect("program should parse");
+        check_program(&program).expect("type parameters are comparable");
+    }
+}
+
+#[test]
+fn tuple_expressions_and_let_destructuring_typecheck() {
+    let src = "@m.tup{F swap:(p:(i32,s))->(s,i32)=v((n,text)=p,(text,n));F main:()->i32=m(c(swap,(1,\"a\"))){(_,n)=>n;};}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("tuples should typecheck");
+}
+
+#[test]
+fn tuple_destructuring_checks_item_types() {
+    let src = "@m.tupbad{F main:()->i32=v((a,b)=(1,\"x\"),c(+,a,b));}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("b is a string");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+}
+
+#[test]
+fn let_destructuring_rejects_refutable_patterns() {
+    let src = "@m.tupref{F main:()->i32=v((1,b)=(1,2),b);}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("literal items are refutable");
+    assert_eq!(err.code, TypeErrorCode::InvalidPattern);
+}
+
+#[test]
+fn nested_non_exhaustive_match_reports_a_missing_pattern() {
+    let src = "@m.nest{T Kind=Normal|Boss;T Room=Room(i32,Kind);T Rooms=RNil|RCons(Room,Rooms);F f1:(r:Rooms)->i32=m(r){RCons(Room(_,Boss),_)=>1;RNil=>0;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("normal rooms are not covered");
+    assert_eq!(err.code, TypeErrorCode::NonExhaustiveMatch);
+    assert!(
+        err.message
+            .contains("pattern `RCons(Room(_,Normal),_)` is not covered"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn nested_exhaustive_match_is_accepted() {
+    let src = "@m.nestok{T L=Nil|Cons(i32,L);F two:(l:L)->b=m(l){Cons(_,Cons(_,_))=>t;Cons(_,Nil)=>f;Nil=>f;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("all list shapes are covered");
+}
+
+#[test]
+fn int_literal_match_without_wildcard_names_an_uncovered_value() {
+    let src = "@m.ints{F name:(n:i32)->s=m(n){0=>\"zero\";1=>\"one\";};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("ints need a fallback arm");
+    assert_eq!(err.code, TypeErrorCode::NonExhaustiveMatch);
+    assert!(err.message.contains("pattern `2`"), "{}", err.message);
+}
+
+#[test]
+fn arms_after_a_covering_arm_are_unreachable() {
+    let src = "@m.dead{T C=Red|Blue;F code:(c:C)->i32=m(c){_=>0;Red=>1;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("Red arm can never run");
+    assert_eq!(err.code, TypeErrorCode::UnreachableArm);
+    assert_eq!(err.code.as_str(), "E3015");
+    assert!(err.message.contains("pattern `Red`"), "{}", err.message);
+}
+
+#[test]
+fn nested_arm_covered_by_earlier_arms_is_unreachable() {
+    let src = "@m.dead2{F g:(p:(b,b))->i32=m(p){(t,_)=>1;(f,_)=>2;(_,t)=>3;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("(_,t) is covered by the first two arms");
+    assert_eq!(err.code, TypeErrorCode::UnreachableArm);
+}
+
+#[test]
+fn guarded_arms_do_not_count_toward_exhaustiveness() {
+    let src = "@m.guardcov{F g:(b:b)->i32=m(b){t=>1;f if c(==,1,1)=>2;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("the guarded f arm may fall through");
+    assert_eq!(err.code, TypeErrorCode::NonExhaustiveMatch);
+    assert!(err.message.contains("pattern `f`"), "{}", err.message);
+
+    let ok = "@m.guardok{F g:(n:i32)->i32=m(n){x if c(>,x,0)=>x;_=>0;};F main:()->i32=0;}";
+    let program = parse_str(ok).expect("program should parse");
+    check_program(&program).expect("guard plus catch-all is exhaustive");
+}
+
+#[test]
+fn guard_must_be_bool() {
+    let src = "@m.guardty{F g:(n:i32)->i32=m(n){x if x=>x;_=>0;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("guard of type i32 is rejected");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+}
+
+#[test]
+fn or_pattern_alternatives_cover_and_must_bind_the_same_names() {
+    let ok = "@m.orcov{T C=R|G|B;F g:(c:C)->i32=m(c){R|G=>1;B=>2;};F main:()->i32=0;}";
+    let program = parse_str(ok).expect("program should parse");
+    check_program(&program).expect("or-pattern plus B covers every colour");
+
+    let dead = "@m.ordead{T C=R|G|B;F g:(c:C)->i32=m(c){R|G=>1;B|G=>2;R=>3;};F main:()->i32=0;}";
+    let program = parse_str(dead).expect("program should parse");
+    let err = check_program(&program).expect_err("R is covered by the first arm");
+    assert_eq!(err.code, TypeErrorCode::UnreachableArm);
+
+    let mismatch =
+        "@m.orbind{T S=A(i32)|B(i32);F g:(s:S)->i32=m(s){A(x)|B(_)=>1;};F main:()->i32=0;}";
+    let program = parse_str(mismatch).expect("program should parse");
+    let err = check_program(&program).expect_err("only one alternative binds x");
+    assert_eq!(err.code, TypeErrorCode::InvalidPattern);
+    assert!(err.message.contains("same names"), "{}", err.message);
+}
+
+#[test]
+fn optional_ctors_infer_and_matches_must_cover_none() {
+    let ok = "@m.opt{F g:(o:?s)->s=m(o){Some(x)=>x;None=>\"\";};F h:()->?i32=None();F main:()->i32=c(unwrap_or,c(map,Some(1),l(x:i32):i32=x),0);}";
+    let program = parse_str(ok).expect("program should parse");
+    check_program(&program).expect("optional program should typecheck");
+
+    let partial = "@m.optp{F g:(o:?i32)->i32=m(o){Some(x)=>x;};F main:()->i32=0;}";
+    let program = parse_str(partial).expect("program should parse");
+    let err = check_program(&program).expect_err("None is not covered");
+    assert_eq!(err.code, TypeErrorCode::NonExhaustiveMatch);
+    assert!(err.message.contains("pattern `None`"), "{}", err.message);
+
+    let mixed = "@m.optm{F main:()->i32=c(unwrap_or,Some(\"x\"),0);}";
+    let program = parse_str(mixed).expect("program should parse");
+    let err = check_program(&program).expect_err("default must match the payload type");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+}
+
+#[test]
+fn try_operator_requires_a_result_returning_function_with_the_same_error() {
+    let ok = "@m.try{F r:(p:s)->s!s!{fs}=Ok(c(str_cat,c(read,p)?,\"!\"));F main:()->i32=0;}";
+    let program = parse_str(ok).expect("program should parse");
+    check_program(&program).expect("read errors are strings, like r's");
+
+    let plain = "@m.tryp{F r:(p:s)->s!{fs}=c(read,p)?;F main:()->i32=0;}";
+    let program = parse_str(plain).expect("program should parse");
+    let err = check_program(&program).expect_err("r does not return a result");
+    assert_eq!(err.code, TypeErrorCode::InvalidTry);
+    assert_eq!(err.code.as_str(), "E3016");
+
+    let other = "@m.tryo{F r:(p:s)->s!i32!{fs}=Ok(c(read,p)?);F main:()->i32=0;}";
+    let program = parse_str(other).expect("program should parse");
+    let err = check_program(&program).expect_err("s errors do not fit an i32 error type");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+}
+
+#[test]
+fn record_literals_and_field_access_are_checked_by_name() {
+    let ok = "@m.rec{T Pt={x:i32,y:i32};F sum:(p:Pt)->i32=c(+,p.x,p{y=1}.y);F main:()->i32=c(sum,Pt(y=2,x=1));}";
+    let program = parse_str(ok).expect("program should parse");
+    check_program(&program).expect("record program should typecheck");
+
+    let missing = "@m.recm{T Pt={x:i32,y:i32};F main:()->i32=v(p=Pt(x=1),0);}";
+    let program = parse_str(missing).expect("program should parse");
+    let err = check_program(&program).expect_err("y is not set");
+    assert_eq!(err.code, TypeErrorCode::InvalidField);
+    assert_eq!(err.code.as_str(), "E3017");
+    assert!(err.message.contains("missing field `y`"), "{}", err.message);
+
+    let unknown = "@m.recu{T Pt={x:i32,y:i32};F f:(p:Pt)->i32=p.z;F main:()->i32=0;}";
+    let program = parse_str(unknown).expect("program should parse");
+    let err = check_program(&program).expect_err("Pt has no z");
+    assert_eq!(err.code, TypeErrorCode::InvalidField);
+    assert!(err.message.contains("no field `z`"), "{}", err.message);
+
+    let wrong = "@m.recw{T Pt={x:i32,y:i32};F f:(p:Pt)->Pt=p{x=\"s\"};F main:()->i32=0;}";
+    let program = parse_str(wrong).expect("program should parse");
+    let err = check_program(&program).expect_err("x is an i32");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+
+    for repeated in [
+        "@m.recr{T Pt={x:i32,y:i32};F main:()->i32=v(p=Pt(x=1,y=2,x=3),0);}",
+        "@m.recur{T Pt={x:i32,y:i32};F main:()->i32=v(a=Pt(x=1,y=2),v(b=a{x=5,x=7},c(-,b.x,7)));}",
+    ] {
+        let program = parse_str(repeated).expect("program should parse");
+        let err = check_program(&program).expect_err("x is set twice");
+        assert_eq!(err.code, TypeErrorCode::InvalidField);
+        assert!(
+            err.message.contains("field `x` is set more than once"),
+            "{}",
+            err.message
+        );
+    }
+}
+
+#[test]
+fn qualified_references_resolve_through_import_aliases() {
+    let a = parse_str(
+        "@dep.a{E[Room,open,v];T Room=Room(i32);F open:(n:i32)->Room=Room(n);V v:i32=1;}",
+    )
+    .expect("a parses");
+    let b = parse_str("@dep.b{E[v];V v:s=\"b\";}").expect("b parses");
+    let main = parse_str("@main.app{:a=dep.a;:b=dep.b;:io=core.io;F keep:(r:a.Room)->a.Room=r;F main:()->i32!{io}=v(r=c(keep,c(a.open,a.v)),{c(io.println,b.v);0});}")
+        .expect("main parses");
+    check_programs(&[main, a.clone(), b.clone()]).expect("qualified names should typecheck");
+
+    let clash = parse_str("@main.clash{:a=dep.a;:b=dep.b;F main:()->i32=c(+,a.v,b.v);}")
+        .expect("clash parses");
+    let err = check_programs(&[clash, a.clone(), b.clone()]).expect_err("b.v is a string");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+
+    let hidden = parse_str("@dep.h{E[main];F main:()->i32=c(helper);F helper:()->i32=0;}")
+        .expect("hidden parses");
+    let user = parse_str("@main.user{:h=dep.h;F main:()->i32=c(h.helper);}").expect("user parses");
+    let err = check_programs(&[user, hidden.clone()]).expect_err("helper is not exported");
+    assert_eq!(err.code, TypeErrorCode::InvalidExport);
+    assert!(
+        err.message
+            .contains("`helper` is not exported by module `dep.h`"),
+        "{}",
+        err.message
+    );
+
+    let missing = parse_str("@main.miss{:a=dep.a;F main:()->i32=a.w;}").expect("missing parses");
+    let err = check_programs(&[missing, a.clone()]).expect_err("a has no w");
+    assert_eq!(err.code, TypeErrorCode::UnknownName);
+
+    let private_type = parse_str("@dep.p{E[mk];T Secret=Secret;F mk:()->Secret=Secret;}")
+        .expect("private type parses");
+    let user = parse_str("@main.pt{:p=dep.p;F f:(x:p.Secret)->i32=0;F main:()->i32=0;}")
+        .expect("user parses");
+    let err = check_programs(&[user, private_type]).expect_err("Secret is not exported");
+    assert_eq!(err.code, TypeErrorCode::InvalidExport);
+}
+
+#[test]
+fn qualified_builtins_keep_their_effects() {
+    let src = "@m.qio{:io=core.io;F main:()->i32=v(u=c(io.println,\"hi\"),0);}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("io.println needs io");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+
+    let src = "@m.qfs{:io=core.io;F main:()->i32!{io}=v(u=c(io.read,\"x\"),0);}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("read lives in core.fs");
+    assert_eq!(err.code, TypeErrorCode::UnknownName);
+}
+
+#[test]
+fn type_aliases_expand_in_signatures() {
+    let src = "@m.alias{A Step=(i32,i32)->i32;A Pair[X]=(X,X);F apply:(g:Step,p:Pair[i32])->i32=m(p){(a,b)=>c(g,a,b);};F main:()->i32=c(apply,l(a:i32,b:i32):i32=c(-,a,b),(2,2));}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("aliases should expand to their bodies");
+
+    let src = "@m.aliasw{A Pair[X]=(X,X);F f:(p:Pair[i32])->i32=m(p){(a,b)=>c(str_cat,a,b);};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("Pair[i32] holds ints");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+
+    let src = "@m.aliasc{A Loop=?Loop;F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("alias refers to itself");
+    assert_eq!(err.code, TypeErrorCode::InvalidTypeAlias);
+    assert_eq!(err.code.as_str(), "E3018");
+
+    let src = "@m.aliasa{A Pair[X]=(X,X);F f:(p:Pair)->i32=0;F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("Pair needs a type arg");
+    assert_eq!(err.code, TypeErrorCode::InvalidTypeAlias);
+}
+
+#[test]
+fn opaque_exports_hide_constructors_from_importers() {
+    let dep = parse_str("@dep.r{E[~Room,open,size,Shape,Ids];A Ids=(i32,i32);T Room=Room(i32);T Shape=Circle(i32)|Square(i32);F open:(n:i32)->Room=Room(n);F size:(r:Room)->i32=m(r){Room(n)=>n;};}")
+        .expect("dep parses");
+    let ok = parse_str("@main.ok{:d=dep.r;F area:(s:d.Shape)->i32=m(s){d.Circle(n)=>c(*,n,3);d.Square(n)=>c(*,n,n);};F keep:(r:d.Room,i:d.Ids)->d.Room=r;F main:()->i32=c(-,c(area,d.Square(2)),c(d.size,c(keep,d.open(4),(1,2))));}")
+        .expect("ok parses");
+    check_programs(&[ok, dep.clone()]).expect("transparent and opaque uses should typecheck");
+
+    let build =
+        parse_str("@main.build{:d=dep.r;F main:()->i32=c(d.size,d.Room(1));}").expect("parses");
+    let err = check_programs(&[build, dep.clone()]).expect_err("Room is opaque");
+    assert_eq!(err.code, TypeErrorCode::InvalidExport);
+    assert!(err.message.contains("opaque"), "{}", err.message);
+
+    let peek =
+        parse_str("@main.peek{:d=dep.r;F f:(r:d.Room)->i32=m(r){d.Room(n)=>n;};F main:()->i32=0;}")
+            .expect("parses");
+    let err = check_programs(&[peek, dep.clone()]).expect_err("Room cannot be matched");
+    assert_eq!(err.code, TypeErrorCode::InvalidExport);
+
+    let partial = parse_str(
+        "@main.part{:d=dep.r;F f:(s:d.Shape)->i32=m(s){d.Circle(n)=>n;};F main:()->i32=0;}",
+    )
+    .expect("parses");
+    let err = check_programs(&[partial, dep]).expect_err("Square is not covered");
+    assert_eq!(err.code, TypeErrorCode::NonExhaustiveMatch);
+
+    let leaky = parse_str("@dep.leak{E[~Box,Full];T Box=Full(i32)|Empty;}").expect("parses");
+    let err = check_programs(&[leaky]).expect_err("opaque type cannot export its ctor");
+    assert_eq!(err.code, TypeErrorCode::InvalidExport);
+}
+
+#[test]
+fn effect_variables_are_inferred_at_call_sites() {
+    let helpers = "T L=Nil|Cons(i32,L);F twice[A]:(g:(A)->A!{e},x:A)->A!{e}=c(g,c(g,x));F fold:(xs:L,acc:i32,g:(i32,i32)->i32!{e})->i32!{e}=m(xs){Nil=>acc;Cons(h,r)=>c(fold,r,c(g,acc,h),g);};";
+
+    let src = format!(
+        "@m.effpure{{{helpers}F main:()->i32=c(+,c(twice,l(n:i32):i32=c(+,n,1),1),c(fold,Cons(1,Nil()),0,+));}}"
+    );
+    let program = parse_str(&src).expect("program should parse");
+    check_program(&program).expect("pure arguments keep the call pure");
+
+    let src = format!(
+        "@m.effio{{{helpers}F main:()->i32!{{io}}=c(twice,l(n:i32):i32!{{io}}={{c(print,\"x\");n}},1);}}"
+    );
+    let program = parse_str(&src).expect("program should parse");
+    check_program(&program).expect("io argument makes the call need io");
+
+    let src = format!(
+        "@m.effleak{{{helpers}F main:()->i32=c(fold,Nil(),0,l(a:i32,b:i32):i32!{{io}}={{c(print,\"x\");a}});}}"
+    );
+    let program = parse_str(&src).expect("program should parse");
+    let err = check_program(&program).expect_err("io argument should reach the caller");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+
+    let two = "F two[A]:(g:(A)->A!{e},h:(A)->A!{e},x:A)->A!{e}=c(h,c(g,x));F sh:(n:i32)->i32!{io}={c(print,\"x\");n};";
+    for args in ["sh,l(n:i32):i32=n", "l(n:i32):i32=n,sh"] {
+        let src = format!("@m.efforder{{{two}F main:()->i32!{{io}}=c(two,{args},1);}}");
+        let program = parse_str(&src).expect("program should parse");
+        check_program(&program).unwrap_or_else(|err| panic!("{args}: {err}"));
+
+        let src = format!("@m.efforderpure{{{two}F main:()->i32=c(two,{args},1);}}");
+        let program = parse_str(&src).expect("program should parse");
+        let err = check_program(&program).expect_err("io reaches the caller in either order");
+        assert_eq!(err.code, TypeErrorCode::EffectViolation, "{args}");
+    }
+
+    let src = "@m.effmap{F main:()->i32=c(unwrap_or,c(map,Some(1),l(n:i32):i32!{io}={c(print,\"x\");n}),0);}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("builtin map propagates its callback's effects");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+}
+
+#[test]
+fn effect_variables_are_rigid_inside_their_function() {
+    let src = "@m.effbody{F run:(g:()->i32!{e})->i32!{e}={c(print,\"x\");c(g)};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("e does not cover io");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+
+    let src = "@m.effundeclared{F run:(g:()->i32!{e})->i32=c(g);F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("calling g needs e");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+}
+
+#[test]
+fn effect_variables_must_be_bound_by_a_parameter() {
+    for src in [
+        "@m.efftypo{F main:()->i32!{oi}=0;}",
+        "@m.effret{F mk:(n:i32)->(i32)->i32!{e}=l(x:i32):i32=n;F main:()->i32=0;}",
+    ] {
+        let program = parse_str(src).expect("program should parse");
+        let err = check_program(&program).expect_err("nothing binds the variable");
+        assert_eq!(err.code, TypeErrorCode::InvalidEffectSet);
+        assert!(
+            err.message.contains("unknown effect atom"),
+            "{}",
+            err.message
+        );
+    }
+
+    let src =
+        "@m.effnested{F run:(p:(()->i32!{e},i32))->()->i32!{e}=m(p){(g,_)=>g;};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("e is bound inside the tuple parameter");
+}
+
+#[test]
+fn less_effectful_functions_can_be_passed_for_effectful_params() {
+    let src = "@m.effsub{F run:(g:(i32)->i32!{io})->i32!{io}=c(g,1);F main:()->i32!{io}=c(run,l(n:i32):i32=n);}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("a pure lambda fits an io parameter");
+
+    let src = "@m.effsup{F run:(g:(i32)->i32)->i32=c(g,1);F main:()->i32!{io}=c(run,l(n:i32):i32!{io}={c(print,\"x\");n});}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("an io lambda does not fit a pure parameter");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+}
+
+#[test]
+fn recursive_lets_bind_every_name_in_every_lambda() {
+    let src = "@m.rec{F main:()->i32=w(ev=l(n:i32):b=i(c(==,n,0),t,c(od,c(-,n,1))),od=l(n:i32):b=i(c(==,n,0),f,c(ev,c(-,n,1))),i(c(ev,4),0,1));}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("mutually recursive lambdas should check");
+
+    let src = "@m.recbad{F main:()->i32=w(go=l(n:i32):i32=c(go,\"x\"),c(go,1));}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("go takes an i32");
+    assert_eq!(err.code, TypeErrorCode::TypeMismatch);
+
+    let src = "@m.recdup{F main:()->i32=w(go=l(n:i32):i32=n,go=l(n:i32):i32=n,c(go,1));}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("names in a group must differ");
+    assert_eq!(err.code, TypeErrorCode::DuplicateSymbol);
+
+    let src = "@m.recscope{F main:()->i32=c(+,w(go=l(n:i32):i32=n,c(go,1)),c(go,1));}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("go is local to the w body");
+    assert_eq!(err.code, TypeErrorCode::UnknownName);
+
+    assert!(parse_str("@m.recval{F main:()->i32=w(x=1,x);}").is_err());
+}
+
+#[test]
+fn ensures_only_see_names_in_scope_at_function_entry() {
+    let src = "@m.ensok{F f1:(n:i32)->i32={_ c(>,_r,n);c(+,n,1)};F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    check_program(&program).expect("ensures may use parameters and _r");
+
+    let src = "@m.ensscope{F f1:(n:i32)->i32=v(k=2,{_ c(>,_r,k);c(+,n,k)});F main:()->i32=0;}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("k is gone when f1 returns");
+    assert_eq!(err.code, TypeErrorCode::UnknownName);
+    assert!(err.message.contains("function exit"), "{}", err.message);
+}
+
+#[test]
+fn checker_reports_every_error_in_source_order() {
+    let src = "@m.multi{F a:(n:i32)->i32=c(+,n,\"x\");F b:()->s=c(nope,1);F main:()->i32=i(1,0,c(len,2));}";
+    let program = parse_str(src).expect("program should parse");
+    let errors = check_programs_all(std::slice::from_ref(&program)).expect_err("four errors");
+    let codes = errors.iter().map(|e| e.code.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        [
+            TypeErrorCode::TypeMismatch,
+            TypeErrorCode::UnknownName,
+            TypeErrorCode::TypeMismatch,
+            TypeErrorCode::TypeMismatch,
+        ]
+    );
+    assert!(
+        errors
+            .windows(2)
+            .all(|w| w[0].span.start <= w[1].span.start)
+    );
+
+    let err = check_program(&program).expect_err("first error is kept");
+    assert_eq!(err.span, errors[0].span);
+    let errors = check_program_with_modules(&program, std::slice::from_ref(&program))
+        .expect_err("per-module errors");
+    assert_eq!(errors.len(), 4);
+}
+
+#[test]
+fn failed_expressions_do_not_cascade() {
+    let src = "@m.cascade{T P={x:i32};F main:()->i32=v(p=c(nope,1),v(q=c(+,p,1),m(p){P(k)=>c(+,c(+,k,q),p.x);}));}";
+    let program = parse_str(src).expect("program should parse");
+    let errors = check_programs_all(std::slice::from_ref(&program)).expect_err("nope is unknown");
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].code, TypeErrorCode::UnknownName);
+
+    let src = "@m.arms{F main:()->i32=m(1){0=>c(nope,1);_=>c(+,\"x\",1);};}";
+    let program = parse_str(src).expect("program should parse");
+    let errors = check_programs_all(std::slice::from_ref(&program)).expect_err("two arms fail");
+    assert_eq!(errors.len(), 2, "{errors:?}");
+}
+
+#[test]
+fn unknown_names_suggest_close_matches_and_mismatches_label_their_origin() {
+    let src = "@m.hint{F twice:(n:i32)->i32=c(+,n,n);F main:()->i32=v(count=2,c(+,c(twise,cout),cuont));}";
+    let program = parse_str(src).expect("program should parse");
+    let errors = check_programs_all(std::slice::from_ref(&program)).expect_err("three typos");
+    let suggested = errors
+        .iter()
+        .map(|err| match err.notes.as_slice() {
+            [
+                Note::Suggestion {
+                    replacement, span, ..
+                },
+            ] => {
+                assert_eq!(*span, err.span);
+                replacement.as_str()
+            }
+            other => panic!("expected one suggestion, got {other:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(suggested, ["twice", "count", "count"]);
+
+    let program = parse_str("@m.far{F main:()->i32=zzz;}").expect("program should parse");
+    let err = check_program(&program).expect_err("unknown name");
+    assert!(err.notes.is_empty(), "{:?}", err.notes);
+
+    let src = "@m.lbl{F main:()->i32=i(t,1,\"no\");}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("branches differ");
+    let [Note::Label { span, message }] = err.notes.as_slice() else {
+        panic!("expected a label, got {:?}", err.notes);
+    };
+    assert_eq!(&src[span.start..span.end], "1");
+    assert_eq!(message, "the then branch has type i32");
+}
+
+// Applies the single `Note::Fix` of the first error in `src`.
+fn apply_first_fix(src: &str) -> (TypeErrorCode, String) {
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("program should fail");
+    let fixes = err
+        .notes
+        .iter()
+        .filter_map(|note| match note {
+            Note::Fix {
+                span, replacement, ..
+            } => Some((*span, replacement.as_str())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let [(span, replacement)] = fixes[..] else {
+        panic!("expected one fix, got {:?}", err.notes);
+    };
+    let fixed = format!("{}{replacement}{}", &src[..span.start], &src[span.end..]);
+    (err.code, fixed)
+}
+
+#[test]
+fn mechanical_errors_carry_fixes() {
+    let (code, fixed) = apply_first_fix("@m.ord{F main:()->i32!{fs,io,io}=0;}");
+    assert_eq!(code, TypeErrorCode::InvalidEffectSet);
+    assert_eq!(fixed, "@m.ord{F main:()->i32!{io,fs}=0;}");
+
+    let (code, fixed) = apply_first_fix("@m.eff{F main:()->i32!{fs}={c(print,\"x\");0};}");
+    assert_eq!(code, TypeErrorCode::EffectViolation);
+    assert_eq!(fixed, "@m.eff{F main:()->i32!{io,fs}={c(print,\"x\");0};}");
+
+    let (code, fixed) =
+        apply_first_fix("@m.lam{F main:()->i32!{io}=v(g=l(x:s):unit=c(print,x),0);}");
+    assert_eq!(code, TypeErrorCode::EffectViolation);
+    assert_eq!(
+        fixed,
+        "@m.lam{F main:()->i32!{io}=v(g=l(x:s):unit!{io}=c(print,x),0);}"
+    );
+
+    let (code, fixed) =
+        apply_first_fix("@m.arms{T C=Red|Green|Blue;F main:()->i32=m(Red()){Green=>1;};}");
+    assert_eq!(code, TypeErrorCode::NonExhaustiveMatch);
+    let program = parse_str(&fixed).expect("fixed program should parse");
+    check_program(&program).expect("missing arms should be added");
+    assert!(fixed.contains("Red=>{a(f,"), "{fixed}");
+
+    let (code, fixed) = apply_first_fix("@m.exp{E[gone,main];F main:()->i32=0;}");
+    assert_eq!(code, TypeErrorCode::InvalidExport);
+    assert_eq!(fixed, "@m.exp{E[main];F main:()->i32=0;}");
+    let (_, fixed) = apply_first_fix("@m.exp{E[gone];F main:()->i32=0;}");
+    assert_eq!(fixed, "@m.exp{F main:()->i32=0;}");
+
+    // Arms of a type with no literal end in a call that never returns.
+    for src in [
+        "@m.adt{T C=Red|Blue;F g:(c:C)->C=m(c){Red=>Blue();};F main:()->i32=0;}",
+        "@m.gen{T C=Red|Blue;F g[A]:(c:C,x:A)->(A,?A)=m(c){Red=>(x,Some(x));};F main:()->i32=0;}",
+        "@m.fun{T C=Red|Blue;F g:(c:C)->(i32)->i32!{io}=m(c){Red=>l(n:i32):i32=n;};F main:()->i32=0;}",
+    ] {
+        let (code, fixed) = apply_first_fix(src);
+        assert_eq!(code, TypeErrorCode::NonExhaustiveMatch);
+        let program = parse_str(&fixed).expect("fixed program should parse");
+        check_program(&program).expect("missing arms should be added");
+        assert!(fixed.contains("Blue=>{a(f,"), "{fixed}");
+    }
+
+    // A match with no arms has an unknown result type, so no fix.
+    let src = "@m.noarms{T C=Red|Blue;F main:()->i32=m(Red()){};}";
+    let program = parse_str(src).expect("program should parse");
+    let err = check_program(&program).expect_err("no arm covers anything");
+    assert_eq!(err.code, TypeErrorCode::NonExhaustiveMatch);
+    assert!(err.notes.is_empty(), "{:?}", err.notes);
+}
+
+#[test]
+fn broken_match_patterns_are_reported_without_coverage_checking() {
+    let cases = [
+        (
+            "@eq.match{$[];T Opt=No|Yes(i32);F main:()->i32=[m Yes(7) {Ys(x) x} {No 0}];}",
+            TypeErrorCode::InvalidPattern,
+        ),
+        (
+            "@eq.arity{F main:()->i32=m(c(parse,\"1\")){Ok()=>0;Er(e)=>1;};}",
+            TypeErrorCode::ArityMismatch,
+        ),
+        (
+            "@eq.tuple{F g:(x:?i32)->i32=m((1,y)){(_,Some(n))=>n;(_,None)=>0;};F main:()->i32=0;}",
+            TypeErrorCode::UnknownName,
+        ),
+    ];
+    for (src, code) in cases {
+        let program = parse_str(src).expect("program should parse");
+        let errors =
+            check_programs_all(std::slice::from_ref(&program)).expect_err("pattern is broken");
+        assert_eq!(errors[0].code, code, "{src}: {errors:?}");
+    }
+}
+
+#[test]
+fn branch_joins_widen_function_effects() {
+    let helpers = "F sh:(n:i32)->i32!{io}={c(print,\"x\");n};F wr:(n:i32)->i32!{fs}={c(write,\"p\",\"x\");n};";
+    for body in [
+        "v(k=l(n:i32):i32=n,v(h=i(t,k,sh),c(h,1)))",
+        "v(k=l(n:i32):i32=n,v(h=i(t,sh,k),c(h,1)))",
+        "v(k=l(n:i32):i32=n,v(h=m(1){0=>k;_=>sh;},c(h,1)))",
+    ] {
+        let src = format!("@m.join{{{helpers}F main:()->i32!{{io}}={body};}}");
+        let program = parse_str(&src).expect("program should parse");
+        check_program(&program).unwrap_or_else(|err| panic!("{body}: {err}"));
+
+        let src = format!("@m.joinpure{{{helpers}F main:()->i32={body};}}");
+        let program = parse_str(&src).expect("program should parse");
+        let err = check_program(&program).expect_err("calling the join needs io");
+        assert_eq!(err.code, TypeErrorCode::EffectViolation, "{body}");
+    }
+
+    let body = "v(h=i(t,sh,wr),c(h,1))";
+    let src = format!("@m.joinboth{{{helpers}F main:()->i32!{{io,fs}}={body};}}");
+    let program = parse_str(&src).expect("program should parse");
+    check_program(&program).expect("the join needs io and fs");
+    let src = format!("@m.joinio{{{helpers}F main:()->i32!{{io}}={body};}}");
+    let program = parse_str(&src).expect("program should parse");
+    let err = check_program(&program).expect_err("fs is not declared");
+    assert_eq!(err.code, TypeErrorCode::EffectViolation);
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use muc::bytecode::compile;
+use muc::parser::parse_str;
+use muc::vfs::{VfsHost, VirtualFs, normalize_path};
+use muc::vm::{FuzzHost, run_bytecode_with_fuel_and_host};
+
+fn run_with_vfs(src: &str, vfs: VirtualFs) -> (Result<(), String>, VirtualFs) {
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    let mut host = VfsHost::new(FuzzHost, vfs);
+    let result =
+        run_bytecode_with_fuel_and_host(&bc, &[], 100_000, &mut host).map_err(|e| e.to_string());
+    (result, host.vfs)
+}
+
+#[test]
+fn vfs_host_serves_reads_and_captures_writes() {
+    let vfs =
+        VirtualFs::from_json_manifest(r#"{"data/in.txt":"seed"}"#).expect("manifest should load");
+    let src = "@x.vfs{F main:()->i32!{fs}=m(c(read,\"./data/in.txt\")){Ok(s)=>{c(write,\"/data/out.txt\",c(str_cat,s,\"!\"));0};Er(_)=>1;};}";
+    let (result, vfs) = run_with_vfs(src, vfs);
+    result.expect("program should run against the vfs");
+    assert_eq!(vfs.get("data/out.txt"), Some("seed!"));
+    assert_eq!(vfs.snapshot().len(), 2);
+}
+
+#[test]
+fn vfs_host_reports_missing_files_as_er() {
+    let src = "@x.vfsmiss{F main:()->i32!{fs}=m(c(read,\"nope.txt\")){Ok(_)=>1;Er(_)=>0;};}";
+    let (result, _) = run_with_vfs(src, VirtualFs::new());
+    result.expect("missing file should surface as Er, not a trap");
+}
+
+#[test]
+fn vfs_paths_are_normalized_and_cannot_escape_root() {
+    assert_eq!(normalize_path("./a/b/../c.txt").as_deref(), Ok("a/c.txt"));
+    assert_eq!(normalize_path("/a//c.txt").as_deref(), Ok("a/c.txt"));
+    assert!(normalize_path("../secret").is_err());
+    assert!(normalize_path("a/..").is_err());
+}
+
+#[test]
+fn vfs_seeds_from_directory_and_roundtrips_manifest() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock should be after unix epoch")
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("muc_vfs_seed_{nanos}"));
+    fs::create_dir_all(dir.join("nested")).expect("temp dir should be created");
+    fs::write(dir.join("top.txt"), "1").expect("seed file should be written");
+    fs::write(dir.join("nested").join("inner.txt"), "2").expect("seed file should be written");
+
+    let vfs = VirtualFs::from_dir(&dir).expect("directory should seed the vfs");
+    assert_eq!(vfs.get("top.txt"), Some("1"));
+    assert_eq!(vfs.get("nested/inner.txt"), Some("2"));
+
+    let reloaded =
+        VirtualFs::from_json_manifest(&vfs.to_json_manifest()).expect("manifest should reload");
+    assert_eq!(reloaded, vfs);
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn vfs_manifest_rejects_non_string_contents() {
+    let err = VirtualFs::from_json_manifest(r#"{"a.txt":1}"#).expect_err("non-string must fail");
+    assert!(err.contains("must be a string"), "unexpected error: {err}");
+}
+
+#[test]
+fn vfs_host_supports_directory_and_append_operations() {
+    let src = "@x.vfsops{F main:()->i32!{fs}={c(mkdir,\"logs/old\");c(append,\"logs/a.txt\",\"x\\n\");c(append,\"logs/a.txt\",\"y\\n\");c(write,\"b.txt\",\"1\");c(remove,\"b.txt\");m(c(exists,\"logs\")){Ok(found)=>i(found,m(c(list_dir,\"logs\")){Ok(names)=>m(c(read_lines,\"logs/a.txt\")){Ok(lines)=>i(c(==,c(stringify,names),\"<array:2>\"),i(c(==,c(stringify,lines),\"<array:2>\"),0,5),4);Er(_)=>3;};Er(_)=>2;},6);Er(_)=>1;}};}";
+    let (result, vfs) = run_with_vfs(src, VirtualFs::new());
+    result.expect("fs operations should run against the vfs");
+    assert_eq!(vfs.get("logs/a.txt"), Some("x\ny\n"));
+    assert_eq!(vfs.get("b.txt"), None);
+    assert_eq!(
+        vfs.list_dir("logs"),
+        Ok(vec!["a.txt".to_string(), "old".to_string()])
+    );
+}
+
+#[test]
+fn vfs_list_dir_is_sorted_and_rejects_missing_directories() {
+    let mut vfs = VirtualFs::new();
+    vfs.insert("z.txt", String::new())
+        .expect("insert should succeed");
+    vfs.insert("a/inner.txt", String::new())
+        .expect("insert should succeed");
+    vfs.insert("m.txt", String::new())
+        .expect("insert should succeed");
+    assert_eq!(
+        vfs.list_dir("."),
+        Ok(vec![
+            "a".to_string(),
+            "m.txt".to_string(),
+            "z.txt".to_string()
+        ])
+    );
+    assert_eq!(vfs.exists("a"), Ok(true));
+    assert_eq!(vfs.exists("nope"), Ok(false));
+    assert!(vfs.list_dir("nope").is_err());
+    assert!(vfs.list_dir("z.txt").is_err());
+    assert!(vfs.create_dir("z.txt/sub").is_err());
+    assert!(vfs.remove("a").is_err());
+}
+
+#[test]
+fn vfs_writes_fail_under_a_file() {
+    let mut vfs = VirtualFs::new();
+    vfs.insert("f", "1".to_string())
+        .expect("insert should succeed");
+    let err = vfs
+        .insert("f/g", String::new())
+        .expect_err("f is a file, not a directory");
+    assert!(err.contains("`f` is a file"), "unexpected error: {err}");
+    assert!(vfs.append("f/g/h.txt", "x").is_err());
+    assert_eq!(vfs.get("f"), Some("1"));
+    assert_eq!(vfs.list_dir("."), Ok(vec!["f".to_string()]));
+    assert!(vfs.list_dir("f").is_err());
+}
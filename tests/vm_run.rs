@@ -1,4 +1,4 @@
-use muc::bytecode::compile;
+use muc::bytecode::{Profile, compile, compile_with_profile};
 use muc::parser::parse_str;
 use muc::vm::run_bytecode;
 
@@ -229,3 +229,269 @@ fn bytecode_runs_structural_equality_for_parsed_json_results() {
     let bc = compile(&program).expect("program should lower to bytecode");
     run_bytecode(&bc, &[]).expect("parsed JSON results should compare structurally");
 }
+
+#[test]
+fn user_functions_shadow_builtins_with_the_same_name() {
+    let src = "@x.shadow{F append:(s,s)->i32=7;F main:()->i32=c(-,c(append,\"a\",\"b\"),7);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("user `append` should be called, not the builtin");
+}
+
+#[test]
+fn local_closures_shadow_builtins_with_the_same_name() {
+    let src = "@x.shadowlocal{F main:()->i32=v(get=l(n:i32):i32=c(-,n,1),c(get,1));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("local `get` should be called, not the builtin");
+}
+
+#[test]
+fn real_host_fs_operations_run_against_disk() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock should be after unix epoch")
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("muc_fs_ops_{nanos}"));
+    let root = dir
+        .to_str()
+        .expect("temp path should be valid utf8")
+        .replace('\\', "/");
+    let src = format!(
+        "@x.fsops{{F main:()->i32!{{fs}}={{c(mkdir,\"{root}/sub\");c(append,\"{root}/log.txt\",\"a\\n\");c(append,\"{root}/log.txt\",\"b\\n\");c(write,\"{root}/gone.txt\",\"\");c(remove,\"{root}/gone.txt\");c(write,\"{root}/sub/want.txt\",\"log.txt\\nsub\");m((c(list_dir,\"{root}\"),c(read_lines,\"{root}/sub/want.txt\"))){{(Ok(names),Ok(want))=>i(c(==,names,want),0,2);_=>1;}}}};}}"
+    );
+    let program = parse_str(&src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("fs operations should succeed");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("log.txt")).expect("log should exist"),
+        "a\nb\n"
+    );
+    assert!(dir.join("sub").is_dir());
+    assert!(!dir.join("gone.txt").exists());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn named_params_lower_to_argument_slots() {
+    let src = "@x.named{F sub:(lhs:i32,rhs:i32)->i32=c(-,lhs,rhs);F main:()->i32=c(sub,5,5);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("named params should address argument slots");
+}
+
+#[test]
+fn comparison_orders_strings_bools_and_adts() {
+    let src = "@x.ord{T Rank=Low|Mid(i32)|High;F main:()->i32=i(c(and,c(and,c(<,\"apple\",\"banana\"),c(>,t,f)),c(and,c(and,c(<,Low(),Mid(0)),c(<,Mid(9),High())),c(and,c(<,Mid(1),Mid(2)),c(>=,Ok(3),Ok(3))))),0,1);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("ordering should follow the total order");
+}
+
+#[test]
+fn adt_order_follows_declaration_not_name() {
+    let src = "@x.ordecl{T Z=Zed|Alpha;F main:()->i32=i(c(<,Zed(),Alpha()),0,1);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("Zed is declared first so sorts first");
+}
+
+#[test]
+fn builtin_some_survives_a_declared_none() {
+    let src =
+        "@x.optshadow{T Mode=None|Fast;F main:()->i32=v(x=Some(3),c(-,m(x){Some(n)=>n;_=>0;},3));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("builtin Some should still lower");
+    run_bytecode(&bc, &[]).expect("Some(3) should match its own arm");
+}
+
+#[test]
+fn tuples_construct_and_destructure_in_let_and_match() {
+    let src = "@x.tup{F divmod:(n:i32,d:i32)->(i32,i32)=(c(/,n,d),c(%,n,d));F main:()->i32=v((q,r)=c(divmod,17,5),m((q,(r,\"x\"))){(a,(b,_))=>c(-,c(+,c(*,a,10),b),32);});}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("17 divmod 5 should be (3,2)");
+}
+
+#[test]
+fn tuple_fields_bind_inside_constructor_patterns() {
+    let src =
+        "@x.tupctor{T Box=Box((i32,i32));F main:()->i32=m(Box((4,4))){Box((x,y))=>c(-,x,y);};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("tuple field pattern should bind both items");
+}
+
+#[test]
+fn nested_constructor_patterns_lower_to_a_decision_tree() {
+    let src = "@x.nested{T L=Nil|Cons(i32,L);F sum2:(l:L)->i32=m(l){Cons(x,Cons(y,_))=>c(+,x,y);Cons(x,Nil)=>x;Nil=>0;};F main:()->i32=c(-,c(+,c(sum2,Cons(3,Cons(4,Cons(9,Nil())))),c(+,c(sum2,Cons(5,Nil())),c(sum2,Nil()))),12);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("nested patterns should lower");
+    run_bytecode(&bc, &[]).expect("sum of first two items should be 7, then 5 and 0");
+}
+
+#[test]
+fn int_string_and_tuple_literal_patterns_are_tested_in_order() {
+    let src = "@x.lits{F score:(p:(i32,s))->i32=m(p){(0,\"zero\")=>1;(0,_)=>2;(n,\"ten\")=>c(+,n,100);_=>4;};F main:()->i32=c(-,c(+,c(+,c(score,(0,\"zero\")),c(score,(0,\"x\"))),c(+,c(score,(10,\"ten\")),c(score,(7,\"y\")))),117);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("literal patterns should lower");
+    run_bytecode(&bc, &[]).expect("1 + 2 + 110 + 4 should be 117");
+}
+
+#[test]
+fn decision_tree_traps_when_no_nested_arm_matches() {
+    let src =
+        "@x.nestedbad{T L=Nil|Cons(i32,L);F main:()->i32=m(Cons(1,Nil())){Cons(2,_)=>0;Nil=>0;};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    let err = run_bytecode(&bc, &[]).expect_err("no arm matches Cons(1,Nil)");
+    assert!(err.to_string().contains("E4005"), "{err}");
+}
+
+#[test]
+fn guards_fall_through_to_later_arms() {
+    let src = "@x.guard{T L=Nil|Cons(i32,L);F cls:(l:L)->i32=m(l){Cons(x,_) if c(>,x,10)=>100;Cons(x,_) if c(>,x,0)=>x;Cons(_,_)=>0;Nil=>7;};F main:()->i32=c(-,c(+,c(+,c(cls,Cons(50,Nil())),c(cls,Cons(3,Nil()))),c(+,c(cls,Cons(0,Nil())),c(cls,Nil()))),110);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("guarded arms should lower");
+    run_bytecode(&bc, &[]).expect("100 + 3 + 0 + 7 should be 110");
+}
+
+#[test]
+fn or_patterns_share_one_arm_body_and_its_bindings() {
+    let src = "@x.orpat{T Sh=Sq(i32)|Rect(i32,i32)|Dot;F side:(s:Sh)->i32=m(s){Sq(n)|Rect(n,_)=>n;Dot=>0;};F small:(n:i32)->i32=m(n){1|2|3=>1;_=>0;};F main:()->i32=c(-,c(+,c(+,c(side,Sq(4)),c(side,Rect(5,9))),c(+,c(small,2),c(small,8))),10);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("or-patterns should lower");
+    run_bytecode(&bc, &[]).expect("4 + 5 + 1 + 0 should be 10");
+}
+
+#[test]
+fn optional_values_match_and_use_prelude_combinators() {
+    let src = "@x.opt{F first:(n:i32)->?i32=i(c(==,n,0),None(),Some(7));F get:(o:?i32)->i32=m(o){Some(n)=>n;None=>0;};F main:()->i32=v(a=c(map,Some(4),l(x:i32):i32=c(*,x,10)),v(b=c(map,c(first,0),l(x:i32):i32=c(+,x,1)),c(-,c(+,c(+,c(get,a),c(unwrap_or,b,5)),c(unwrap_or,c(first,1),0)),52)));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("optional values should lower");
+    run_bytecode(&bc, &[]).expect("40 + 5 + 7 should be 52");
+}
+
+#[test]
+fn try_operator_unwraps_ok_and_returns_er_early() {
+    let src = "@x.try{F half:(n:i32)->i32!s=i(c(==,c(%,n,2),0),Ok(c(/,n,2)),Er(\"odd\"));F quarter:(n:i32)->i32!s=Ok(c(+,100,c(half,c(half,n)?)?));F main:()->i32=m((c(quarter,8),c(quarter,6))){(Ok(102),Er(\"odd\"))=>0;_=>1;};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("try should lower");
+    run_bytecode(&bc, &[]).expect("8 -> Ok(102) and 6 -> Er(odd)");
+}
+
+#[test]
+fn records_construct_read_and_update_by_field_name() {
+    let src = "@x.rec{T Pt={x:i32,y:i32};T Tag={y:i32,name:s};F bump:(p:Pt)->Pt=p{y=c(+,p.y,10)};F main:()->i32=v(p=c(bump,Pt(y=2,x=5)),v(g=Tag(name=\"n\",y=100),v(u=g{name=\"m\"},m((p.x,p.y,u.y,u.name)){(5,12,100,\"m\")=>0;_=>1;})));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("records should lower");
+    run_bytecode(&bc, &[]).expect("record fields should read back");
+}
+
+#[test]
+fn qualified_builtin_calls_lower_like_bare_ones() {
+    let src = "@x.qual{:io=core.io;:p=core.prelude;F main:()->i32!{io}={c(io.println,\"qualified\");c(-,c(p.len,\"abc\"),3)};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("qualified builtins should lower");
+    run_bytecode(&bc, &[]).expect("len(abc) - 3 should be 0");
+}
+
+#[test]
+fn type_aliases_are_erased_before_lowering() {
+    let src = "@x.alias{A Step=(i32,i32)->i32;A Pair[X]=(X,X);F apply:(g:Step,p:Pair[i32])->i32=m(p){(a,b)=>c(g,a,b);};F main:()->i32=c(apply,l(a:i32,b:i32):i32=c(-,a,b),(5,5));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("aliases should lower");
+    run_bytecode(&bc, &[]).expect("5 - 5 should be 0");
+}
+
+#[test]
+fn effect_polymorphic_helpers_run_with_pure_and_effectful_arguments() {
+    let src = "@x.effpoly{F twice[A]:(g:(A)->A!{e},x:A)->A!{e}=c(g,c(g,x));F main:()->i32!{io}=v(n=c(twice,l(k:i32):i32=c(+,k,1),1),c(-,c(twice,l(k:i32):i32!{io}={c(print,\"x\");c(+,k,n)},0),6));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower");
+    run_bytecode(&bc, &[]).expect("twice adds n twice");
+}
+
+#[test]
+fn local_recursive_functions_call_themselves_and_each_other() {
+    let src = "@x.rec{F main:()->i32=v(k=10,w(ev=l(n:i32):b=i(c(==,n,0),t,c(od,c(-,n,1))),od=l(n:i32):b=i(c(==,n,0),f,c(ev,c(-,n,1))),sum=l(n:i32):i32=i(c(==,n,0),k,c(+,n,c(sum,c(-,n,1)))),i(c(and,c(ev,10),c(od,7)),c(-,c(sum,4),20),1)));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("recursive lets should lower");
+    run_bytecode(&bc, &[]).expect("sum(4) from k=10 should be 20");
+}
+
+#[test]
+fn nested_lambdas_get_their_own_function_ids() {
+    let src = "@x.nested_lambda{F main:()->i32=v(g=l(a:i32):i32=v(h=l(b:i32):i32=c(+,a,b),c(h,1)),c(-,c(g,2),3));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("nested lambdas should lower");
+    run_bytecode(&bc, &[]).expect("inner closure should capture a");
+}
+
+#[test]
+fn ensures_bind_the_returned_value() {
+    let src = "@x.ens{F abs:(n:i32)->i32={_ c(>=,_r,0);i(c(<,n,0),c(neg,n),n)};F main:()->i32=c(-,c(abs,c(neg,3)),3);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("ensures using _r should lower");
+    run_bytecode(&bc, &[]).expect("abs result is non-negative");
+
+    let src = "@x.ensbad{F bump:(n:i32)->i32={_ c(>,_r,n);n};F main:()->i32=c(bump,1);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower");
+    let err = run_bytecode(&bc, &[]).expect_err("bump does not bump");
+    let msg = err.to_string();
+    assert!(msg.contains("E4002"), "{msg}");
+    assert!(msg.contains("ensure `c(>,_r,n)` failed in `bump`"), "{msg}");
+}
+
+#[test]
+fn ensures_run_on_early_returns_from_try() {
+    let src = "@x.enstry{F check:(n:i32)->i32!s=i(c(==,c(%,n,2),0),Ok(n),Er(\"odd\"));F half:(n:i32)->i32!s={_ m(_r){Ok(_)=>t;Er(e)=>c(==,e,\"fine\");};v(x=c(check,n)?,Ok(c(/,x,2)))};F main:()->i32=m(c(half,3)){Ok(_)=>0;Er(_)=>1;};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower");
+    let err = run_bytecode(&bc, &[]).expect_err("the early Er return should fail the ensure");
+    let msg = err.to_string();
+    assert!(msg.contains("E4002") && msg.contains("`half`"), "{msg}");
+}
+
+#[test]
+fn release_builds_drop_requires_and_ensures_but_keep_asserts() {
+    let src = "@x.rel{F pos:(n:i32)->i32={^c(>,n,0);_ c(>,_r,n);n};F main:()->i32=c(pos,0);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("debug build should lower");
+    let err = run_bytecode(&bc, &[]).expect_err("debug builds check the require");
+    assert!(err.to_string().contains("E4002"), "{err}");
+    let bc = compile_with_profile(&program, Profile::Release).expect("release build should lower");
+    run_bytecode(&bc, &[]).expect("release builds skip contracts");
+
+    let src = "@x.relassert{F main:()->i32={a(c(==,1,2));0};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile_with_profile(&program, Profile::Release).expect("release build should lower");
+    run_bytecode(&bc, &[]).expect_err("asserts stay in release builds");
+}
+
+#[test]
+fn generic_comparison_runs_and_traps_on_functions() {
+    let src = "@x.mx{F mx[A]:(a:A,b:A)->A=i(c(>,a,b),a,b);F main:()->i32=i(c(==,c(mx,\"a\",\"b\"),\"b\"),c(mx,c(-,0,1),0),1);}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    run_bytecode(&bc, &[]).expect("a generic max should pick the larger value");
+
+    let src = "@x.mxf{F mx[A]:(a:A,b:A)->A=i(c(>,a,b),a,b);F main:()->i32=v(id=l(x:i32):i32=x,v(g=c(mx,id,id),c(g,0)));}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    let err = run_bytecode(&bc, &[]).expect_err("functions are not comparable");
+    assert!(
+        err.to_string().contains("cannot compare function values"),
+        "{err}"
+    );
+}
+
+#[test]
+fn placeholder_match_arm_traps_before_its_non_returning_call() {
+    let src = "@x.ph{T C=Red|Blue;F g:(c:C)->C=m(c){Red=>Blue();Blue=>{a(f,\"unhandled match arm\");w(u=l(x:i32):C=c(u,x),c(u,0))};};F main:()->i32=m(c(g,Blue())){_=>0;};}";
+    let program = parse_str(src).expect("program should parse");
+    let bc = compile(&program).expect("program should lower to bytecode");
+    let err = run_bytecode(&bc, &[]).expect_err("the placeholder arm should trap");
+    assert!(err.to_string().contains("E4001"), "{err}");
+}